@@ -0,0 +1,111 @@
+//! Base64 encoding, used to save binary values as text.
+//!
+//! It uses the standard alphabet with padding, like the `base64`
+//! command line tool, so saved values can be decoded by hand.
+
+const ALPHABET: &'static [u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encode some bytes.
+///
+/// # Example
+///
+/// ```rust
+/// use slate::base64;
+///
+/// assert_eq!("c2xhdGU=", base64::encode(b"slate"));
+/// ```
+pub fn encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity((bytes.len() + 2) / 3 * 4);
+
+    for chunk in bytes.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as usize) << 16 | (b[1] as usize) << 8 | b[2] as usize;
+
+        encoded.push(ALPHABET[n >> 18 & 63] as char);
+        encoded.push(ALPHABET[n >> 12 & 63] as char);
+        encoded.push(if chunk.len() > 1 { ALPHABET[n >> 6 & 63] as char } else { '=' });
+        encoded.push(if chunk.len() > 2 { ALPHABET[n & 63] as char } else { '=' });
+    }
+
+    encoded
+}
+
+/// Decode a text, ignoring white space.
+///
+/// It returns `None` if the text is not valid base64.
+///
+/// # Example
+///
+/// ```rust
+/// use slate::base64;
+///
+/// assert_eq!(Some(b"slate".to_vec()), base64::decode("c2xhdGU="));
+/// assert_eq!(None, base64::decode("c2x*"));
+/// ```
+pub fn decode(text: &str) -> Option<Vec<u8>> {
+    let text: Vec<u8> = text.bytes().filter(|b| !(*b as char).is_whitespace()).collect();
+    if text.len() % 4 != 0 {
+        return None;
+    }
+
+    let mut decoded = Vec::with_capacity(text.len() / 4 * 3);
+    for (index, chunk) in text.chunks(4).enumerate() {
+        let last = index == text.len() / 4 - 1;
+        let padding = chunk.iter().rev().take_while(|&&b| b == b'=').count();
+        if padding > 2 || (padding > 0 && !last) {
+            return None;
+        }
+
+        let mut n = 0;
+        for &b in &chunk[..4 - padding] {
+            let value = match ALPHABET.iter().position(|&a| a == b) {
+                Some(value) => value,
+                None => return None,
+            };
+            n = n << 6 | value;
+        }
+        n <<= 6 * padding;
+
+        decoded.push((n >> 16) as u8);
+        if padding < 2 {
+            decoded.push((n >> 8 & 255) as u8);
+        }
+        if padding < 1 {
+            decoded.push((n & 255) as u8);
+        }
+    }
+
+    Some(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_encodes_and_decodes_bytes() {
+        let cases: &[(&[u8], &str)] = &[(b"", ""),
+                                        (b"f", "Zg=="),
+                                        (b"fo", "Zm8="),
+                                        (b"foo", "Zm9v"),
+                                        (b"foob", "Zm9vYg=="),
+                                        (b"\x00\xff\xfe\x80", "AP/+gA==")];
+
+        for &(bytes, text) in cases {
+            assert_eq!(text, encode(bytes));
+            assert_eq!(Some(bytes.to_vec()), decode(text));
+        }
+    }
+
+    #[test]
+    fn it_rejects_invalid_text() {
+        assert_eq!(None, decode("Zm9"));
+        assert_eq!(None, decode("Zg==Zg=="));
+        assert_eq!(None, decode("Z==="));
+    }
+
+    #[test]
+    fn it_ignores_white_space() {
+        assert_eq!(Some(b"foob".to_vec()), decode("Zm9v\nYg==\n"));
+    }
+}
@@ -1,10 +1,28 @@
 extern crate slate;
 
 use std::env;
-use slate::cli;
+use std::io::{self, IsTerminal};
+use std::process;
+use slate::cli::{self, Streams};
+use slate::config::Config;
 
 pub fn main() {
     let argv: Vec<String> = env::args().collect();
+    let config = Config::from_env();
 
-    cli::run(argv);
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let stderr = io::stderr();
+    let terminal = stdout.is_terminal();
+    let code = cli::run(argv,
+                        &config,
+                        &mut Streams {
+                            stdin: &mut stdin.lock(),
+                            stdout: &mut stdout.lock(),
+                            stderr: &mut stderr.lock(),
+                            terminal: terminal,
+                            inherit_stdin: true,
+                        });
+
+    process::exit(code);
 }
@@ -1,81 +1,366 @@
 use docopt;
+use serde_json;
 use serde::de::Deserialize;
-use std::process;
+use std::io::{BufRead, Write};
 
 use command;
-use errors::CommandError;
+use hooks;
+use plugins;
+#[cfg(unix)]
+use daemon;
+use errors::{CommandError, EXIT_IO};
 use results::CommandResult;
-use message::Message;
+use message::{Format, Message};
 use config::Config;
+use highlight::{Color, Theme};
 use Slate;
 
-const USAGE: &'static str = "
+pub const USAGE: &'static str = "
 Slate: Manage your snippets from your command line.
 
 Note that Slate will use the file ~/.slate to save
 its contents.
 
 Usage:
-  slate <command> [<args>...]
+  slate [--output <format>] [--color <when>] <command> [<args>...]
   slate [options]
 
 Options:
-  -h --help      Show this screen.
-  -v --version   Show version.
+  -h --help             Show this screen.
+  -v --version          Show version.
+  -o --output <format>  Output format: text, json or ndjson [default: text].
+  --color <when>        Highlight values: auto, always or never [default: auto].
+  --list-plugins        List the external commands found in the PATH.
 
 Commands:
    set     Write a new key and value.
    get     Read a key.
+   append  Add text at the end of a value.
+   prepend Add text at the beginning of a value.
+   show    Show a key with its details.
    list    List all keys.
    rename  Rename a key.
+   alias   Give another name to a key.
+   copy    Copy keys, in the store or into another one.
+   move    Move keys into another profile or store.
    remove  Delete a key.
+   search  Find keys by their values.
+   find    Find keys by their names.
+   link    Link a directory of files as read-only keys.
+   migrate Copy all keys into a different backend.
+   batch   Apply operations from stdin at once.
+   serve   Serve the store to other programs.
+   http    Serve the store with a REST API.
+   watch   Print the changes of the store as they happen.
+   gc      Remove the expired keys.
+   recent  List the last used keys.
+   stats   Show statistics about the keys.
+   doctor  Check the store for problems.
+   dedupe  Find and merge keys with the same values.
+   diff    Show the differences between values or stores.
+   completions  Print the completion script for a shell.
+
+Any other command is run with the executable slate-<command>
+found in the PATH, if it exists. It gets the store to use
+in the SLATE_FILEPATH, SLATE_BACKEND and SLATE_PROFILE env vars.
+
+With --color auto values are only highlighted in terminals,
+and never if the NO_COLOR env var is set. The colors are
+set with the SLATE_THEME env var: default, light, mono or a
+list like keyword=1;35,comment=2.
+
+Exit codes:
+   0  Success.
+   1  Usage error: invalid arguments or options.
+   2  The key doesn't exist.
+   3  The key already exists.
+   4  The store is corrupted.
+   5  I/O or storage error.
+   6  The store is locked by another process.
+   7  A hook rejected the change.
+   8  slate doctor found problems.
 ";
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug)]
 enum Command {
     Set,
+    Append,
+    Prepend,
     Get,
+    Show,
     List,
     Remove,
     Rename,
+    Alias,
+    Copy,
+    Move,
+    Search,
+    Find,
+    Link,
+    Migrate,
+    Batch,
+    Serve,
+    Http,
+    Watch,
+    Gc,
+    Recent,
+    Stats,
+    Doctor,
+    Dedupe,
+    Diff,
+    Completions,
+    Complete,
 }
 
 impl Command {
-    fn run(self, slate: &Slate, argv: &Vec<String>) -> CommandResult {
+    /// Get the command with the given name, if it is not external.
+    fn from_name(name: &str) -> Option<Command> {
+        let command = match name {
+            "set" => Command::Set,
+            "append" => Command::Append,
+            "prepend" => Command::Prepend,
+            "get" => Command::Get,
+            "show" => Command::Show,
+            "list" => Command::List,
+            "remove" => Command::Remove,
+            "rename" => Command::Rename,
+            "alias" => Command::Alias,
+            "copy" => Command::Copy,
+            "move" => Command::Move,
+            "search" => Command::Search,
+            "find" => Command::Find,
+            "link" => Command::Link,
+            "migrate" => Command::Migrate,
+            "batch" => Command::Batch,
+            "serve" => Command::Serve,
+            "http" => Command::Http,
+            "watch" => Command::Watch,
+            "gc" => Command::Gc,
+            "recent" => Command::Recent,
+            "stats" => Command::Stats,
+            "doctor" => Command::Doctor,
+            "dedupe" => Command::Dedupe,
+            "diff" => Command::Diff,
+            "completions" => Command::Completions,
+            "__complete" => Command::Complete,
+            _ => return None,
+        };
+
+        Some(command)
+    }
+
+    /// Whether the command can work through a running daemon.
+    fn uses_daemon(&self) -> bool {
+        match *self {
+            // The doctor checks the files of the store.
+            Command::Serve | Command::Doctor => false,
+            _ => true,
+        }
+    }
+
+    fn run(self,
+           slate: &Slate,
+           argv: &Vec<String>,
+           streams: &mut Streams,
+           format: Format,
+           theme: Option<&Theme>)
+           -> CommandResult {
         match self {
-            Command::Set => command::set::run(slate, argv),
-            Command::Get => command::get::run(slate, argv),
-            Command::List => command::list::run(slate, argv),
-            Command::Remove => command::remove::run(slate, argv),
-            Command::Rename => command::rename::run(slate, argv),
+            Command::Set => command::set::run(slate, argv, streams),
+            Command::Append => command::append::run(slate, argv, streams),
+            Command::Prepend => command::prepend::run(slate, argv, streams),
+            Command::Get => command::get::run(slate, argv, streams),
+            Command::Show => command::show::run(slate, argv, streams, format, theme),
+            Command::List => command::list::run(slate, argv, streams),
+            Command::Remove => command::remove::run(slate, argv, streams),
+            Command::Rename => command::rename::run(slate, argv, streams),
+            Command::Alias => command::alias::run(slate, argv, streams),
+            Command::Copy => command::copy::run(slate, argv, streams),
+            Command::Move => command::mv::run(slate, argv, streams),
+            Command::Search => command::search::run(slate, argv, streams),
+            Command::Find => command::find::run(slate, argv, streams),
+            Command::Link => command::link::run(slate, argv, streams),
+            Command::Migrate => command::migrate::run(slate, argv, streams),
+            Command::Batch => command::batch::run(slate, argv, streams),
+            Command::Serve => command::serve::run(slate, argv, streams),
+            Command::Http => command::http::run(slate, argv, streams),
+            Command::Watch => command::watch::run(slate, argv, streams, format),
+            Command::Gc => command::gc::run(slate, argv, streams),
+            Command::Recent => command::recent::run(slate, argv, streams),
+            Command::Stats => command::stats::run(slate, argv, streams),
+            Command::Doctor => command::doctor::run(slate, argv, streams, format),
+            Command::Dedupe => command::dedupe::run(slate, argv, streams),
+            Command::Diff => command::diff::run(slate, argv, streams, theme),
+            Command::Completions => command::completions::run(slate, argv, streams),
+            Command::Complete => command::complete::run(slate, argv, streams),
         }
     }
 }
 
+/// Input and output streams used by the program.
+///
+/// The binary uses the standard ones, but any reader and
+/// writers can be used, for example to test the commands.
+pub struct Streams<'a> {
+    pub stdin: &'a mut BufRead,
+    pub stdout: &'a mut Write,
+    pub stderr: &'a mut Write,
+    /// Whether stdout is a terminal, where values can be highlighted.
+    pub terminal: bool,
+    /// Whether `stdin` is the stdin of the process, so the programs
+    /// it runs, like plugins, can read it directly.
+    pub inherit_stdin: bool,
+}
+
 #[derive(Debug, Deserialize)]
 struct Args {
-    arg_command: Command,
+    arg_command: String,
+    arg_args: Vec<String>,
+    flag_output: String,
+    flag_color: String,
+    flag_list_plugins: bool,
     flag_help: bool,
     flag_version: bool,
 }
 
+#[derive(Serialize)]
+struct ErrorReport<'a> {
+    error: ErrorDetails<'a>,
+}
+
+#[derive(Serialize)]
+struct ErrorDetails<'a> {
+    code: &'a str,
+    message: String,
+}
+
 /// Main entry point of the command.
 ///
-/// It executes the given command and handles its output and errors.
-pub fn run(argv: Vec<String>) {
-    let args: Args = parse_main_args(USAGE, &argv).unwrap_or_else(|e| e.exit());
+/// It executes the given command with the configuration, handles
+/// its output and errors using the streams, and returns the exit
+/// code of the program.
+///
+/// # Example
+///
+/// ```rust
+/// use slate::cli::{self, Streams};
+/// use slate::config::Config;
+/// use std::env;
+/// use std::io;
+///
+/// let mut temp = env::temp_dir();
+/// temp.push(".slate");
+///
+/// let config = Config { filepath: temp, ..Default::default() };
+/// let argv = vec!["slate".to_string(), "list".to_string()];
+/// let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+/// let code = cli::run(argv, &config, &mut Streams {
+///   stdin: &mut io::empty(),
+///   stdout: &mut stdout,
+///   stderr: &mut stderr,
+///   terminal: false,
+///   inherit_stdin: false,
+/// });
+///
+/// assert_eq!(0, code);
+/// ```
+pub fn run(argv: Vec<String>, config: &Config, streams: &mut Streams) -> i32 {
+    let args: Args = match parse_main_args(USAGE, &argv) {
+        Ok(args) => args,
+        Err(e) => return error(From::from(e), Format::Text, streams),
+    };
 
-    let command = args.arg_command;
+    let format: Format = match args.flag_output.parse() {
+        Ok(format) => format,
+        Err(e) => return error(CommandError::Argument(e), Format::Text, streams),
+    };
 
-    let config = Config::from_env();
-    let slate: Slate = From::from(&config);
+    let color: Color = match args.flag_color.parse() {
+        Ok(color) => color,
+        Err(e) => return error(CommandError::Argument(e), format, streams),
+    };
+    let theme: Option<Theme> = if color.enabled(streams.terminal) {
+        // A broken theme shouldn't stop every command.
+        match config.theme.parse() {
+            Ok(theme) => Some(theme),
+            Err(e) => {
+                let _ = writeln!(streams.stderr, "Warning: {}, using the default theme", e);
+                Some(Theme::default())
+            }
+        }
+    } else {
+        None
+    };
+
+    if args.flag_list_plugins {
+        let found: Vec<String> = plugins::list(&plugins::search_paths())
+            .iter()
+            .map(|&(ref name, ref path)| format!("{}\t{}", name, path.display()))
+            .collect();
+        return out(Some(Message::Info(found.join("\n"))), format, None, streams);
+    }
 
-    match command.run(&slate, &argv) {
-        Err(e) => error(e),
-        Ok(message) => out(message),
+    let command = match Command::from_name(&args.arg_command) {
+        Some(command) => command,
+        None => return external(&args, config, format, streams),
     };
+
+    // Subcommands only see their own arguments, without global options.
+    let start = argv.len() - args.arg_args.len() - 1;
+    let mut subargv = vec![argv[0].clone()];
+    subargv.extend_from_slice(&argv[start..]);
+
+    let mut config = config.clone();
+    if command.uses_daemon() {
+        connect(&mut config);
+    }
+    let output: hooks::Output = Default::default();
+    config.hook_output = Some(output.clone());
+    let slate: Slate = From::from(&config);
+
+    let result = command.run(&slate, &subargv, streams, format, theme.as_ref());
+    if let Ok(output) = output.lock() {
+        let _ = streams.stderr.write_all(&output);
+    }
+
+    match result {
+        Err(e) => error(e, format, streams),
+        Ok(message) => out(message, format, theme.as_ref(), streams),
+    }
+}
+
+/// Run an external command, or fail if there is none.
+fn external(args: &Args, config: &Config, format: Format, streams: &mut Streams) -> i32 {
+    if args.arg_command.is_empty() {
+        return error(CommandError::Argument(USAGE.trim().to_string()), format, streams);
+    }
+
+    match plugins::find(&args.arg_command, &plugins::search_paths()) {
+        Some(executable) => {
+            match plugins::run(&executable, &args.arg_args, config, streams) {
+                Ok(code) => code,
+                Err(e) => error(e, format, streams),
+            }
+        }
+        None => {
+            let message = format!("Unknown command: {}", args.arg_command);
+            error(CommandError::Argument(message), format, streams)
+        }
+    }
 }
 
+/// Use the daemon serving the store, if there is one running.
+#[cfg(unix)]
+fn connect(config: &mut Config) {
+    let socket = daemon::socket_path(config);
+    if daemon::is_running(&socket) {
+        config.socket = Some(socket);
+    }
+}
+
+#[cfg(not(unix))]
+fn connect(_config: &mut Config) {}
+
 /// Parse arguments based on a USAGE slice string.
 ///
 /// This is used mainly by subcommands.
@@ -101,16 +386,58 @@ fn parse_main_args<'a, T>(usage: &str, argv: &Vec<String>) -> Result<T, docopt::
     })
 }
 
-/// Show errors to the user.
-fn error(err: CommandError) {
-    println!("{}", err);
-    process::exit(1);
+/// Show errors to the user in stderr, and get the exit code.
+///
+/// With machine readable formats the error is written
+/// as a JSON object. Requests for help or the version are
+/// not errors, so they are shown in stdout.
+fn error(err: CommandError, format: Format, streams: &mut Streams) -> i32 {
+    if let CommandError::Usage(ref e) = err {
+        if !e.fatal() {
+            let _ = writeln!(streams.stdout, "{}", e);
+            return 0;
+        }
+    }
+
+    let _ = match format {
+        Format::Text => writeln!(streams.stderr, "{}", err),
+        Format::Json | Format::Ndjson => {
+            let report = ErrorReport {
+                error: ErrorDetails {
+                    code: err.kind(),
+                    message: format!("{}", err),
+                },
+            };
+            match serde_json::to_string(&report) {
+                Ok(encoded) => writeln!(streams.stderr, "{}", encoded),
+                Err(_) => writeln!(streams.stderr, "{}", err),
+            }
+        }
+    };
+
+    err.exit_code()
 }
 
-/// Show program messages to the user.
-fn out(message: Option<Message>) {
+/// Show program messages to the user, highlighted with the theme
+/// if there is one, and get the exit code.
+fn out(message: Option<Message>,
+       format: Format,
+       theme: Option<&Theme>,
+       streams: &mut Streams)
+       -> i32 {
     if let Some(msg) = message {
-        print!("{}", msg);
+        if let (&Message::Bytes { ref bytes, .. }, Format::Text) = (&msg, format) {
+            return if streams.stdout.write_all(bytes).is_err() { EXIT_IO } else { 0 };
+        }
+
+        let rendered = match theme {
+            Some(theme) => msg.highlight(format, theme),
+            None => msg.render(format),
+        };
+        if write!(streams.stdout, "{}", rendered).is_err() {
+            return EXIT_IO;
+        }
     };
-    process::exit(0);
+
+    0
 }
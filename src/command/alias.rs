@@ -0,0 +1,46 @@
+use cli::{parse_args, Streams};
+use Slate;
+use message::{Change, Message};
+use results::CommandResult;
+
+pub const USAGE: &'static str = "
+Slate: Give another name to a key.
+
+Usage:
+  slate alias [--force] <alias> <target>
+  slate alias --help
+
+The alias has the value of the target, which can be another
+alias, wherever a value is read, like in `slate get`. Renaming
+the target changes its aliases, and removing it leaves them
+dangling. Aliases that point to each other are rejected.
+
+Options:
+  -h --help   Show this help.
+  -f --force  Overwrite the alias if it already exists.
+
+Examples:
+
+  slate alias pods k8s/pods
+  #=> The alias has been created
+
+  slate get pods
+  #=> kubectl get pods -A
+";
+
+#[derive(Debug, Deserialize)]
+struct Args {
+    arg_alias: String,
+    arg_target: String,
+    flag_force: bool,
+}
+
+pub fn run(slate: &Slate, argv: &Vec<String>, _streams: &mut Streams) -> CommandResult {
+    let args: Args = try!(parse_args(USAGE, argv));
+
+    try!(slate.alias(&args.arg_alias, &args.arg_target, args.flag_force));
+
+    let change = Change::new("alias").key(&args.arg_alias).from(&args.arg_target);
+
+    Ok(Some(Message::Change(change, Some("The alias has been created".to_string()))))
+}
@@ -0,0 +1,43 @@
+use cli::{parse_args, Streams};
+use Slate;
+use command::set;
+use message::{Change, Message};
+use results::CommandResult;
+
+pub const USAGE: &'static str = "
+Slate: Add text at the end of a value.
+
+Usage:
+  slate append <key> [<text>]
+  slate append --help
+
+If <text> is not present, <stdin> will be used. The text is added
+in a new line, and the key is created if it doesn't exist.
+
+Options:
+  -h --help  Show this help.
+
+Examples:
+
+  slate append hosts web-3.example.com
+
+  echo '- [ ] Update the docs' | slate append release
+";
+
+#[derive(Debug, Deserialize)]
+struct Args {
+    arg_key: String,
+    arg_text: Option<String>,
+}
+
+pub fn run(slate: &Slate, argv: &Vec<String>, streams: &mut Streams) -> CommandResult {
+    let args: Args = try!(parse_args(USAGE, argv));
+
+    let text = match args.arg_text {
+        Some(text) => text,
+        None => try!(set::input(streams)),
+    };
+    try!(slate.append(&args.arg_key, &text));
+
+    Ok(Some(Message::Change(Change::new("append").key(&args.arg_key), None)))
+}
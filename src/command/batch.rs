@@ -0,0 +1,151 @@
+use std::io::BufRead;
+use cli::{parse_args, Streams};
+use Slate;
+use message::{Change, Message};
+use results::CommandResult;
+use errors::CommandError;
+
+pub const USAGE: &'static str = "
+Slate: Apply several operations read from <stdin> at once.
+
+Usage:
+  slate batch
+  slate batch [options]
+
+Each line of <stdin> is an operation:
+
+  set <key> <value>         The value is the rest of the line.
+  remove <key>
+  rename <old> <new> [--force]
+
+Empty lines and lines starting with # are ignored. The store is
+read and written only once, and if any operation fails nothing
+is saved.
+
+Options:
+  -h --help  Show this help.
+
+Examples:
+
+  printf 'set foo bar\\nrename foo baz\\n' | slate batch
+  #=> 2 operations applied
+";
+
+#[derive(Debug, Deserialize)]
+struct Args;
+
+#[derive(Debug, PartialEq)]
+enum Operation {
+    Set(String, String),
+    Remove(String),
+    Rename(String, String, bool),
+}
+
+pub fn run(slate: &Slate, argv: &Vec<String>, streams: &mut Streams) -> CommandResult {
+    let _args: Args = try!(parse_args(USAGE, argv));
+
+    let mut operations = Vec::new();
+    for (index, line) in streams.stdin.lines().enumerate() {
+        let line = try!(line);
+        if let Some(operation) = try!(parse(&line, index + 1)) {
+            operations.push(operation);
+        }
+    }
+
+    try!(slate.transaction(|tx| {
+        for operation in &operations {
+            match *operation {
+                Operation::Set(ref key, ref value) => tx.set(key, value),
+                Operation::Remove(ref key) => tx.remove(key),
+                Operation::Rename(ref old, ref new, force) => try!(tx.rename(old, new, force)),
+            }
+        }
+        Ok(())
+    }));
+
+    let total = operations.len();
+    let change = Change::new("batch").count(total);
+
+    Ok(Some(Message::Change(change, Some(format!("{} operations applied", total)))))
+}
+
+/// Parse one line of the input.
+///
+/// It returns `None` for empty lines and comments.
+fn parse(line: &str, number: usize) -> Result<Option<Operation>, CommandError> {
+    let line = line.trim_left().trim_right_matches(|c| c == '\r' || c == '\n');
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+
+    let invalid = || CommandError::Argument(format!("Invalid operation at line {}: {}", number, line));
+
+    let (name, rest) = split_word(line);
+    let operation = match name {
+        "set" => {
+            let (key, value) = split_word(rest);
+            if key.is_empty() {
+                return Err(invalid());
+            }
+            Operation::Set(key.to_string(), value.to_string())
+        }
+        "remove" => {
+            let words: Vec<&str> = rest.split_whitespace().collect();
+            if words.len() != 1 {
+                return Err(invalid());
+            }
+            Operation::Remove(words[0].to_string())
+        }
+        "rename" => {
+            let words: Vec<&str> = rest.split_whitespace().collect();
+            match words.as_slice() {
+                [old, new] => Operation::Rename(old.to_string(), new.to_string(), false),
+                [old, new, "--force"] | [old, new, "-f"] => {
+                    Operation::Rename(old.to_string(), new.to_string(), true)
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        _ => return Err(invalid()),
+    };
+
+    Ok(Some(operation))
+}
+
+/// Split the first word of a line, separated by a single space,
+/// from the rest of it.
+fn split_word(line: &str) -> (&str, &str) {
+    let mut parts = line.splitn(2, ' ');
+    let word = parts.next().unwrap_or("");
+    let rest = parts.next().unwrap_or("");
+
+    (word, rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_operations() {
+        assert_eq!(Operation::Set("foo".to_string(), "bar baz ".to_string()),
+                   parse("set foo bar baz ", 1).unwrap().unwrap());
+        assert_eq!(Operation::Remove("foo".to_string()),
+                   parse("remove foo", 1).unwrap().unwrap());
+        assert_eq!(Operation::Rename("a".to_string(), "b".to_string(), true),
+                   parse("rename a b --force", 1).unwrap().unwrap());
+    }
+
+    #[test]
+    fn it_skips_empty_lines_and_comments() {
+        assert_eq!(None, parse("", 1).unwrap());
+        assert_eq!(None, parse("  # set foo bar", 1).unwrap());
+    }
+
+    #[test]
+    fn it_fails_with_invalid_operations() {
+        assert!(parse("get foo", 1).is_err());
+        assert!(parse("remove", 1).is_err());
+        assert!(parse("rename a", 1).is_err());
+    }
+}
@@ -0,0 +1,23 @@
+use cli::{parse_args, Streams};
+use Slate;
+use message::Message;
+use results::CommandResult;
+
+pub const USAGE: &'static str = "
+Slate: List the keys for shell completions.
+
+Usage:
+  slate __complete [<args>...]
+";
+
+#[derive(Debug, Deserialize)]
+struct Args;
+
+pub fn run(slate: &Slate, argv: &Vec<String>, _streams: &mut Streams) -> CommandResult {
+    let _args: Args = try!(parse_args(USAGE, argv));
+    let mut list = try!(slate.list());
+    list.extend(try!(slate.linked()).into_iter().map(|linked| linked.key));
+    list.sort();
+
+    Ok(Some(Message::Keys(list)))
+}
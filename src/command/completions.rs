@@ -0,0 +1,43 @@
+use cli::{self, parse_args, Streams};
+use Slate;
+use command;
+use completions::{self, Shell, Subcommand};
+use message::Message;
+use results::CommandResult;
+
+pub const USAGE: &'static str = "
+Slate: Print the completion script for a shell.
+
+Usage:
+  slate completions <shell>
+  slate completions [options]
+
+Shells are `bash`, `zsh` and `fish`. Keys are completed with
+the ones in the current store.
+
+Options:
+  -h --help  Show this help.
+
+Examples:
+
+  source <(slate completions bash)
+
+  slate completions fish > ~/.config/fish/completions/slate.fish
+";
+
+#[derive(Debug, Deserialize)]
+struct Args {
+    arg_shell: String,
+}
+
+pub fn run(_slate: &Slate, argv: &Vec<String>, _streams: &mut Streams) -> CommandResult {
+    let args: Args = try!(parse_args(USAGE, argv));
+    let shell: Shell = try!(args.arg_shell.parse());
+
+    let subcommands: Vec<Subcommand> = command::USAGES.iter()
+        .map(|&(name, usage)| Subcommand::new(name, usage, command::KEYS.contains(&name)))
+        .collect();
+    let global = completions::options(cli::USAGE);
+
+    Ok(Some(Message::Raw(completions::script(shell, &global, &subcommands))))
+}
@@ -0,0 +1,160 @@
+use std::path::PathBuf;
+use cli::{parse_args, Streams};
+use Slate;
+use config::Config;
+use message::{Change, Message};
+use results::CommandResult;
+use errors::{CommandError, SlateError};
+use store;
+use transfer::{self, Mode};
+
+pub const USAGE: &'static str = "
+Slate: Copy keys, in the same store or into another one.
+
+Usage:
+  slate copy [--force] <src> <dst>
+  slate copy [--force] (--to-profile <profile> | --to-store <path>) <key>...
+  slate copy --help
+
+The source is kept, with its attributes and history. In the same
+store, <src> can be a pattern with * and ?, and then <dst> is a
+prefix added to each of the keys that match it. Keys can also be
+patterns when they are copied into another profile, or another
+store file.
+
+Nothing is copied if any key already exists where it is going to
+be saved, unless --force is given.
+
+Options:
+  -h --help               Show this help.
+  -f --force              Overwrite the keys that already exist.
+  -p --to-profile <name>  Copy into the store of another profile.
+  -s --to-store <path>    Copy into another store file.
+
+Examples:
+
+  slate copy deploy deploy-staging
+  #=> The key has been copied
+
+  slate copy 'k8s-*' old/
+  #=> 3 keys copied
+
+  slate copy --to-profile work vpn
+  #=> 1 key copied to the profile work
+";
+
+#[derive(Debug, Deserialize)]
+struct Args {
+    arg_src: Option<String>,
+    arg_dst: Option<String>,
+    arg_key: Vec<String>,
+    flag_force: bool,
+    flag_to_profile: Option<String>,
+    flag_to_store: Option<String>,
+}
+
+pub fn run(slate: &Slate, argv: &Vec<String>, _streams: &mut Streams) -> CommandResult {
+    let args: Args = try!(parse_args(USAGE, argv));
+
+    if let Some(destination) = destination(slate, &args.flag_to_profile, &args.flag_to_store) {
+        return between(slate, &try!(destination), &args.arg_key, Mode::Copy, args.flag_force);
+    }
+
+    let (src, dst) = match (args.arg_src, args.arg_dst) {
+        (Some(src), Some(dst)) => (src, dst),
+        _ => {
+            let message = "You must provide the source and the destination".to_string();
+            return Err(CommandError::Argument(message));
+        }
+    };
+
+    if !store::is_pattern(&src) {
+        try!(slate.copy(&src, &dst, args.flag_force).map_err(|err| not_found(slate, err)));
+        let change = Change::new("copy").key(&dst).from(&src);
+
+        return Ok(Some(Message::Change(change, Some("The key has been copied".to_string()))));
+    }
+
+    let keys = try!(slate.list());
+    let selected = try!(store::select(&keys, &[src]).map_err(|err| not_found(slate, err)));
+    let conflicts: Vec<String> = selected.iter()
+        .map(|key| format!("{}{}", dst, key))
+        .filter(|key| keys.contains(key))
+        .collect();
+    if !conflicts.is_empty() && !args.flag_force {
+        return Err(CommandError::Conflicts(conflicts));
+    }
+    for key in &selected {
+        try!(slate.copy(key, &format!("{}{}", dst, key), true));
+    }
+
+    let total = selected.len();
+
+    Ok(Some(Message::Change(Change::new("copy").count(total), Some(keys_text(total, "copied")))))
+}
+
+/// Configuration of the store chosen with `--to-profile` or
+/// `--to-store`, if any.
+pub fn destination(slate: &Slate,
+                   profile: &Option<String>,
+                   path: &Option<String>)
+                   -> Option<Result<Config, CommandError>> {
+    let config = match (profile, path) {
+        (&Some(ref profile), _) => slate.config.for_profile(profile),
+        (_, &Some(ref path)) => slate.config.for_file(PathBuf::from(path)),
+        _ => return None,
+    };
+
+    if transfer::same_file(&config.filepath, &slate.config.filepath) {
+        return Some(Err(CommandError::Argument("The destination is the current store".to_string())));
+    }
+
+    Some(Ok(config))
+}
+
+/// Copy or move keys, or patterns, into another store.
+pub fn between(slate: &Slate,
+               config: &Config,
+               patterns: &Vec<String>,
+               mode: Mode,
+               force: bool)
+               -> CommandResult {
+    let keys = try!(store::select(&try!(slate.list()), patterns).map_err(|err| not_found(slate, err)));
+    let destination = Slate { config: config };
+
+    let result = try!(transfer::transfer(slate, &destination, &keys, mode, force));
+    if !result.conflicts.is_empty() && !force {
+        return Err(CommandError::Conflicts(result.conflicts));
+    }
+
+    let (action, done) = match mode {
+        Mode::Copy => ("copy", "copied"),
+        Mode::Move => ("move", "moved"),
+    };
+    let path = format!("{}", config.filepath.display());
+    let place = if config.profile != slate.config.profile {
+        format!("the profile {}", config.profile)
+    } else {
+        path.clone()
+    };
+    let text = format!("{} to {}", keys_text(result.keys.len(), done), place);
+
+    Ok(Some(Message::Change(Change::new(action).count(result.keys.len()).path(&path), Some(text))))
+}
+
+/// Missing keys are reported with a suggestion, like in `get`.
+fn not_found(slate: &Slate, err: SlateError) -> CommandError {
+    match err {
+        SlateError::NotFound(key) => {
+            let similar = slate.suggest(&key).unwrap_or(None);
+            CommandError::NotFound(key, similar)
+        }
+        err => From::from(err),
+    }
+}
+
+fn keys_text(total: usize, done: &str) -> String {
+    let plural = if total == 1 { "" } else { "s" };
+
+    format!("{} key{} {}", total, plural, done)
+}
@@ -0,0 +1,190 @@
+use serde_json;
+use cli::{parse_args, Streams};
+use Slate;
+use dedupe::Group;
+use diff;
+use message::{Change, Message};
+use results::CommandResult;
+use errors::{CommandError, SlateError};
+
+pub const USAGE: &'static str = "
+Slate: Find keys with the same or similar values.
+
+Usage:
+  slate dedupe [--similarity <ratio>] [--merge]
+  slate dedupe --help
+
+Keys with the same value are joined with =, and keys with
+similar values with ~, followed by how similar they are. The
+similarity goes from 0 to 1, where 1 means equal.
+
+When merging, each group is shown asking which key to keep, and
+whether to turn the others into aliases of it or to delete them.
+Similar values are shown as a diff against the kept one first.
+Aliases of deleted keys point to the kept one. Press Enter to
+skip a group.
+
+Options:
+  -h --help                Show this help.
+  -s --similarity <ratio>  Lowest similarity of near duplicates [default: 0.9].
+  -m --merge               Merge the groups, one by one.
+
+Examples:
+
+  slate dedupe
+  #=> k8s/pods = pods
+  #=> deploy-prod ~ deploy-staging (93%)
+
+  slate dedupe --merge
+  #=> 1) k8s/pods
+  #=> 2) pods
+  #=> Keep which key? [1-2] 1
+  #=> (a)lias the others, (d)elete them, or (s)kip? a
+  #=> 1 key turned into an alias
+";
+
+#[derive(Debug, Deserialize)]
+struct Args {
+    flag_similarity: String,
+    flag_merge: bool,
+}
+
+#[derive(Serialize)]
+struct Row<'a> {
+    keys: &'a Vec<String>,
+    similarity: f64,
+    exact: bool,
+}
+
+pub fn run(slate: &Slate, argv: &Vec<String>, streams: &mut Streams) -> CommandResult {
+    let args: Args = try!(parse_args(USAGE, argv));
+    let similarity: f64 = match args.flag_similarity.parse() {
+        Ok(ratio) if ratio >= 0.0 && ratio <= 1.0 => ratio,
+        _ => {
+            let message = format!("Invalid similarity: {}, it must be between 0 and 1",
+                                  args.flag_similarity);
+            return Err(CommandError::Argument(message));
+        }
+    };
+
+    let groups = try!(slate.duplicates(similarity));
+
+    if args.flag_merge {
+        return merge(slate, groups, streams);
+    }
+
+    let mut rows = Vec::new();
+    for group in &groups {
+        let row = Row {
+            keys: &group.keys,
+            similarity: group.similarity,
+            exact: group.is_exact(),
+        };
+        rows.push(try!(serde_json::to_value(&row).map_err(SlateError::from)));
+    }
+    let text: Vec<String> = groups.iter().map(describe).collect();
+
+    Ok(Some(Message::Rows {
+        name: "duplicates",
+        rows: rows,
+        text: text.join("\n"),
+    }))
+}
+
+/// The keys of a group, like `a = b` or `a ~ b (93%)`.
+fn describe(group: &Group) -> String {
+    if group.is_exact() {
+        group.keys.join(" = ")
+    } else {
+        format!("{} ({:.0}%)",
+                group.keys.join(" ~ "),
+                (group.similarity * 100.0).floor())
+    }
+}
+
+/// Ask how to merge every group, and merge them. The questions are
+/// written in stderr, so stdout only gets the result.
+fn merge(slate: &Slate, groups: Vec<Group>, streams: &mut Streams) -> CommandResult {
+    let mut removed: Vec<String> = Vec::new();
+    let mut aliased = 0;
+
+    for group in groups {
+        // Keys of previous groups may be gone.
+        let keys: Vec<String> = group.keys
+            .iter()
+            .filter(|key| !removed.contains(key))
+            .cloned()
+            .collect();
+        if keys.len() < 2 {
+            continue;
+        }
+
+        try!(writeln!(streams.stderr, "{}", describe(&Group { keys: keys.clone(), ..group.clone() })));
+        for (number, key) in keys.iter().enumerate() {
+            try!(writeln!(streams.stderr, "  {}) {}", number + 1, key));
+        }
+
+        let answer = try!(ask(streams, &format!("Keep which key? [1-{}] ", keys.len())));
+        let keep = match answer.parse::<usize>() {
+            Ok(number) if number >= 1 && number <= keys.len() => number - 1,
+            _ => continue,
+        };
+        let kept = keys[keep].clone();
+        if !group.is_exact() {
+            let value = try!(slate.entry(&kept)).value;
+            for key in keys.iter().filter(|key| **key != kept) {
+                let other = try!(slate.entry(key)).value;
+                try!(write!(streams.stderr, "{}", diff::unified(&value, &other, &kept, key)));
+            }
+        }
+        let answer = try!(ask(streams, "(a)lias the others, (d)elete them, or (s)kip? "));
+        let others = keys.into_iter().enumerate().filter(|&(index, _)| index != keep).map(|(_, key)| key);
+        match answer.as_str() {
+            "a" => {
+                for key in others {
+                    try!(slate.alias(&key, &kept, true));
+                    aliased += 1;
+                }
+            }
+            "d" => {
+                let aliases = try!(slate.aliases());
+                for key in others {
+                    try!(slate.remove(&key));
+                    for &(ref alias, ref target) in &aliases {
+                        if target == &key {
+                            try!(slate.alias(alias, &kept, true));
+                        }
+                    }
+                    removed.push(key);
+                }
+            }
+            _ => continue,
+        }
+    }
+
+    let plural = |count: usize| if count == 1 { "" } else { "s" };
+    let mut done = Vec::new();
+    if !removed.is_empty() {
+        done.push(format!("{} key{} removed", removed.len(), plural(removed.len())));
+    }
+    if aliased > 0 {
+        let aliases = if aliased == 1 { "an alias" } else { "aliases" };
+        done.push(format!("{} key{} turned into {}", aliased, plural(aliased), aliases));
+    }
+    let text = if done.is_empty() { "Nothing was merged".to_string() } else { done.join(", ") };
+    let change = Change::new("dedupe").count(removed.len() + aliased);
+
+    Ok(Some(Message::Change(change, Some(text))))
+}
+
+/// Ask a question, and get the answer without spaces. The answer is
+/// empty at the end of the input.
+fn ask(streams: &mut Streams, question: &str) -> Result<String, CommandError> {
+    try!(write!(streams.stderr, "{}", question));
+    try!(streams.stderr.flush());
+
+    let mut answer = String::new();
+    try!(streams.stdin.read_line(&mut answer));
+
+    Ok(answer.trim().to_lowercase())
+}
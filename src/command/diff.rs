@@ -0,0 +1,203 @@
+use serde_json;
+use std::path::PathBuf;
+use cli::{parse_args, Streams};
+use Slate;
+use diff::{self, Changes};
+use message::Message;
+use results::CommandResult;
+use errors::{CommandError, SlateError};
+use highlight::{paint, Kind, Theme};
+use store::{self, Entry};
+
+pub const USAGE: &'static str = "
+Slate: Show the differences between values or stores.
+
+Usage:
+  slate diff <key> <other>
+  slate diff --rev <number> <key>
+  slate diff --store <path> [<key>]
+  slate diff --help
+
+Values are compared by lines and shown as a unified diff, with
+colors in terminals. Like `diff -u`, a last line without a
+newline is followed by a `\\ No newline at end of file` line.
+A revision of a key is compared with its current value: 1 is the
+first value it had, and `slate show` tells how many revisions
+there are.
+
+Another store, like the export of a teammate, is compared listing
+the keys that it adds (+), removes (-) or changes (~). When a key
+is given, its values in both stores are compared. The other
+store is only read, it is never created or upgraded.
+
+Options:
+  -h --help         Show this help.
+  --rev <number>    Revision of the key to compare.
+  --store <path>    Other store to compare, a JSON or SQLite file.
+
+Examples:
+
+  slate diff deploy-prod deploy-staging
+  #=> --- deploy-prod
+  #=> +++ deploy-staging
+  #=> @@ -1 +1 @@
+  #=> -make deploy ENV=prod
+  #=> \\ No newline at end of file
+  #=> +make deploy ENV=staging
+  #=> \\ No newline at end of file
+
+  slate diff --store team.json
+  #=> + k8s/nodes
+  #=> ~ deploy-prod
+";
+
+#[derive(Debug, Deserialize)]
+struct Args {
+    arg_key: Option<String>,
+    arg_other: Option<String>,
+    flag_rev: Option<String>,
+    flag_store: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Values<'a> {
+    from: &'a str,
+    to: &'a str,
+    diff: &'a str,
+}
+
+pub fn run(slate: &Slate,
+           argv: &Vec<String>,
+           _streams: &mut Streams,
+           theme: Option<&Theme>)
+           -> CommandResult {
+    let args: Args = try!(parse_args(USAGE, argv));
+
+    if let Some(path) = args.flag_store {
+        let path = PathBuf::from(path);
+        if !path.is_file() {
+            return Err(CommandError::Argument(format!("The store {} doesn't exist", path.display())));
+        }
+        let config = slate.config.for_file(path.clone());
+        let other: Slate = From::from(&config);
+
+        return match args.arg_key {
+            Some(key) => {
+                let from = try!(slate.entry(&key));
+                let store = try!(store::open_read_only(&config));
+                let to = match try!(store::follow(&*store, &key)) {
+                    (_, Some(entry)) => entry,
+                    (chain, None) => {
+                        let last = chain[chain.len() - 1].clone();
+                        return Err(From::from(SlateError::NotFound(last)));
+                    }
+                };
+                values(&from, &to, &key, &format!("{} ({})", key, path.display()), theme)
+            }
+            None => stores(try!(diff::stores(slate, &other)), theme),
+        };
+    }
+
+    let key = args.arg_key.unwrap_or_default();
+    let current = try!(slate.entry(&key));
+
+    match (args.flag_rev, args.arg_other) {
+        (Some(rev), _) => {
+            let revisions = try!(slate.history(&key));
+            let revision = match rev.parse::<usize>() {
+                Ok(number) if number >= 1 && number <= revisions.len() => &revisions[number - 1],
+                _ => {
+                    let message = format!("Invalid revision: {}, {} has {} revisions",
+                                          rev,
+                                          key,
+                                          revisions.len());
+                    return Err(CommandError::Argument(message));
+                }
+            };
+            let from = Entry { value: revision.value.clone(), ..current.clone() };
+
+            values(&from, &current, &format!("{} (revision {})", key, rev), &key, theme)
+        }
+        (None, Some(other)) => {
+            let to = try!(slate.entry(&other));
+            values(&current, &to, &key, &other, theme)
+        }
+        (None, None) => Err(CommandError::Argument(USAGE.trim().to_string())),
+    }
+}
+
+/// Compare two values.
+fn values(from: &Entry, to: &Entry, from_name: &str, to_name: &str, theme: Option<&Theme>) -> CommandResult {
+    let unified = if from.is_binary() || to.is_binary() {
+        if from.value == to.value {
+            String::new()
+        } else {
+            "Binary values differ".to_string()
+        }
+    } else {
+        diff::unified(&from.value, &to.value, from_name, to_name)
+    };
+
+    let text = if unified.is_empty() {
+        "The values are equal".to_string()
+    } else {
+        match theme {
+            Some(theme) => colorize(&unified, theme),
+            None => unified.clone(),
+        }
+    };
+    let record = Values {
+        from: from_name,
+        to: to_name,
+        diff: &unified,
+    };
+    let record = try!(serde_json::to_value(&record).map_err(SlateError::from));
+
+    Ok(Some(Message::Record(record, text)))
+}
+
+/// List the keys that differ between two stores.
+fn stores(changes: Changes, theme: Option<&Theme>) -> CommandResult {
+    let mut lines = Vec::new();
+    let groups = [("+", &changes.added, Kind::Added),
+                  ("-", &changes.removed, Kind::Removed),
+                  ("~", &changes.changed, Kind::Plain)];
+    for &(sign, keys, kind) in &groups {
+        for key in keys.iter() {
+            let line = format!("{} {}", sign, key);
+            lines.push(match theme {
+                Some(theme) => paint(&line, kind, theme),
+                None => line,
+            });
+        }
+    }
+    let text = if changes.is_empty() {
+        "The stores have the same keys and values".to_string()
+    } else {
+        lines.join("\n")
+    };
+    let record = try!(serde_json::to_value(&changes).map_err(SlateError::from));
+
+    Ok(Some(Message::Record(record, text)))
+}
+
+/// Color the lines of a unified diff.
+fn colorize(unified: &str, theme: &Theme) -> String {
+    let lines: Vec<String> = unified.lines()
+        .enumerate()
+        .map(|(index, line)| {
+            let kind = if index < 2 || line.starts_with("@@") || line.starts_with('\\') {
+                Kind::Comment
+            } else if line.starts_with('+') {
+                Kind::Added
+            } else if line.starts_with('-') {
+                Kind::Removed
+            } else {
+                Kind::Plain
+            };
+            paint(line, kind, theme)
+        })
+        .collect();
+
+    lines.join("\n")
+}
@@ -0,0 +1,114 @@
+use serde_json;
+use cli::{parse_args, Streams};
+use Slate;
+use doctor;
+use message::{Format, Message};
+use results::CommandResult;
+use errors::{CommandError, SlateError};
+
+pub const USAGE: &'static str = "
+Slate: Check the store for problems.
+
+Usage:
+  slate doctor [--fix]
+  slate doctor --help
+
+These are checked:
+
+  format       The store can be read by its backend, and a
+               SQLite store has the format of this version.
+  permissions  The store and its history can only be read by
+               their owner.
+  orphans      There are no locks or temporary files left by
+               processes that didn't finish, no history or usage
+               of keys that don't exist, and the linked
+               directories exist.
+
+The exit code is 8 if there are problems left.
+
+Options:
+  -h --help  Show this help.
+  --fix      Fix the problems that can be fixed.
+
+Examples:
+
+  slate doctor
+  #=> permissions: /home/user/.slate can be read by other users (mode 644)
+  #=> Found 1 problem, 1 can be fixed with --fix
+
+  slate doctor --fix
+  #=> permissions: /home/user/.slate can be read by other users (mode 644) (fixed)
+";
+
+#[derive(Debug, Deserialize)]
+struct Args {
+    flag_fix: bool,
+}
+
+#[derive(Serialize)]
+struct Row<'a> {
+    check: &'a str,
+    message: &'a str,
+    fixable: bool,
+    fixed: bool,
+}
+
+pub fn run(slate: &Slate,
+           argv: &Vec<String>,
+           streams: &mut Streams,
+           format: Format)
+           -> CommandResult {
+    let args: Args = try!(parse_args(USAGE, argv));
+
+    let problems = doctor::check(slate.config);
+    if problems.is_empty() {
+        return Ok(Some(Message::Info("No problems found".to_string())));
+    }
+
+    let mut rows = Vec::new();
+    let mut lines = Vec::new();
+    let mut left = 0;
+    let mut fixable = 0;
+    for problem in &problems {
+        let mut suffix = String::new();
+        let fixed = args.flag_fix && problem.fix.is_some() &&
+                    match doctor::fix(slate.config, problem) {
+            Ok(_) => true,
+            Err(e) => {
+                suffix = format!(" (cannot be fixed: {})", e);
+                false
+            }
+        };
+        if fixed {
+            suffix = " (fixed)".to_string();
+        } else {
+            left += 1;
+            if problem.fix.is_some() {
+                fixable += 1;
+            }
+        }
+
+        lines.push(format!("{}: {}{}", problem.check, problem.message, suffix));
+        rows.push(try!(serde_json::to_value(&Row {
+                check: problem.check,
+                message: &problem.message,
+                fixable: problem.fix.is_some(),
+                fixed: fixed,
+            })
+            .map_err(SlateError::from)));
+    }
+
+    let report = Message::Rows {
+        name: "problems",
+        rows: rows,
+        text: lines.join("\n"),
+    };
+    if left == 0 {
+        return Ok(Some(report));
+    }
+
+    // The report is shown even if the command fails.
+    try!(write!(streams.stdout, "{}", report.render(format)));
+
+    Err(CommandError::Problems(left, fixable))
+}
@@ -0,0 +1,52 @@
+use cli::{parse_args, Streams};
+use Slate;
+use command::list;
+use results::CommandResult;
+use store;
+
+pub const USAGE: &'static str = "
+Slate: Find keys by their names.
+
+Usage:
+  slate find <pattern>
+  slate find --help
+
+Keys are found if their names contain <pattern>, ignoring the
+case, or if they match it when it has the wildcards * or ?. The
+keys of linked directories are found too.
+
+Options:
+  -h --help  Show this help.
+
+Examples:
+
+  slate find deploy
+  #=> deploy-staging
+  #=> deploy/prod  [snippets]
+
+  slate find 'k8s-*'
+  #=> k8s-nodes
+  #=> k8s-pods
+";
+
+#[derive(Debug, Deserialize)]
+struct Args {
+    arg_pattern: String,
+}
+
+pub fn run(slate: &Slate, argv: &Vec<String>, _streams: &mut Streams) -> CommandResult {
+    let args: Args = try!(parse_args(USAGE, argv));
+    let pattern = args.arg_pattern;
+
+    let lowercase = pattern.to_lowercase();
+    let found = |key: &String| if store::is_pattern(&pattern) {
+        store::matches(&pattern, key)
+    } else {
+        key.to_lowercase().contains(&lowercase)
+    };
+
+    let keys = try!(slate.list()).into_iter().filter(|key| found(key)).collect();
+    let linked = try!(slate.linked()).into_iter().filter(|linked| found(&linked.key)).collect();
+
+    Ok(Some(try!(list::layered(keys, linked, &try!(slate.aliases()), true))))
+}
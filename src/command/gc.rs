@@ -0,0 +1,34 @@
+use cli::{parse_args, Streams};
+use Slate;
+use message::{Change, Message};
+use results::CommandResult;
+
+pub const USAGE: &'static str = "
+Slate: Remove the expired keys.
+
+Expired keys are already hidden, and removed with any
+other change. This removes them without changing anything else,
+and drops the usage and the history of the keys that are gone.
+
+Usage:
+  slate gc [--help]
+
+Options:
+  -h --help  Show this help.
+
+Examples:
+
+  slate gc
+  #=> 2 expired keys removed
+";
+
+#[derive(Debug, Deserialize)]
+struct Args;
+
+pub fn run(slate: &Slate, argv: &Vec<String>, _streams: &mut Streams) -> CommandResult {
+    let _args: Args = try!(parse_args(USAGE, argv));
+    let removed = try!(slate.gc());
+
+    Ok(Some(Message::Change(Change::new("gc").count(removed),
+                            Some(format!("{} expired keys removed", removed)))))
+}
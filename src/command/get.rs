@@ -1,19 +1,40 @@
-use cli::parse_args;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use cli::{parse_args, Streams};
 use Slate;
+use highlight;
+use store::Entry;
 use results::CommandResult;
-use message::Message;
+use message::{Change, Message};
+use errors::{CommandError, SlateError};
 
-const USAGE: &'static str = "
+pub const USAGE: &'static str = "
 Slate: Get a value by name.
 
 Usage:
-  slate get <key>
-  slate get -n <key>
+  slate get [-n] [-d <value>] <key>
+  slate get [-f] --output <path> <key>
   slate get -h
 
+If the key doesn't exist the command fails with the exit code 2,
+unless a default value is given.
+
+In a terminal the value is highlighted, see the global
+option --color. Binary values are not shown in terminals, save
+them in a file instead.
+
+With --output the value is saved in a file, with the permissions
+of the file it was read from with `slate set --file`. If <path> is
+a directory, the original name of the file is used, without any
+directories. Existing files are only replaced with --force.
+
 Options:
-  -h --help    Show this help.
-  -n --no-eol  Do not print the trailing newline character. Show the value as it was saved.
+  -h --help             Show this help.
+  -n --no-eol           Do not print the trailing newline character. Show the value as it was saved.
+  -d --default <value>  Show this value if the key doesn't exist.
+  -o --output <path>    Save the value in a file.
+  -f --force            Replace the file if it exists.
 
 Examples:
 
@@ -24,23 +45,141 @@ Examples:
   slate get -n foo
   bar%
   $ _ # no EOL
+
+  $ slate get --default baz missing
+  baz
+  $ _
+
+  $ slate get --output ~/.ssh/ ssh-config
+  The value has been saved in /home/user/.ssh/config
 ";
 
 #[derive(Debug, Deserialize)]
 struct Args {
     arg_key: String,
     flag_no_eol: bool,
+    flag_default: Option<String>,
+    flag_output: Option<String>,
+    flag_force: bool,
 }
 
-pub fn run(slate: &Slate, argv: &Vec<String>) -> CommandResult {
-    let args: Args = parse_args(USAGE, argv).unwrap_or_else(|e| e.exit());
+pub fn run(slate: &Slate, argv: &Vec<String>, streams: &mut Streams) -> CommandResult {
+    let args: Args = try!(parse_args(USAGE, argv));
 
-    let value = try!(slate.get(&args.arg_key));
-    let message: Message = if args.flag_no_eol {
-        Message::Raw(value)
-    } else {
-        Message::Info(value)
+    let (value, language) = match slate.entry(&args.arg_key) {
+        Ok(entry) => {
+            slate.record_use(&args.arg_key);
+            if let Some(path) = args.flag_output {
+                return save(&args.arg_key, &entry, Path::new(&path), args.flag_force);
+            }
+            if entry.is_binary() {
+                return binary(&args.arg_key, &entry, streams);
+            }
+            (entry.value.clone(), highlight::language(&entry))
+        }
+        Err(SlateError::NotFound(key)) => {
+            match args.flag_default {
+                Some(value) => (value, None),
+                None => {
+                    let similar = try!(slate.suggest(&key));
+                    return Err(CommandError::NotFound(key, similar));
+                }
+            }
+        }
+        Err(e) => return Err(From::from(e)),
+    };
+
+    let message = Message::Value {
+        key: args.arg_key,
+        value: value,
+        eol: !args.flag_no_eol,
+        language: language,
     };
 
     Ok(Some(message))
 }
+
+/// Save the value of an entry in a file. Existing files are only
+/// replaced with `force`.
+fn save(key: &str, entry: &Entry, path: &Path, force: bool) -> CommandResult {
+    let bytes = try!(decode(key, entry));
+    let path = if path.is_dir() {
+        // Only the name, so saved names can't point outside the directory.
+        let name = entry.filename.as_ref().map(|name| name.as_str()).unwrap_or(key);
+        match Path::new(name).file_name() {
+            Some(name) => path.join(name),
+            None => {
+                let message = format!("{} is not a valid file name, give the path of a file", name);
+                return Err(CommandError::Argument(message));
+            }
+        }
+    } else {
+        PathBuf::from(path)
+    };
+
+    let mut file = match create(&path, entry.mode, force) {
+        Ok(file) => file,
+        Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => {
+            let message = format!("The file {} already exists. Use --force to replace it",
+                                  path.display());
+            return Err(CommandError::Argument(message));
+        }
+        Err(e) => return Err(From::from(e)),
+    };
+    try!(file.write_all(&bytes));
+
+    let path = format!("{}", path.display());
+    let text = format!("The value has been saved in {}", path);
+
+    Ok(Some(Message::Change(Change::new("get").key(key).path(&path), Some(text))))
+}
+
+/// Binary values are written as they are, but not in terminals.
+fn binary(key: &str, entry: &Entry, streams: &Streams) -> CommandResult {
+    if streams.terminal {
+        let message = format!("The value of {} is binary. Use --output to save it in a file", key);
+        return Err(CommandError::Argument(message));
+    }
+
+    Ok(Some(Message::Bytes { key: key.to_owned(), bytes: try!(decode(key, entry)) }))
+}
+
+fn decode(key: &str, entry: &Entry) -> Result<Vec<u8>, CommandError> {
+    match entry.bytes() {
+        Some(bytes) => Ok(bytes),
+        None => {
+            let reason = format!("The value of {} cannot be decoded", key);
+            Err(From::from(SlateError::Corrupted(reason)))
+        }
+    }
+}
+
+/// Open the file to save a value in, with the permissions of the
+/// entry from the start, so it is never readable by others.
+fn create(path: &Path, mode: Option<u32>, force: bool) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(!force).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        if let Some(mode) = mode {
+            options.mode(mode & 0o777);
+        }
+    }
+
+    let file = try!(options.open(path));
+    // The mode of new files is reduced by the umask, and replaced
+    // files keep theirs.
+    #[cfg(unix)]
+    {
+        use std::fs::Permissions;
+        use std::os::unix::fs::PermissionsExt;
+        if let Some(mode) = mode {
+            try!(file.set_permissions(Permissions::from_mode(mode & 0o777)));
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = mode;
+
+    Ok(file)
+}
@@ -0,0 +1,66 @@
+use std::env;
+use cli::{parse_args, Streams};
+use Slate;
+use http::Server;
+use results::CommandResult;
+
+pub const USAGE: &'static str = "
+Slate: Serve the store with a REST API over HTTP.
+
+Usage:
+  slate http [--bind <address>] [--token <token>]
+  slate http [options]
+
+Routes:
+  GET    /keys?prefix=<prefix>  List keys, as a JSON array.
+  GET    /keys/<key>            Read a value.
+  PUT    /keys/<key>            Write a value with the body of the request.
+  DELETE /keys/<key>            Delete a key.
+  POST   /rename                Rename a key: {\"from\": ..., \"to\": ..., \"force\": false}
+
+Values have an ETag. Use the If-Match and If-None-Match headers to
+avoid overwriting changes made by someone else.
+
+With a token, requests need the header `Authorization: Bearer <token>`.
+The token can also be set in the SLATE_HTTP_TOKEN env var.
+Without a token, only loopback addresses are allowed.
+
+Options:
+  -h --help             Show this help.
+  -b --bind <address>   Address to listen on [default: 127.0.0.1:7777].
+  -t --token <token>    Token required in every request.
+
+Examples:
+
+  SLATE_HTTP_TOKEN=secret slate http --bind 127.0.0.1:8080
+  #=> Listening on 127.0.0.1:8080
+
+  curl -H 'Authorization: Bearer secret' http://127.0.0.1:8080/keys/foo
+  #=> bar
+";
+
+#[derive(Debug, Deserialize)]
+struct Args {
+    flag_bind: String,
+    flag_token: Option<String>,
+}
+
+pub fn run(slate: &Slate, argv: &Vec<String>, streams: &mut Streams) -> CommandResult {
+    let args: Args = try!(parse_args(USAGE, argv));
+
+    let token = args.flag_token
+        .or_else(|| env::var("SLATE_HTTP_TOKEN").ok())
+        .and_then(|token| if token.is_empty() { None } else { Some(token) });
+    let server = try!(Server::bind(slate.config, &args.flag_bind, token));
+
+    let address = match server.address() {
+        Some(address) => format!("{}", address),
+        None => args.flag_bind.clone(),
+    };
+    try!(writeln!(streams.stdout, "Listening on {}", address));
+    try!(streams.stdout.flush());
+
+    server.run();
+
+    Ok(None)
+}
@@ -0,0 +1,104 @@
+use serde_json;
+use std::path::Path;
+use cli::{parse_args, Streams};
+use Slate;
+use links;
+use message::{Change, Message};
+use results::CommandResult;
+use errors::{CommandError, SlateError};
+
+pub const USAGE: &'static str = "
+Slate: Link a directory of files as read-only keys.
+
+Usage:
+  slate link [<dir>]
+  slate link --remove <dir>
+  slate link --help
+
+Every file inside <dir> is a key: its path relative to <dir>
+without the extension, like deploy/prod for deploy/prod.sh.
+Hidden files and directories, like .git, are skipped. Files are
+read every time they are used, and can't be changed with slate.
+Keys saved in the store hide the linked keys with the same name.
+
+Without <dir>, the linked directories are listed.
+
+Options:
+  -h --help    Show this help.
+  -r --remove  Stop using a linked directory.
+
+Examples:
+
+  slate link ~/src/dotfiles/snippets
+  #=> Linked /home/user/src/dotfiles/snippets with 12 keys
+
+  slate link
+  #=> /home/user/src/dotfiles/snippets  (12 keys)
+";
+
+#[derive(Debug, Deserialize)]
+struct Args {
+    arg_dir: Option<String>,
+    flag_remove: bool,
+}
+
+#[derive(Serialize)]
+struct Link {
+    path: String,
+    keys: usize,
+}
+
+pub fn run(slate: &Slate, argv: &Vec<String>, _streams: &mut Streams) -> CommandResult {
+    let args: Args = try!(parse_args(USAGE, argv));
+
+    let dir = match args.arg_dir {
+        Some(dir) => dir,
+        None => return list(slate),
+    };
+
+    if args.flag_remove {
+        try!(slate.unlink(Path::new(&dir)));
+        let change = Change::new("unlink").path(&dir);
+
+        return Ok(Some(Message::Change(change, Some(format!("Unlinked {}", dir)))));
+    }
+
+    let path = try!(slate.link(Path::new(&dir)));
+    let total = links::keys(&[path.clone()]).len();
+    let path = format!("{}", path.display());
+    let text = format!("Linked {} with {} keys", path, total);
+
+    Ok(Some(Message::Change(Change::new("link").path(&path).count(total), Some(text))))
+}
+
+fn list(slate: &Slate) -> CommandResult {
+    let mut rows = Vec::new();
+    let mut lines = Vec::new();
+    for dir in slate.links() {
+        let link = Link {
+            path: format!("{}", dir.display()),
+            keys: links::keys(&[dir.clone()]).len(),
+        };
+        lines.push(format!("{}  ({} keys)", link.path, link.keys));
+        rows.push(try!(serde_json::to_value(&link).map_err(SlateError::from)));
+    }
+
+    Ok(Some(Message::Rows {
+        name: "links",
+        rows: rows,
+        text: lines.join("\n"),
+    }))
+}
+
+/// Fail if a key comes from a linked directory, as it can't be
+/// changed.
+pub fn writable(slate: &Slate, key: &String) -> Result<(), CommandError> {
+    match try!(slate.link_of(key)) {
+        Some(linked) => {
+            Err(CommandError::Argument(format!("The key {} is read-only, it is linked from {}",
+                                               key,
+                                               linked.dir.display())))
+        }
+        None => Ok(()),
+    }
+}
@@ -1,31 +1,182 @@
-use cli::parse_args;
+use serde_json;
+use cli::{parse_args, Streams};
 use Slate;
+use links::Linked;
 use message::Message;
 use results::CommandResult;
+use time;
+use usage::Sort;
+use errors::{CommandError, SlateError};
 
-const USAGE: &'static str = "
+pub const USAGE: &'static str = "
 Slate: List all value names.
 
 Usage:
-  slate list [--help]
+  slate list [--sort <order>]
+  slate list --expiring
+  slate list --help
+
+Keys can be sorted by name, by frecency (how often and how
+recently they were used), by recent use, by size or by the
+last time they were updated.
+
+Keys of linked directories (see `slate link`) are shown with
+the name of their directory, after the keys of the store unless
+they are sorted by name. Aliases are shown with the key they
+point to.
 
 Options:
-  -h --help  Show this help.
+  -h --help          Show this help.
+  -s --sort <order>  Order: name, frecency, recent, size or updated [default: name].
+  --expiring         List only the keys that expire, with their dates.
 
 Examples:
 
   slate list
   #=> foo
   #=> more
+
+  slate list --sort frecency
+  #=> more
+  #=> foo
+
+  slate list --expiring
+  #=> token  2024-05-01 18:30:00 UTC (in 1h 59m)
+
+  slate link ~/snippets && slate list
+  #=> deploy/prod  [snippets]
+  #=> foo
+  #=> more
+
+  slate alias bar foo && slate list
+  #=> bar -> foo
+  #=> foo
+  #=> more
 ";
 
 #[derive(Debug, Deserialize)]
-struct Args;
+struct Args {
+    flag_sort: String,
+    flag_expiring: bool,
+}
+
+#[derive(Serialize)]
+struct Layered<'a> {
+    key: &'a str,
+    /// `store` or `link`.
+    layer: &'static str,
+    /// The file of a linked key.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+    /// The key an alias points to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct Expiring<'a> {
+    key: &'a str,
+    expires: u64,
+    remaining: u64,
+}
+
+pub fn run(slate: &Slate, argv: &Vec<String>, _streams: &mut Streams) -> CommandResult {
+    let args: Args = try!(parse_args(USAGE, argv));
+
+    if args.flag_expiring {
+        return expiring(slate);
+    }
+
+    let order: Sort = try!(args.flag_sort.parse());
+    let list = try!(slate.list_by(order));
+    let linked = try!(slate.linked());
+    let aliases = try!(slate.aliases());
+
+    Ok(Some(try!(layered(list, linked, &aliases, order == Sort::Name))))
+}
+
+/// List keys of the store and of the linked directories, with the
+/// layer they come from and the keys the aliases point to. Without
+/// linked keys or aliases, it is a plain list.
+///
+/// With `merge` all the keys are sorted by name, otherwise the
+/// linked ones go after the others.
+pub fn layered(keys: Vec<String>,
+               linked: Vec<Linked>,
+               aliases: &Vec<(String, String)>,
+               merge: bool)
+               -> Result<Message, CommandError> {
+    let target = |key: &str| {
+        aliases.iter().find(|&&(ref alias, _)| alias == key).map(|&(_, ref target)| target.as_str())
+    };
+    if linked.is_empty() && !keys.iter().any(|key| target(key).is_some()) {
+        return Ok(Message::Keys(keys));
+    }
+
+    let mut all: Vec<(String, Option<Linked>)> = keys.into_iter().map(|key| (key, None)).collect();
+    all.extend(linked.into_iter().map(|linked| (linked.key.clone(), Some(linked))));
+    if merge {
+        all.sort_by(|a, b| a.0.cmp(&b.0));
+    }
+
+    let mut rows = Vec::new();
+    let mut lines = Vec::new();
+    for &(ref key, ref linked) in &all {
+        let row = Layered {
+            key: key,
+            layer: if linked.is_some() { "link" } else { "store" },
+            path: linked.as_ref().map(|linked| format!("{}", linked.path.display())),
+            target: target(key),
+        };
+        rows.push(try!(serde_json::to_value(&row).map_err(SlateError::from)));
+
+        lines.push(match *linked {
+            Some(ref linked) => {
+                let dir = linked.dir.file_name().unwrap_or(linked.dir.as_os_str());
+                format!("{}  [{}]", key, dir.to_string_lossy())
+            }
+            None => {
+                match target(key) {
+                    Some(target) => format!("{} -> {}", key, target),
+                    None => key.to_owned(),
+                }
+            }
+        });
+    }
+
+    Ok(Message::Rows {
+        name: "keys",
+        rows: rows,
+        text: lines.join("\n"),
+    })
+}
+
+fn expiring(slate: &Slate) -> CommandResult {
+    let now = time::now();
+    let expiring = try!(slate.expiring());
 
-pub fn run(slate: &Slate, argv: &Vec<String>) -> CommandResult {
-    let _args: Args = parse_args(USAGE, argv).unwrap_or_else(|e| e.exit());
-    let list = try!(slate.list());
-    let output = list.join("\n");
+    let mut rows = Vec::new();
+    for &(ref key, expires) in &expiring {
+        let row = Expiring {
+            key: key,
+            expires: expires,
+            remaining: expires - now,
+        };
+        rows.push(try!(serde_json::to_value(&row).map_err(SlateError::from)));
+    }
+    let text = expiring.iter()
+        .map(|&(ref key, expires)| {
+            format!("{}  {} (in {})",
+                    key,
+                    time::format_date(expires),
+                    time::format_duration(expires - now))
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
 
-    Ok(Some(Message::Info(output)))
+    Ok(Some(Message::Rows {
+        name: "expiring",
+        rows: rows,
+        text: text,
+    }))
 }
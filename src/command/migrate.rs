@@ -0,0 +1,70 @@
+use std::path::PathBuf;
+use cli::{parse_args, Streams};
+use Slate;
+use config::{Backend, Config};
+use message::{Change, Message};
+use results::CommandResult;
+use errors::CommandError;
+
+pub const USAGE: &'static str = "
+Slate: Copy all keys into a store with a different backend.
+
+Usage:
+  slate migrate --to <backend> [<path>]
+  slate migrate [options]
+
+Backends are `json` and `sqlite`. If <path> is not present, the
+current file with the extension of the backend will be used
+(`.json` or `.db`). The destination must not exist.
+
+Once migrated, use the SLATE_BACKEND and SLATE_FILEPATH env vars
+to work with the new store.
+
+Options:
+  -h --help           Show this help.
+  -t --to <backend>   Backend of the new store.
+
+Examples:
+
+  slate migrate --to sqlite
+  #=> 2 keys migrated to /home/user/.slate.db
+";
+
+#[derive(Debug, Deserialize)]
+struct Args {
+    flag_to: String,
+    arg_path: Option<String>,
+}
+
+pub fn run(slate: &Slate, argv: &Vec<String>, _streams: &mut Streams) -> CommandResult {
+    let args: Args = try!(parse_args(USAGE, argv));
+
+    let backend: Backend = try!(args.flag_to.parse());
+    let filepath = match args.arg_path {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let extension = match backend {
+                Backend::Json => "json",
+                Backend::Sqlite => "db",
+            };
+            slate.filepath().with_extension(extension)
+        }
+    };
+
+    if filepath.exists() {
+        return Err(CommandError::Argument(format!("The file {} already exists",
+                                                  filepath.display())));
+    }
+
+    let destination = Config {
+        filepath: filepath,
+        backend: backend,
+        socket: None,
+        ..slate.config.clone()
+    };
+    let total = try!(slate.migrate(&destination));
+    let path = format!("{}", destination.filepath.display());
+    let text = format!("{} keys migrated to {}", total, path);
+
+    Ok(Some(Message::Change(Change::new("migrate").count(total).path(&path), Some(text))))
+}
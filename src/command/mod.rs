@@ -1,5 +1,58 @@
 pub mod set;
+pub mod append;
+pub mod prepend;
 pub mod get;
+pub mod show;
 pub mod list;
 pub mod remove;
 pub mod rename;
+pub mod alias;
+pub mod copy;
+pub mod mv;
+pub mod search;
+pub mod find;
+pub mod link;
+pub mod migrate;
+pub mod batch;
+pub mod serve;
+pub mod http;
+pub mod watch;
+pub mod gc;
+pub mod recent;
+pub mod stats;
+pub mod doctor;
+pub mod dedupe;
+pub mod diff;
+pub mod completions;
+pub mod complete;
+
+/// Names and USAGE strings of all the public commands.
+pub const USAGES: &'static [(&'static str, &'static str)] = &[("set", set::USAGE),
+                                                              ("append", append::USAGE),
+                                                              ("prepend", prepend::USAGE),
+                                                              ("get", get::USAGE),
+                                                              ("show", show::USAGE),
+                                                              ("list", list::USAGE),
+                                                              ("rename", rename::USAGE),
+                                                              ("alias", alias::USAGE),
+                                                              ("copy", copy::USAGE),
+                                                              ("move", mv::USAGE),
+                                                              ("remove", remove::USAGE),
+                                                              ("search", search::USAGE),
+                                                              ("find", find::USAGE),
+                                                              ("link", link::USAGE),
+                                                              ("migrate", migrate::USAGE),
+                                                              ("batch", batch::USAGE),
+                                                              ("serve", serve::USAGE),
+                                                              ("http", http::USAGE),
+                                                              ("watch", watch::USAGE),
+                                                              ("gc", gc::USAGE),
+                                                              ("recent", recent::USAGE),
+                                                              ("stats", stats::USAGE),
+                                                              ("doctor", doctor::USAGE),
+                                                              ("dedupe", dedupe::USAGE),
+                                                              ("diff", diff::USAGE),
+                                                              ("completions", completions::USAGE)];
+
+/// Commands whose arguments are existing keys.
+pub const KEYS: &'static [&'static str] = &["get", "show", "append", "prepend", "remove", "rename", "alias", "copy", "move", "diff"];
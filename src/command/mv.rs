@@ -0,0 +1,55 @@
+use cli::{parse_args, Streams};
+use Slate;
+use command::copy;
+use results::CommandResult;
+use errors::CommandError;
+use transfer::Mode;
+
+pub const USAGE: &'static str = "
+Slate: Move keys into another profile or store.
+
+Usage:
+  slate move [--force] (--to-profile <profile> | --to-store <path>) <key>...
+  slate move --help
+
+The keys keep their attributes and history, and are removed from
+the current store once they are saved in the other one. Keys can
+be patterns with * and ?, like 'k8s-*'.
+
+Nothing is moved if any key already exists in the other store,
+unless --force is given. Use `slate rename` to move a key inside
+the same store.
+
+Options:
+  -h --help               Show this help.
+  -f --force              Overwrite the keys that already exist.
+  -p --to-profile <name>  Move into the store of another profile.
+  -s --to-store <path>    Move into another store file.
+
+Examples:
+
+  slate move --to-profile work vpn 'k8s-*'
+  #=> 4 keys moved to the profile work
+
+  slate move --to-store ~/backup.db old
+  #=> 1 key moved to /home/user/backup.db
+";
+
+#[derive(Debug, Deserialize)]
+struct Args {
+    arg_key: Vec<String>,
+    flag_force: bool,
+    flag_to_profile: Option<String>,
+    flag_to_store: Option<String>,
+}
+
+pub fn run(slate: &Slate, argv: &Vec<String>, _streams: &mut Streams) -> CommandResult {
+    let args: Args = try!(parse_args(USAGE, argv));
+
+    match copy::destination(slate, &args.flag_to_profile, &args.flag_to_store) {
+        Some(destination) => {
+            copy::between(slate, &try!(destination), &args.arg_key, Mode::Move, args.flag_force)
+        }
+        None => Err(CommandError::Argument("You must provide the destination".to_string())),
+    }
+}
@@ -0,0 +1,43 @@
+use cli::{parse_args, Streams};
+use Slate;
+use command::set;
+use message::{Change, Message};
+use results::CommandResult;
+
+pub const USAGE: &'static str = "
+Slate: Add text at the beginning of a value.
+
+Usage:
+  slate prepend <key> [<text>]
+  slate prepend --help
+
+If <text> is not present, <stdin> will be used. The text is added
+in its own line, and the key is created if it doesn't exist.
+
+Options:
+  -h --help  Show this help.
+
+Examples:
+
+  slate prepend hosts web-0.example.com
+
+  echo '# Release checklist' | slate prepend release
+";
+
+#[derive(Debug, Deserialize)]
+struct Args {
+    arg_key: String,
+    arg_text: Option<String>,
+}
+
+pub fn run(slate: &Slate, argv: &Vec<String>, streams: &mut Streams) -> CommandResult {
+    let args: Args = try!(parse_args(USAGE, argv));
+
+    let text = match args.arg_text {
+        Some(text) => text,
+        None => try!(set::input(streams)),
+    };
+    try!(slate.prepend(&args.arg_key, &text));
+
+    Ok(Some(Message::Change(Change::new("prepend").key(&args.arg_key), None)))
+}
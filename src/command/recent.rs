@@ -0,0 +1,43 @@
+use cli::{parse_args, Streams};
+use Slate;
+use message::Message;
+use results::CommandResult;
+use errors::CommandError;
+
+pub const USAGE: &'static str = "
+Slate: List the last used keys.
+
+Usage:
+  slate recent [-n <count>]
+  slate recent --help
+
+Keys are used with `slate get`. Set SLATE_TRACKING=off to
+stop recording it.
+
+Options:
+  -h --help           Show this help.
+  -n --count <count>  How many keys to show [default: 10].
+
+Examples:
+
+  slate recent -n 2
+  #=> foo
+  #=> more
+";
+
+#[derive(Debug, Deserialize)]
+struct Args {
+    flag_count: String,
+}
+
+pub fn run(slate: &Slate, argv: &Vec<String>, _streams: &mut Streams) -> CommandResult {
+    let args: Args = try!(parse_args(USAGE, argv));
+    let count: usize = match args.flag_count.parse() {
+        Ok(count) => count,
+        Err(_) => return Err(CommandError::Argument(format!("Invalid count: {}", args.flag_count))),
+    };
+
+    let recent = try!(slate.recent(count));
+
+    Ok(Some(Message::Keys(recent)))
+}
@@ -1,10 +1,11 @@
-use cli::parse_args;
+use cli::{parse_args, Streams};
 use Slate;
-use message::Message;
+use command::link;
+use message::{Change, Message};
 use results::CommandResult;
 use errors::CommandError;
 
-const USAGE: &'static str = "
+pub const USAGE: &'static str = "
 Slate: Remove an element.
 
 Usage:
@@ -28,12 +29,13 @@ struct Args {
     flag_all: bool,
 }
 
-pub fn run(slate: &Slate, argv: &Vec<String>) -> CommandResult {
-    let args: Args = parse_args(USAGE, argv).unwrap_or_else(|e| e.exit());
+pub fn run(slate: &Slate, argv: &Vec<String>, streams: &mut Streams) -> CommandResult {
+    let args: Args = try!(parse_args(USAGE, argv));
 
     if args.flag_all {
         try!(slate.clear());
-        Ok(Some(Message::Info("All keys have been removed".to_string())))
+        Ok(Some(Message::Change(Change::new("clear"),
+                                Some("All keys have been removed".to_string()))))
     } else {
         let key: String = match args.arg_key {
             Some(string) => string,
@@ -42,8 +44,22 @@ pub fn run(slate: &Slate, argv: &Vec<String>) -> CommandResult {
             }
         };
 
+        try!(link::writable(slate, &key));
+        let dangling: Vec<String> = try!(slate.aliases())
+            .into_iter()
+            .filter(|&(_, ref target)| target == &key)
+            .map(|(alias, _)| alias)
+            .collect();
         try!(slate.remove(&key));
 
-        Ok(Some(Message::Info("The key has been removed".to_string())))
+        if !dangling.is_empty() {
+            try!(writeln!(streams.stderr,
+                          "Warning: these aliases point to {}, that doesn't exist anymore: {}",
+                          key,
+                          dangling.join(", ")));
+        }
+
+        Ok(Some(Message::Change(Change::new("remove").key(&key),
+                                Some("The key has been removed".to_string()))))
     }
 }
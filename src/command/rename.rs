@@ -1,17 +1,21 @@
-use cli::parse_args;
+use cli::{parse_args, Streams};
 use Slate;
-use message::Message;
+use command::link;
+use message::{Change, Message};
 use results::CommandResult;
 
-const USAGE: &'static str = "
+pub const USAGE: &'static str = "
 Slate: Rename a key with new name.
 
 Usage:
-  slate rename <old> <new>
+  slate rename [--force] <old> <new>
   slate rename [options]
 
+The new name must not be in use, unless --force is given.
+
 Options:
-  -h --help  Show this help.
+  -h --help   Show this help.
+  -f --force  Overwrite the new key if it already exists.
 
 Examples:
 
@@ -23,12 +27,16 @@ Examples:
 struct Args {
     arg_old: String,
     arg_new: String,
+    flag_force: bool,
 }
 
-pub fn run(slate: &Slate, argv: &Vec<String>) -> CommandResult {
-    let args: Args = parse_args(USAGE, argv).unwrap_or_else(|e| e.exit());
+pub fn run(slate: &Slate, argv: &Vec<String>, _streams: &mut Streams) -> CommandResult {
+    let args: Args = try!(parse_args(USAGE, argv));
+
+    try!(link::writable(slate, &args.arg_old));
+    try!(slate.rename(&args.arg_old, &args.arg_new, args.flag_force));
 
-    try!(slate.rename(&args.arg_old, &args.arg_new));
+    let change = Change::new("rename").key(&args.arg_new).from(&args.arg_old);
 
-    Ok(Some(Message::Info("The key has been renamed".to_string())))
+    Ok(Some(Message::Change(change, Some("The key has been renamed".to_string()))))
 }
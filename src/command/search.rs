@@ -0,0 +1,39 @@
+use cli::{parse_args, Streams};
+use Slate;
+use command::list;
+use results::CommandResult;
+
+pub const USAGE: &'static str = "
+Slate: Find keys by the contents of their values.
+
+Usage:
+  slate search <query>...
+  slate search [options]
+
+Every word of the query must be present in the value. With the
+SQLite backend, words are matched as prefixes of the words in the
+values. The files of linked directories are searched too.
+
+Options:
+  -h --help  Show this help.
+
+Examples:
+
+  slate search daemon
+  #=> nginx
+  #=> redis
+";
+
+#[derive(Debug, Deserialize)]
+struct Args {
+    arg_query: Vec<String>,
+}
+
+pub fn run(slate: &Slate, argv: &Vec<String>, _streams: &mut Streams) -> CommandResult {
+    let args: Args = try!(parse_args(USAGE, argv));
+    let query = args.arg_query.join(" ");
+    let found = try!(slate.search(&query));
+    let linked = try!(slate.search_linked(&query));
+
+    Ok(Some(try!(list::layered(found, linked, &try!(slate.aliases()), true))))
+}
@@ -0,0 +1,90 @@
+#[cfg(unix)]
+use std::env;
+use std::path::PathBuf;
+use cli::{parse_args, Streams};
+use Slate;
+use results::CommandResult;
+
+#[cfg(unix)]
+use daemon;
+use errors::CommandError;
+
+pub const USAGE: &'static str = "
+Slate: Serve the store to other programs.
+
+Usage:
+  slate serve [--socket <path>] [--tcp <address> --token <token>]
+  slate serve [options]
+
+The daemon speaks JSON-RPC 2.0, one message per line, on a Unix
+socket. By default it is the store file with the `.sock` extension
+added, like ~/.slate.sock. While it is running, the other slate
+commands use it instead of reading the store file.
+
+TCP connections must send the token before anything else, with
+the method `authenticate` and the param `token`. The token can
+also be set in the SLATE_SERVE_TOKEN env var.
+
+Options:
+  -h --help            Show this help.
+  -s --socket <path>   Path of the Unix socket.
+  -t --tcp <address>   Listen on a loopback TCP address too, like 127.0.0.1:7777.
+  --token <token>      Token required in TCP connections.
+
+Examples:
+
+  slate serve
+  #=> Listening on /home/user/.slate.sock
+
+  echo '{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"get\",\"params\":{\"key\":\"foo\"}}' \\
+    | nc -U ~/.slate.sock
+  #=> {\"jsonrpc\":\"2.0\",\"id\":1,\"result\":\"bar\"}
+";
+
+#[derive(Debug, Deserialize)]
+struct Args {
+    flag_socket: Option<String>,
+    flag_tcp: Option<String>,
+    flag_token: Option<String>,
+}
+
+#[cfg(unix)]
+pub fn run(slate: &Slate, argv: &Vec<String>, streams: &mut Streams) -> CommandResult {
+    let args: Args = try!(parse_args(USAGE, argv));
+
+    let socket = match args.flag_socket {
+        Some(path) => PathBuf::from(path),
+        None => daemon::socket_path(slate.config),
+    };
+
+    let token = args.flag_token
+        .or_else(|| env::var("SLATE_SERVE_TOKEN").ok())
+        .and_then(|token| if token.is_empty() { None } else { Some(token) });
+    if let Some(ref address) = args.flag_tcp {
+        try!(daemon::check_loopback(address));
+        if token.is_none() {
+            let message = format!("A token is needed to listen on {}", address);
+            return Err(CommandError::Argument(message));
+        }
+    }
+
+    try!(writeln!(streams.stdout, "Listening on {}", socket.display()));
+    if let Some(ref address) = args.flag_tcp {
+        try!(writeln!(streams.stdout, "Listening on {}", address));
+    }
+    try!(streams.stdout.flush());
+
+    try!(daemon::serve(slate.config,
+                       &socket,
+                       args.flag_tcp.as_ref().map(|a| a.as_str()),
+                       token.as_ref().map(|t| t.as_str())));
+
+    Ok(None)
+}
+
+#[cfg(not(unix))]
+pub fn run(_slate: &Slate, argv: &Vec<String>, _streams: &mut Streams) -> CommandResult {
+    let _args: Args = try!(parse_args(USAGE, argv));
+
+    Err(CommandError::Argument("The daemon needs Unix sockets".to_string()))
+}
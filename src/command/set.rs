@@ -1,51 +1,151 @@
-use std::io::{self, Read};
-use cli::parse_args;
+use std::path::Path;
+use cli::{parse_args, Streams};
 use Slate;
+use store::Entry;
+use time;
+use highlight;
 use results::CommandResult;
+use message::{Change, Message};
 use errors::CommandError;
 
-const USAGE: &'static str = "
+pub const USAGE: &'static str = "
 Slate: Set a value using a name (or key).
 
 Usage:
-  slate set <key> [<value>]
-  slate set [options]
+  slate set [options] <key> [<value>]
+  slate set --help
 
-If <value> is not present, <stdin> will be used.
+If <value> is not present, <stdin> will be used. Binary values
+are saved with base64. Files keep their name and permissions, so
+they can be restored exactly with `slate get --output <path>`.
+If <key> is an alias, the key it points to is set.
+
+With --line, only that line of the value is replaced, and the key
+keeps its attributes. Lines are numbered from 1.
+
+Expired keys are hidden, and removed from the store with the
+next change or with `slate gc`. Durations are made of numbers
+with the units s, m, h, d and w, like `2h` or `1h30m`. Dates are
+in UTC, like `2024-05-01` or `2024-05-01 18:30`.
+
+The language is used to highlight the value. Without it, it
+is detected from the shebang or the contents of the value.
 
 Options:
-  -h --help  Show this help.
+  -h --help             Show this help.
+  --ttl <duration>      Remove the key after some time.
+  --expires <date>      Remove the key at some date.
+  -l --lang <language>  Language of the value, like shell, sql or yaml.
+  --tags <tags>         Tags of the value, separated by commas.
+  --description <text>  What the value is for.
+  --line <number>       Replace just a line of the value.
+  --file <path>         Save the contents of a file.
 
 Examples:
 
   slate set foo bar
 
   cat config.yml | slate set config
+
+  slate set --ttl 2h token 7a4d2c
+
+  slate set --tags k8s,ops --description \"Pods in all namespaces\" pods \"kubectl get pods -A\"
+
+  slate set --line 2 hosts web-2.example.com
+
+  slate set --file ~/.ssh/config ssh-config
 ";
 
 #[derive(Debug, Deserialize)]
 struct Args {
     arg_key: String,
     arg_value: Option<String>,
+    flag_ttl: Option<String>,
+    flag_expires: Option<String>,
+    flag_lang: Option<String>,
+    flag_tags: Option<String>,
+    flag_description: Option<String>,
+    flag_line: Option<usize>,
+    flag_file: Option<String>,
 }
 
-pub fn run(slate: &Slate, argv: &Vec<String>) -> CommandResult {
-    let args: Args = parse_args(USAGE, argv).unwrap_or_else(|e| e.exit());
+pub fn run(slate: &Slate, argv: &Vec<String>, streams: &mut Streams) -> CommandResult {
+    let args: Args = try!(parse_args(USAGE, argv));
+
+    if let Some(number) = args.flag_line {
+        let attributes = args.flag_ttl.is_some() || args.flag_expires.is_some() ||
+                         args.flag_lang.is_some() || args.flag_tags.is_some() ||
+                         args.flag_description.is_some() || args.flag_file.is_some();
+        if attributes {
+            return Err(CommandError::Argument("Use --line without other options".to_string()));
+        }
+
+        let line = match args.arg_value {
+            Some(v) => v,
+            None => try!(input(streams)),
+        };
+        try!(slate.set_line(&args.arg_key, number, &line));
+
+        return Ok(Some(Message::Change(Change::new("set").key(&args.arg_key), None)));
+    }
+
+    let expires = match (args.flag_ttl, args.flag_expires) {
+        (Some(_), Some(_)) => {
+            return Err(CommandError::Argument("Use either --ttl or --expires".to_string()))
+        }
+        (Some(ttl), _) => Some(time::now() + try!(time::parse_duration(&ttl))),
+        (_, Some(date)) => Some(try!(time::parse_date(&date))),
+        _ => None,
+    };
+    if expires.map(|expires| expires <= time::now()).unwrap_or(false) {
+        return Err(CommandError::Argument("The expiration date is in the past".to_string()));
+    }
 
     let key = args.arg_key;
-    let value = match args.arg_value {
-        Some(v) => Ok(v),
-        None => input(),
+    let value = match (args.arg_value, args.flag_file) {
+        (Some(_), Some(_)) => {
+            return Err(CommandError::Argument("Use either <value> or --file".to_string()))
+        }
+        (Some(v), None) => Entry::new(&v),
+        (None, Some(path)) => try!(Entry::from_file(Path::new(&path))),
+        (None, None) => Entry::from_bytes(try!(input_bytes(streams))),
+    };
+    let entry = Entry {
+        expires: expires,
+        language: args.flag_lang.map(|language| highlight::normalize(&language)),
+        tags: args.flag_tags.map(|tags| tags_from(&tags)).unwrap_or_default(),
+        description: args.flag_description,
+        ..value
     };
-    let value = try!(value);
-    try!(slate.set(&key, &value));
+    try!(slate.set_entry(&key, &entry));
+
+    Ok(Some(Message::Change(Change::new("set").key(&key), None)))
+}
 
-    Ok(None)
+/// Split a list of tags separated by commas, without duplicates.
+fn tags_from(list: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in list.split(',').map(|tag| tag.trim()).filter(|tag| !tag.is_empty()) {
+        if !tags.iter().any(|t| t == tag) {
+            tags.push(tag.to_owned());
+        }
+    }
+
+    tags
+}
+
+/// Read the value from <stdin>, which can be binary.
+fn input_bytes(streams: &mut Streams) -> Result<Vec<u8>, CommandError> {
+    let mut buffer = Vec::new();
+    try!(streams.stdin.read_to_end(&mut buffer));
+
+    Ok(buffer)
 }
 
-fn input() -> Result<String, CommandError> {
+/// Read the value from <stdin>.
+pub fn input(streams: &mut Streams) -> Result<String, CommandError> {
     let mut buffer = String::new();
-    try!(io::stdin().read_to_string(&mut buffer));
+    try!(streams.stdin.read_to_string(&mut buffer));
 
     Ok(buffer)
 }
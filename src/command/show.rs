@@ -0,0 +1,234 @@
+use serde_json;
+use std::env;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use cli::{parse_args, Streams};
+use Slate;
+use message::{Format, Message};
+use results::CommandResult;
+use errors::{CommandError, SlateError};
+use highlight::{self, Theme};
+use time;
+use usage;
+
+pub const USAGE: &'static str = "
+Slate: Show a value with its details.
+
+Usage:
+  slate show [--no-pager] <key>
+  slate show --help
+
+The details of the key are shown in a header, followed by the
+value with line numbers. In a terminal, values longer than the
+screen are shown with $PAGER (or `less -R`). Use `slate get` to
+get just the value.
+
+Options:
+  -h --help      Show this help.
+  -P --no-pager  Don't use a pager.
+
+Examples:
+
+  slate show pods
+  #=> Key:          pods
+  #=> Tags:         k8s, ops
+  #=> Description:  Pods in all namespaces
+  #=> Language:     shell
+  #=> Size:         19 bytes, 1 line
+  #=> Created:      2024-05-01 18:30:00 UTC
+  #=> Updated:      2024-05-01 18:30:00 UTC
+  #=> Revisions:    1
+  #=>
+  #=> 1 | kubectl get pods -A
+";
+
+/// Lines of the screen, when the terminal doesn't tell.
+const SCREEN_LINES: usize = 24;
+
+#[derive(Debug, Deserialize)]
+struct Args {
+    arg_key: String,
+    flag_no_pager: bool,
+}
+
+#[derive(Serialize)]
+struct Details<'a> {
+    key: &'a str,
+    value: &'a str,
+    tags: &'a Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    language: Option<String>,
+    size: usize,
+    lines: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    created: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    updated: Option<u64>,
+    revisions: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expires: Option<u64>,
+    binary: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    filename: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mode: Option<u32>,
+}
+
+pub fn run(slate: &Slate,
+           argv: &Vec<String>,
+           streams: &mut Streams,
+           format: Format,
+           theme: Option<&Theme>)
+           -> CommandResult {
+    let args: Args = try!(parse_args(USAGE, argv));
+    let key = args.arg_key;
+
+    let entry = match slate.entry(&key) {
+        Ok(entry) => entry,
+        Err(SlateError::NotFound(key)) => {
+            let similar = try!(slate.suggest(&key));
+            return Err(CommandError::NotFound(key, similar));
+        }
+        Err(e) => return Err(From::from(e)),
+    };
+    let revisions = try!(slate.history(&key));
+
+    let details = Details {
+        key: &key,
+        value: &entry.value,
+        tags: &entry.tags,
+        description: entry.description.as_ref().map(|d| d.as_str()),
+        language: if entry.is_binary() { None } else { highlight::language(&entry) },
+        size: entry.bytes().map(|bytes| bytes.len()).unwrap_or(entry.value.len()),
+        lines: if entry.is_binary() { 0 } else { entry.value.lines().count() },
+        created: usage::read(slate.config).get(&key).and_then(|usage| usage.created),
+        updated: revisions.last().and_then(|revision| revision.time),
+        revisions: revisions.len(),
+        expires: entry.expires,
+        binary: entry.is_binary(),
+        filename: entry.filename.as_ref().map(|name| name.as_str()),
+        mode: entry.mode,
+    };
+    let text = render(&details, theme);
+
+    let pager = !args.flag_no_pager && format == Format::Text && streams.terminal &&
+                text.lines().count() > screen_lines();
+    if pager && page(&text) {
+        return Ok(None);
+    }
+
+    let record = try!(serde_json::to_value(&details).map_err(SlateError::from));
+
+    Ok(Some(Message::Record(record, text)))
+}
+
+/// Text with the header and the value with line numbers.
+fn render(details: &Details, theme: Option<&Theme>) -> String {
+    let date = |time: Option<u64>| time.map(time::format_date).unwrap_or("unknown".to_string());
+    let plural = if details.lines == 1 { "" } else { "s" };
+    let length = if details.binary {
+        format!("{}, binary", size(details.size))
+    } else {
+        format!("{}, {} line{}", size(details.size), details.lines, plural)
+    };
+    let mut header = vec![("Key", details.key.to_owned()),
+                          ("Tags", or_dash(details.tags.join(", "))),
+                          ("Description", or_dash(details.description.unwrap_or("").to_owned())),
+                          ("Language", or_dash(details.language.clone().unwrap_or_default())),
+                          ("Size", length),
+                          ("Created", date(details.created)),
+                          ("Updated", date(details.updated)),
+                          ("Revisions", details.revisions.to_string())];
+    if details.filename.is_some() || details.mode.is_some() {
+        header.push(("File", file(details)));
+    }
+    if let Some(expires) = details.expires {
+        header.push(("Expires", time::format_date(expires)));
+    }
+
+    let mut text: String = header.iter()
+        .map(|&(name, ref value)| format!("{:<13} {}\n", format!("{}:", name), value))
+        .collect();
+    text.push('\n');
+
+    if details.binary {
+        text.push_str("(binary value, use `slate get --output <path>` to save it)\n");
+        return text;
+    }
+
+    let value = match (theme, details.language.as_ref()) {
+        (Some(theme), Some(language)) => highlight::highlight(details.value, language, theme),
+        _ => details.value.to_owned(),
+    };
+    let width = details.lines.to_string().len();
+    for (number, line) in value.lines().enumerate() {
+        text.push_str(&format!("{:>width$} | {}\n", number + 1, line, width = width));
+    }
+
+    text
+}
+
+/// Name and permissions of the file the value was read from.
+fn file(details: &Details) -> String {
+    match (details.filename, details.mode) {
+        (Some(name), Some(mode)) => format!("{} (mode {:o})", name, mode),
+        (Some(name), None) => name.to_owned(),
+        (None, Some(mode)) => format!("(mode {:o})", mode),
+        (None, None) => String::new(),
+    }
+}
+
+fn or_dash(value: String) -> String {
+    if value.is_empty() { "-".to_string() } else { value }
+}
+
+/// Size in bytes for humans, like `12 bytes` or `1.5 KB`.
+pub fn size(bytes: usize) -> String {
+    if bytes < 1024 {
+        format!("{} bytes", bytes)
+    } else if bytes < 1024 * 1024 {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    } else {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    }
+}
+
+fn screen_lines() -> usize {
+    env::var("LINES").ok().and_then(|lines| lines.parse().ok()).unwrap_or(SCREEN_LINES)
+}
+
+/// Show the text with the pager, and get whether it could be started.
+fn page(text: &str) -> bool {
+    let pager = match env::var("PAGER") {
+        Ok(ref pager) if !pager.trim().is_empty() => pager.to_owned(),
+        _ => "less -R".to_string(),
+    };
+
+    let child = Command::new("sh").arg("-c").arg(&pager).stdin(Stdio::piped()).spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(_) => return false,
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        // The pager may quit before reading everything.
+        let _ = stdin.write_all(text.as_bytes());
+    }
+
+    let _ = child.wait();
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_shows_sizes_for_humans() {
+        assert_eq!("12 bytes", size(12));
+        assert_eq!("1.5 KB", size(1536));
+        assert_eq!("2.0 MB", size(2 * 1048576));
+    }
+}
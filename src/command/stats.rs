@@ -0,0 +1,95 @@
+use serde_json;
+use std::fs;
+use cli::{parse_args, Streams};
+use Slate;
+use command::show::size;
+use message::Message;
+use results::CommandResult;
+use errors::{CommandError, SlateError};
+use stats::Count;
+
+pub const USAGE: &'static str = "
+Slate: Show statistics about the keys.
+
+Usage:
+  slate stats [--stale <days>] [--top <count>]
+  slate stats --help
+
+Namespaces are the beginning of the keys, before the first
+/, :, ., - or _. Keys are stale when they haven't been used or
+changed in some days, or if their usage was never recorded.
+
+Options:
+  -h --help         Show this help.
+  --stale <days>    Days without use to be stale [default: 90].
+  --top <count>     How many of the largest values to show [default: 5].
+
+Examples:
+
+  slate stats
+  #=> Keys:        4
+  #=> Size:        1.2 KB
+  #=> Largest:     deploy (1.0 KB), k8s/pods (16 bytes)
+  #=> Namespaces:  k8s (2)
+  #=> Tags:        ops (3), k8s (2)
+  #=> Stale:       vpn
+  #=> Duplicates:  k8s/pods = pods
+  #=> File:        /home/user/.slate (1.5 KB)
+";
+
+#[derive(Debug, Deserialize)]
+struct Args {
+    flag_stale: String,
+    flag_top: String,
+}
+
+pub fn run(slate: &Slate, argv: &Vec<String>, _streams: &mut Streams) -> CommandResult {
+    let args: Args = try!(parse_args(USAGE, argv));
+    let stale: u64 = match args.flag_stale.parse() {
+        Ok(stale) => stale,
+        Err(_) => return Err(CommandError::Argument(format!("Invalid days: {}", args.flag_stale))),
+    };
+    let top: usize = match args.flag_top.parse() {
+        Ok(top) => top,
+        Err(_) => return Err(CommandError::Argument(format!("Invalid count: {}", args.flag_top))),
+    };
+
+    let summary = try!(slate.stats(stale, top));
+    let file = slate.filepath();
+    let file_size = fs::metadata(file).map(|metadata| metadata.len()).ok();
+
+    let or_dash = |text: String| if text.is_empty() { "-".to_string() } else { text };
+    let counts = |counts: &Vec<Count>| -> String {
+        let counts: Vec<String> = counts.iter().map(|c| format!("{} ({})", c.name, c.keys)).collect();
+        or_dash(counts.join(", "))
+    };
+    let largest: Vec<String> = summary.largest
+        .iter()
+        .map(|largest| format!("{} ({})", largest.key, size(largest.size)))
+        .collect();
+    let duplicates: Vec<String> = summary.duplicates.iter().map(|keys| keys.join(" = ")).collect();
+    let file_text = match file_size {
+        Some(bytes) => format!("{} ({})", file.display(), size(bytes as usize)),
+        None => format!("{} (missing)", file.display()),
+    };
+
+    let lines = vec![("Keys", summary.keys.to_string()),
+                     ("Size", size(summary.size)),
+                     ("Largest", or_dash(largest.join(", "))),
+                     ("Namespaces", counts(&summary.namespaces)),
+                     ("Tags", counts(&summary.tags)),
+                     ("Stale", or_dash(summary.stale.join(", "))),
+                     ("Duplicates", or_dash(duplicates.join(", "))),
+                     ("File", file_text)];
+    let text: Vec<String> = lines.iter()
+        .map(|&(name, ref value)| format!("{:<12} {}", format!("{}:", name), value))
+        .collect();
+
+    let mut record = try!(serde_json::to_value(&summary).map_err(SlateError::from));
+    if let Some(fields) = record.as_object_mut() {
+        fields.insert("file".to_string(), From::from(format!("{}", file.display())));
+        fields.insert("file_size".to_string(), file_size.map(From::from).unwrap_or_default());
+    }
+
+    Ok(Some(Message::Record(record, text.join("\n"))))
+}
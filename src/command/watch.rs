@@ -0,0 +1,51 @@
+use cli::{parse_args, Streams};
+use Slate;
+use message::{Change, Format, Message};
+use results::CommandResult;
+
+pub const USAGE: &'static str = "
+Slate: Print the changes of the store as they happen.
+
+Usage:
+  slate watch [<prefix>]
+  slate watch [options]
+
+Only keys that start with <prefix> are watched, if given. Each
+change is printed as `added`, `modified` or `removed` followed by
+the key. Use `slate --output ndjson watch` to get JSON objects.
+
+Options:
+  -h --help  Show this help.
+
+Examples:
+
+  slate watch redis
+  #=> modified redis
+  #=> added redis-cluster
+";
+
+#[derive(Debug, Deserialize)]
+struct Args {
+    arg_prefix: Option<String>,
+}
+
+pub fn run(slate: &Slate,
+           argv: &Vec<String>,
+           streams: &mut Streams,
+           format: Format)
+           -> CommandResult {
+    let args: Args = try!(parse_args(USAGE, argv));
+    let prefix = args.arg_prefix.unwrap_or_default();
+
+    for event in try!(slate.subscribe(&prefix)) {
+        let event = try!(event);
+        let action = event.kind.name();
+        let text = format!("{} {}", action, event.key);
+        let message = Message::Change(Change::new(action).key(&event.key), Some(text));
+
+        try!(write!(streams.stdout, "{}", message.render(format)));
+        try!(streams.stdout.flush());
+    }
+
+    Ok(None)
+}
@@ -0,0 +1,377 @@
+//! Generation of shell completion scripts.
+//!
+//! Scripts are built from the USAGE strings of the commands, so
+//! they always cover the same subcommands and flags the program
+//! accepts. Keys are completed at runtime with `slate __complete`.
+
+use std::str::FromStr;
+
+/// Shells with completion scripts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl FromStr for Shell {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Shell, String> {
+        match s {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            _ => Err(format!("Unknown shell: {}", s)),
+        }
+    }
+}
+
+/// An option of a command, as described in its USAGE.
+#[derive(Debug, Default, PartialEq)]
+pub struct Flag {
+    pub short: Option<String>,
+    pub long: Option<String>,
+    pub description: String,
+    pub takes_value: bool,
+}
+
+impl Flag {
+    /// All the names of the flag, with dashes.
+    fn names(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        if let Some(ref short) = self.short {
+            names.push(format!("-{}", short));
+        }
+        if let Some(ref long) = self.long {
+            names.push(format!("--{}", long));
+        }
+
+        names
+    }
+}
+
+/// A subcommand with the information needed to complete it.
+#[derive(Debug)]
+pub struct Subcommand {
+    pub name: String,
+    pub description: String,
+    pub flags: Vec<Flag>,
+    /// Whether its arguments are existing keys.
+    pub keys: bool,
+}
+
+impl Subcommand {
+    pub fn new(name: &str, usage: &str, keys: bool) -> Subcommand {
+        Subcommand {
+            name: name.to_string(),
+            description: description(usage),
+            flags: options(usage),
+            keys: keys,
+        }
+    }
+}
+
+/// Get the description of a command from the first line of its USAGE.
+pub fn description(usage: &str) -> String {
+    let line = usage.lines().map(|l| l.trim()).find(|l| !l.is_empty()).unwrap_or("");
+
+    line.trim_left_matches("Slate:").trim().to_string()
+}
+
+/// Get the flags listed in the `Options:` section of a USAGE.
+///
+/// # Example
+///
+/// ```rust
+/// use slate::completions::options;
+///
+/// let usage = "
+/// Options:
+///   -h --help             Show this help.
+///   -d --default <value>  Default value.
+/// ";
+/// let flags = options(usage);
+///
+/// assert_eq!(Some("default".to_string()), flags[1].long);
+/// assert!(flags[1].takes_value);
+/// ```
+pub fn options(usage: &str) -> Vec<Flag> {
+    let mut flags = Vec::new();
+    let mut lines = usage.lines().skip_while(|l| l.trim() != "Options:").skip(1);
+
+    while let Some(line) = lines.next() {
+        let line = line.trim();
+        if line.is_empty() {
+            break;
+        }
+        if !line.starts_with('-') {
+            continue;
+        }
+
+        let (spec, description) = match line.find("  ") {
+            Some(index) => (&line[..index], line[index..].trim()),
+            None => (line, ""),
+        };
+
+        let mut flag = Flag { description: description.to_string(), ..Default::default() };
+        for token in spec.split(|c: char| c.is_whitespace() || c == ',' || c == '=') {
+            if token.starts_with("--") {
+                flag.long = Some(token[2..].to_string());
+            } else if token.starts_with('-') {
+                flag.short = Some(token[1..].to_string());
+            } else if token.starts_with('<') {
+                flag.takes_value = true;
+            }
+        }
+        flags.push(flag);
+    }
+
+    flags
+}
+
+/// Build the completion script for a shell.
+pub fn script(shell: Shell, global: &[Flag], commands: &[Subcommand]) -> String {
+    match shell {
+        Shell::Bash => bash(global, commands),
+        Shell::Zsh => zsh(global, commands),
+        Shell::Fish => fish(global, commands),
+    }
+}
+
+fn flag_names(flags: &[Flag]) -> String {
+    let names: Vec<String> = flags.iter().flat_map(|f| f.names()).collect();
+
+    names.join(" ")
+}
+
+/// Quote a string between single quotes for any shell.
+fn quote(s: &str) -> String {
+    format!("'{}'", s.replace("'", "'\\''"))
+}
+
+fn bash(global: &[Flag], commands: &[Subcommand]) -> String {
+    let names: Vec<&str> = commands.iter().map(|c| c.name.as_str()).collect();
+
+    let mut cases = String::new();
+    for command in commands {
+        cases.push_str(&format!("        {})\n            opts={}\n            keys={}\n            ;;\n",
+                                command.name,
+                                quote(&flag_names(&command.flags)),
+                                if command.keys { 1 } else { 0 }));
+    }
+
+    format!(r#"# bash completion for slate
+_slate() {{
+    local cur prev command i opts keys
+    cur="${{COMP_WORDS[COMP_CWORD]}}"
+    prev="${{COMP_WORDS[COMP_CWORD-1]}}"
+
+    if [[ "$prev" == "-o" || "$prev" == "--output" ]]; then
+        COMPREPLY=($(compgen -W "text json ndjson" -- "$cur"))
+        return
+    fi
+    if [[ "$prev" == "--color" ]]; then
+        COMPREPLY=($(compgen -W "auto always never" -- "$cur"))
+        return
+    fi
+
+    for (( i = 1; i < COMP_CWORD; i++ )); do
+        case "${{COMP_WORDS[i]}}" in
+            -o|--output|--color) (( i++ )) ;;
+            -*) ;;
+            *) command="${{COMP_WORDS[i]}}"; break ;;
+        esac
+    done
+
+    if [[ -z "$command" ]]; then
+        COMPREPLY=($(compgen -W {commands} -- "$cur"))
+        return
+    fi
+
+    opts=""
+    keys=0
+    case "$command" in
+{cases}    esac
+
+    if [[ "$cur" == -* ]]; then
+        COMPREPLY=($(compgen -W "$opts" -- "$cur"))
+    elif [[ "$keys" == 1 ]]; then
+        local IFS=$'\n'
+        COMPREPLY=($(compgen -W "$(slate __complete 2>/dev/null)" -- "$cur"))
+    fi
+}}
+complete -F _slate slate
+"#,
+            commands = quote(&format!("{} {}", names.join(" "), flag_names(global))),
+            cases = cases)
+}
+
+fn zsh(global: &[Flag], commands: &[Subcommand]) -> String {
+    let descriptions: Vec<String> = commands.iter()
+        .map(|c| format!("        {}", quote(&format!("{}:{}", c.name, c.description))))
+        .collect();
+
+    let mut cases = String::new();
+    for command in commands {
+        cases.push_str(&format!("        {})\n            opts=({})\n            keys={}\n            ;;\n",
+                                command.name,
+                                flag_names(&command.flags),
+                                if command.keys { 1 } else { 0 }));
+    }
+
+    format!(r#"#compdef slate
+_slate() {{
+    local -a commands opts
+    local command keys i
+    commands=(
+{descriptions}
+    )
+
+    if [[ "${{words[CURRENT-1]}}" == "-o" || "${{words[CURRENT-1]}}" == "--output" ]]; then
+        compadd -- text json ndjson
+        return
+    fi
+    if [[ "${{words[CURRENT-1]}}" == "--color" ]]; then
+        compadd -- auto always never
+        return
+    fi
+
+    for (( i = 2; i < CURRENT; i++ )); do
+        case "${{words[i]}}" in
+            -o|--output|--color) (( i++ )) ;;
+            -*) ;;
+            *) command="${{words[i]}}"; break ;;
+        esac
+    done
+
+    if [[ -z "$command" ]]; then
+        if [[ "${{words[CURRENT]}}" == -* ]]; then
+            compadd -- {global}
+        else
+            _describe 'command' commands
+        fi
+        return
+    fi
+
+    keys=0
+    case "$command" in
+{cases}    esac
+
+    if [[ "${{words[CURRENT]}}" == -* ]]; then
+        compadd -- $opts
+    elif (( keys )); then
+        compadd -- ${{(f)"$(slate __complete 2>/dev/null)"}}
+    fi
+}}
+compdef _slate slate
+"#,
+            descriptions = descriptions.join("\n"),
+            global = flag_names(global),
+            cases = cases)
+}
+
+fn fish(global: &[Flag], commands: &[Subcommand]) -> String {
+    let mut lines = vec!["# fish completion for slate".to_string(),
+                         "complete -c slate -f".to_string()];
+
+    for flag in global {
+        lines.push(fish_flag("__fish_use_subcommand", flag));
+    }
+
+    for command in commands {
+        lines.push(format!("complete -c slate -n __fish_use_subcommand -a {} -d {}",
+                           command.name,
+                           quote(&command.description)));
+    }
+
+    for command in commands {
+        let condition = format!("__fish_seen_subcommand_from {}", command.name);
+        for flag in &command.flags {
+            lines.push(fish_flag(&condition, flag));
+        }
+        if command.keys {
+            lines.push(format!("complete -c slate -n {} -a '(slate __complete 2>/dev/null)'",
+                               quote(&condition)));
+        }
+    }
+
+    lines.join("\n") + "\n"
+}
+
+fn fish_flag(condition: &str, flag: &Flag) -> String {
+    let mut line = format!("complete -c slate -n {}", quote(condition));
+    if let Some(ref short) = flag.short {
+        line.push_str(&format!(" -s {}", short));
+    }
+    if let Some(ref long) = flag.long {
+        line.push_str(&format!(" -l {}", long));
+    }
+    if flag.takes_value {
+        line.push_str(" -r");
+    }
+    line.push_str(&format!(" -d {}", quote(&flag.description)));
+
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USAGE: &'static str = "
+Slate: Get a value by name.
+
+Usage:
+  slate get [-n] <key>
+
+Options:
+  -h --help    Show this help.
+  -n --no-eol  Do not print the trailing newline character.
+
+Examples:
+  -x is not an option
+";
+
+    #[test]
+    fn it_reads_descriptions() {
+        assert_eq!("Get a value by name.", description(USAGE));
+    }
+
+    #[test]
+    fn it_reads_options() {
+        let flags = options(USAGE);
+
+        assert_eq!(2, flags.len());
+        assert_eq!(Flag {
+                       short: Some("n".to_string()),
+                       long: Some("no-eol".to_string()),
+                       description: "Do not print the trailing newline character.".to_string(),
+                       takes_value: false,
+                   },
+                   flags[1]);
+    }
+
+    #[test]
+    fn it_builds_scripts_with_commands_flags_and_keys() {
+        let commands = vec![Subcommand::new("get", USAGE, true)];
+
+        for shell in &[Shell::Bash, Shell::Zsh, Shell::Fish] {
+            let script = script(*shell, &[], &commands);
+
+            assert!(script.contains("get"));
+            assert!(script.contains("no-eol"));
+            assert!(script.contains("slate __complete"));
+        }
+    }
+
+    #[test]
+    fn it_skips_the_values_of_global_options() {
+        for shell in &[Shell::Bash, Shell::Zsh] {
+            let script = script(*shell, &[], &[]);
+
+            assert!(script.contains("-o|--output|--color) (( i++ ))"));
+            assert!(script.contains("auto always never"));
+        }
+    }
+}
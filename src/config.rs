@@ -1,7 +1,19 @@
 use std::env;
+use std::fmt;
 use std::path::PathBuf;
+use std::str::FromStr;
+
+use hooks;
 
 const SLATE_FILEPATH: &'static str = "SLATE_FILEPATH";
+const SLATE_BACKEND: &'static str = "SLATE_BACKEND";
+const SLATE_PROFILE: &'static str = "SLATE_PROFILE";
+const SLATE_HOOKS: &'static str = "SLATE_HOOKS";
+const SLATE_TRACKING: &'static str = "SLATE_TRACKING";
+const SLATE_THEME: &'static str = "SLATE_THEME";
+
+/// Name of the profile used when none is given.
+pub const DEFAULT_PROFILE: &'static str = "default";
 
 pub trait EnvWrapper {
     fn var(&self, var: &'static str) -> Result<String, env::VarError>;
@@ -15,11 +27,79 @@ impl EnvWrapper for Env {
     }
 }
 
+/// Storage engines available to save the contents.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Backend {
+    /// A plain JSON file.
+    Json,
+    /// An embedded SQLite database.
+    Sqlite,
+}
+
+impl Backend {
+    /// Name of the file used by default with this backend for
+    /// a profile, relative to the HOME dir.
+    fn default_filename(&self, profile: &str) -> String {
+        let name = if profile == DEFAULT_PROFILE {
+            ".slate".to_string()
+        } else {
+            format!(".slate-{}", profile)
+        };
+
+        match *self {
+            Backend::Json => name,
+            Backend::Sqlite => name + ".db",
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Backend::Json => write!(f, "json"),
+            Backend::Sqlite => write!(f, "sqlite"),
+        }
+    }
+}
+
+impl Default for Backend {
+    fn default() -> Backend {
+        Backend::Json
+    }
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Backend, String> {
+        match s {
+            "json" => Ok(Backend::Json),
+            "sqlite" => Ok(Backend::Sqlite),
+            _ => Err(format!("Unknown backend: {}", s)),
+        }
+    }
+}
+
 /// Main Configuration struct
 #[derive(Clone)]
 pub struct Config {
     /// Path to the slate file.
     pub filepath: PathBuf,
+    /// Storage engine used to save the contents.
+    pub backend: Backend,
+    /// Name of the profile, each one has its own store.
+    pub profile: String,
+    /// Directory with the hooks run on every change, if any.
+    pub hooks: Option<PathBuf>,
+    /// Where the output of the hooks is collected, instead of
+    /// stderr, so `cli::run` can write it into its streams.
+    pub hook_output: Option<hooks::Output>,
+    /// Socket of the daemon serving the store, if it is running.
+    pub socket: Option<PathBuf>,
+    /// Whether to record when keys are used.
+    pub tracking: bool,
+    /// Colors used to highlight values, see `highlight::Theme`.
+    pub theme: String,
 }
 
 impl Config {
@@ -44,7 +124,7 @@ impl Config {
     /// ```
     ///
     /// If there is no env var, the default `$HOME/.slate` value
-    /// will be used.
+    /// will be used (`$HOME/.slate.db` with the SQLite backend).
     ///
     /// # Example
     ///
@@ -57,19 +137,134 @@ impl Config {
     /// println!("{}", config.filepath.to_str().unwrap());
     /// //=> $HOME/.slate
     /// ```
+    ///
+    /// The backend is chosen with the env var `SLATE_BACKEND`, which
+    /// can be either `json` (the default) or `sqlite`. Unknown values
+    /// fall back to `json`.
+    ///
+    /// The profile is chosen with the env var `SLATE_PROFILE`. Profiles
+    /// other than `default` use their own file, like `$HOME/.slate-work`.
+    ///
+    /// Hooks are only run if the env var `SLATE_HOOKS` is set with
+    /// the directory that contains them.
+    ///
+    /// The usage of the keys is recorded unless the env var
+    /// `SLATE_TRACKING` is set to `off`, `false`, `no` or `0`.
+    ///
+    /// Values are highlighted with the theme in the env var
+    /// `SLATE_THEME`, like `light` or `keyword=1;35,comment=2`.
     pub fn from_env() -> Config {
         let wrapper = Env;
         Self::new(wrapper)
     }
 
     pub fn new<T: EnvWrapper>(wrapper: T) -> Config {
-        let config: Config = match wrapper.var(SLATE_FILEPATH) {
-            Ok(value) => Config { filepath: PathBuf::from(value) },
+        let backend: Backend = match wrapper.var(SLATE_BACKEND) {
+            Ok(value) => value.parse().unwrap_or_default(),
             Err(_) => Default::default(),
         };
 
+        let profile = match wrapper.var(SLATE_PROFILE) {
+            Ok(ref value) if !value.is_empty() => value.to_owned(),
+            _ => DEFAULT_PROFILE.to_string(),
+        };
+
+        let mut config: Config = match wrapper.var(SLATE_FILEPATH) {
+            Ok(value) => {
+                Config {
+                    filepath: PathBuf::from(value),
+                    backend: backend,
+                    profile: profile,
+                    hooks: None,
+                    hook_output: None,
+                    socket: None,
+                    tracking: true,
+                    theme: String::new(),
+                }
+            }
+            Err(_) => Self::default_for(&profile, backend),
+        };
+
+        if let Ok(value) = wrapper.var(SLATE_HOOKS) {
+            if !value.is_empty() {
+                config.hooks = Some(PathBuf::from(value));
+            }
+        }
+
+        if let Ok(value) = wrapper.var(SLATE_TRACKING) {
+            config.tracking = match value.to_lowercase().as_str() {
+                "off" | "false" | "no" | "0" => false,
+                _ => true,
+            };
+        }
+
+        if let Ok(value) = wrapper.var(SLATE_THEME) {
+            config.theme = value;
+        }
+
         config
     }
+
+    /// Get the default Config struct for the given profile
+    /// and backend.
+    ///
+    /// It panics if there is no HOME dir.
+    pub fn default_for(profile: &str, backend: Backend) -> Config {
+        let mut path = match env::home_dir() {
+            Some(home) => home,
+            None => panic!("No HOME dir found"),
+        };
+        path.push(backend.default_filename(profile));
+
+        Config {
+            filepath: path,
+            backend: backend,
+            profile: profile.to_owned(),
+            hooks: None,
+            hook_output: None,
+            socket: None,
+            tracking: true,
+            theme: String::new(),
+        }
+    }
+
+    /// Get the configuration of another profile, with its store
+    /// next to the store of this one and with the same backend.
+    pub fn for_profile(&self, profile: &str) -> Config {
+        let filepath = self.filepath.with_file_name(self.backend.default_filename(profile));
+
+        Config {
+            filepath: filepath,
+            profile: profile.to_owned(),
+            socket: None,
+            ..self.clone()
+        }
+    }
+
+    /// Get the configuration of another store file. Files with the
+    /// extension `.db`, `.sqlite` or `.sqlite3` use the SQLite
+    /// backend, and any other the JSON one.
+    pub fn for_file(&self, filepath: PathBuf) -> Config {
+        let backend = match filepath.extension().and_then(|e| e.to_str()) {
+            Some("db") | Some("sqlite") | Some("sqlite3") => Backend::Sqlite,
+            _ => Backend::Json,
+        };
+
+        Config {
+            filepath: filepath,
+            backend: backend,
+            socket: None,
+            ..self.clone()
+        }
+    }
+
+    /// Env vars that describe this configuration, so other
+    /// programs can use the same store.
+    pub fn vars(&self) -> Vec<(&'static str, String)> {
+        vec![(SLATE_FILEPATH, format!("{}", self.filepath.display())),
+             (SLATE_BACKEND, format!("{}", self.backend)),
+             (SLATE_PROFILE, self.profile.clone())]
+    }
 }
 
 impl Default for Config {
@@ -80,13 +275,7 @@ impl Default for Config {
     ///
     /// It panics if there is no HOME dir.
     fn default() -> Config {
-        let mut path = match env::home_dir() {
-            Some(home) => home,
-            None => panic!("No HOME dir found"),
-        };
-        path.push(".slate");
-
-        Config { filepath: path }
+        Self::default_for(DEFAULT_PROFILE, Default::default())
     }
 }
 
@@ -127,4 +316,103 @@ mod tests {
 
         assert_eq!(expected, config.filepath);
     }
+
+    struct BackendEnv {
+        backend: &'static str,
+    }
+
+    impl EnvWrapper for BackendEnv {
+        fn var(&self, var: &'static str) -> Result<String, env::VarError> {
+            match var {
+                "SLATE_BACKEND" => Ok(self.backend.to_string()),
+                _ => Err(env::VarError::NotPresent),
+            }
+        }
+    }
+
+    struct ProfileEnv {
+        profile: &'static str,
+    }
+
+    impl EnvWrapper for ProfileEnv {
+        fn var(&self, var: &'static str) -> Result<String, env::VarError> {
+            match var {
+                "SLATE_PROFILE" => Ok(self.profile.to_string()),
+                _ => Err(env::VarError::NotPresent),
+            }
+        }
+    }
+
+    #[test]
+    fn it_uses_a_file_per_profile() {
+        let wrapper = ProfileEnv { profile: "work" };
+        let config: Config = Config::new(wrapper);
+        let mut expected: PathBuf = env::home_dir().unwrap();
+        expected.push(".slate-work");
+
+        assert_eq!("work", config.profile);
+        assert_eq!(expected, config.filepath);
+    }
+
+    #[test]
+    fn it_uses_json_as_default_backend() {
+        let wrapper = MockEnv { value: None };
+        let config: Config = Config::new(wrapper);
+
+        assert_eq!(Backend::Json, config.backend);
+    }
+
+    #[test]
+    fn it_uses_the_backend_from_the_environment() {
+        let wrapper = BackendEnv { backend: "sqlite" };
+        let config: Config = Config::new(wrapper);
+        let mut expected: PathBuf = env::home_dir().unwrap();
+        expected.push(".slate.db");
+
+        assert_eq!(Backend::Sqlite, config.backend);
+        assert_eq!(expected, config.filepath);
+    }
+
+    #[test]
+    fn it_ignores_unknown_backends() {
+        let wrapper = BackendEnv { backend: "postgres" };
+        let config: Config = Config::new(wrapper);
+
+        assert_eq!(Backend::Json, config.backend);
+    }
+
+    struct TrackingEnv {
+        tracking: &'static str,
+    }
+
+    impl EnvWrapper for TrackingEnv {
+        fn var(&self, var: &'static str) -> Result<String, env::VarError> {
+            match var {
+                "SLATE_TRACKING" => Ok(self.tracking.to_string()),
+                _ => Err(env::VarError::NotPresent),
+            }
+        }
+    }
+
+    #[test]
+    fn it_tracks_usage_unless_disabled() {
+        assert!(Config::new(MockEnv { value: None }).tracking);
+        assert!(Config::new(TrackingEnv { tracking: "on" }).tracking);
+        assert!(!Config::new(TrackingEnv { tracking: "off" }).tracking);
+        assert!(!Config::new(TrackingEnv { tracking: "0" }).tracking);
+    }
+
+    #[test]
+    fn it_builds_configs_for_other_stores() {
+        let config = Config::default().for_file(PathBuf::from("/tmp/slate/store.json"));
+        assert_eq!(Backend::Json, config.backend);
+
+        let work = config.for_profile("work");
+        assert_eq!(PathBuf::from("/tmp/slate/.slate-work"), work.filepath);
+        assert_eq!("work", work.profile);
+
+        let db = config.for_file(PathBuf::from("/tmp/slate/other.db"));
+        assert_eq!(Backend::Sqlite, db.backend);
+        assert_eq!(PathBuf::from("/tmp/slate/.slate-work.db"), db.for_profile("work").filepath);
+    }
 }
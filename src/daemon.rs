@@ -0,0 +1,793 @@
+//! A daemon that serves a store to other programs.
+//!
+//! The daemon listens on a Unix socket, and optionally on a TCP
+//! address, and speaks JSON-RPC 2.0 with one message per line. The
+//! methods are `get`, `set`, `list`, `remove`, `rename` and `search`,
+//! which work like the ones of `Slate`, plus `entry` to get a value
+//! with its attributes, `raw` to get an entry as it is saved, without
+//! following aliases or links, `update` to change an entry, `gc`,
+//! `read`, `write` and `commit` to work with the whole contents, and
+//! `subscribe` to get a `changed` notification every time the store
+//! file changes.
+//!
+//! Requests are handled one at a time, so writers never overlap. An
+//! `update` or a `commit` with the `expected` entries of the keys it
+//! changes is only applied if nobody changed them since they were
+//! read.
+//!
+//! The Unix socket is protected by its permissions. TCP connections
+//! must call `authenticate` with the token of the daemon before any
+//! other method, and the address must be a loopback one, like
+//! `127.0.0.1:7777`.
+//!
+//! The output of the hooks run for a failed change is sent back in
+//! the error, so clients can show why a hook rejected it.
+
+use serde_json::{self, Value};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use config::Config;
+use errors::SlateError;
+use hooks;
+use results::SlateResult;
+use store::{self, Contents, Entry};
+use Slate;
+
+const JSONRPC: &'static str = "2.0";
+
+const PARSE_ERROR: i32 = -32700;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+const SERVER_ERROR: i32 = -32000;
+const UNAUTHORIZED: i32 = -32001;
+
+/// How often the store file is checked for changes.
+const POLL_INTERVAL_MS: u64 = 500;
+
+/// Parameters of the methods. Each method uses only some of them.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Params {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    /// The value with its attributes, instead of just `value`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entry: Option<Entry>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub src: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dst: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub force: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contents: Option<Contents>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub touched: Option<HashSet<String>>,
+    /// The entries that the keys must still have for an `update` or
+    /// a `commit` to be applied. Missing keys must not exist.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected: Option<Contents>,
+    /// The token of the daemon, to `authenticate` TCP connections.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Request {
+    jsonrpc: String,
+    id: Option<Value>,
+    method: String,
+    params: Option<Params>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Response {
+    jsonrpc: String,
+    id: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    method: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+}
+
+#[derive(Debug, Serialize)]
+struct Notification {
+    jsonrpc: String,
+    method: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct RpcError {
+    code: i32,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<ErrorData>,
+}
+
+/// Details to rebuild a `SlateError` in the client.
+#[derive(Debug, Deserialize, Serialize)]
+struct ErrorData {
+    kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+    /// Output of the hooks run for the change.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output: Option<String>,
+}
+
+impl RpcError {
+    fn new(code: i32, message: &str) -> RpcError {
+        RpcError {
+            code: code,
+            message: message.to_string(),
+            data: None,
+        }
+    }
+
+    fn into_slate_error(self) -> SlateError {
+        let (kind, detail) = match self.data {
+            Some(data) => (data.kind, data.detail.unwrap_or_default()),
+            None => (String::new(), String::new()),
+        };
+
+        match kind.as_str() {
+            "not_found" => SlateError::NotFound(detail),
+            "conflict" => SlateError::Conflict(detail),
+            "changed" => SlateError::Changed(detail),
+            "binary" => SlateError::Binary(detail),
+            "cycle" => SlateError::Cycle(detail.lines().map(|key| key.to_owned()).collect()),
+            "corrupted" => SlateError::Corrupted(detail),
+            "lock_timeout" => SlateError::LockTimeout,
+            "vetoed" => SlateError::Vetoed(detail),
+            _ => SlateError::IO(io::Error::new(io::ErrorKind::Other, self.message)),
+        }
+    }
+}
+
+impl From<SlateError> for RpcError {
+    fn from(err: SlateError) -> RpcError {
+        let detail = match err {
+            SlateError::NotFound(ref s) |
+            SlateError::Conflict(ref s) |
+            SlateError::Changed(ref s) |
+            SlateError::Binary(ref s) |
+            SlateError::Corrupted(ref s) |
+            SlateError::Vetoed(ref s) => Some(s.clone()),
+            SlateError::Cycle(ref keys) => Some(keys.join("\n")),
+            _ => None,
+        };
+
+        RpcError {
+            code: SERVER_ERROR,
+            message: format!("{}", err),
+            data: Some(ErrorData {
+                kind: err.kind().to_string(),
+                detail: detail,
+                output: None,
+            }),
+        }
+    }
+}
+
+impl From<serde_json::Error> for RpcError {
+    fn from(err: serde_json::Error) -> RpcError {
+        From::from(SlateError::from(err))
+    }
+}
+
+/// Path of the socket used by default to serve a store.
+pub fn socket_path(config: &Config) -> PathBuf {
+    let mut name = config.filepath.clone().into_os_string();
+    name.push(".sock");
+
+    PathBuf::from(name)
+}
+
+/// Whether there is a daemon listening on the socket.
+pub fn is_running(socket: &Path) -> bool {
+    socket.exists() && UnixStream::connect(socket).is_ok()
+}
+
+/// A connection to a daemon.
+pub struct Client {
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+    next_id: u64,
+    hook_output: Option<hooks::Output>,
+}
+
+impl Client {
+    pub fn connect(socket: &Path) -> SlateResult<Client> {
+        let writer = try!(UnixStream::connect(socket));
+        let reader = BufReader::new(try!(writer.try_clone()));
+
+        Ok(Client {
+            reader: reader,
+            writer: writer,
+            next_id: 1,
+            hook_output: None,
+        })
+    }
+
+    /// Collect the output of the hooks of failed calls in a buffer,
+    /// like `Config::hook_output`, instead of stderr.
+    pub fn hook_output(self, output: Option<hooks::Output>) -> Client {
+        Client { hook_output: output, ..self }
+    }
+
+    /// Call a method and wait for its result.
+    pub fn call(&mut self, method: &str, params: Params) -> SlateResult<Value> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let request = Request {
+            jsonrpc: JSONRPC.to_string(),
+            id: Some(Value::from(id)),
+            method: method.to_string(),
+            params: Some(params),
+        };
+        try!(send(&mut self.writer, &request));
+
+        loop {
+            let response = try!(self.receive());
+            if response.id != Some(Value::from(id)) {
+                continue;
+            }
+
+            return match response.error {
+                Some(error) => {
+                    let output = error.data.as_ref().and_then(|data| data.output.as_ref());
+                    if let Some(output) = output {
+                        try!(hooks::forward(self.hook_output.as_ref(), output.as_bytes()));
+                    }
+                    Err(error.into_slate_error())
+                }
+                None => Ok(response.result.unwrap_or(Value::Null)),
+            };
+        }
+    }
+
+    /// Ask the daemon for notifications about changes.
+    pub fn subscribe(&mut self) -> SlateResult<()> {
+        try!(self.call("subscribe", Default::default()));
+
+        Ok(())
+    }
+
+    /// Wait until the daemon notifies a change in the store.
+    pub fn wait_change(&mut self) -> SlateResult<()> {
+        loop {
+            let response = try!(self.receive());
+            if response.method.as_ref().map(|m| m.as_str()) == Some("changed") {
+                return Ok(());
+            }
+        }
+    }
+
+    fn receive(&mut self) -> SlateResult<Response> {
+        let mut line = String::new();
+        if try!(self.reader.read_line(&mut line)) == 0 {
+            return Err(SlateError::IO(io::Error::new(io::ErrorKind::UnexpectedEof,
+                                                     "The daemon closed the connection")));
+        }
+
+        Ok(try!(serde_json::from_str(&line)))
+    }
+}
+
+/// Streams the daemon can talk through.
+trait Connection: io::Read + Write + Send + 'static {
+    fn duplicate(&self) -> io::Result<Box<Write + Send>>;
+}
+
+impl Connection for UnixStream {
+    fn duplicate(&self) -> io::Result<Box<Write + Send>> {
+        Ok(Box::new(try!(self.try_clone())))
+    }
+}
+
+impl Connection for TcpStream {
+    fn duplicate(&self) -> io::Result<Box<Write + Send>> {
+        Ok(Box::new(try!(self.try_clone())))
+    }
+}
+
+struct Server {
+    config: Config,
+    token: Option<String>,
+    lock: Mutex<()>,
+    subscribers: Mutex<Vec<Box<Write + Send>>>,
+}
+
+/// Check that a TCP address only resolves to loopback addresses,
+/// since anyone who can connect can change the store.
+pub fn check_loopback(address: &str) -> SlateResult<()> {
+    let addresses: Vec<_> = try!(address.to_socket_addrs()).collect();
+
+    if addresses.is_empty() || addresses.iter().any(|a| !a.ip().is_loopback()) {
+        let message = format!("Refusing to listen on {}, only loopback addresses like \
+                               127.0.0.1 are allowed",
+                              address);
+        return Err(SlateError::IO(io::Error::new(io::ErrorKind::InvalidInput, message)));
+    }
+
+    Ok(())
+}
+
+/// Serve the store of the configuration until the process ends.
+///
+/// It fails if another daemon is already listening on the socket.
+/// A loopback TCP address, like `127.0.0.1:7777`, can be given to
+/// listen on it too, with the token its clients must send.
+pub fn serve(config: &Config, socket: &Path, tcp: Option<&str>, token: Option<&str>) -> SlateResult<()> {
+    if let Some(address) = tcp {
+        try!(check_loopback(address));
+        if token.is_none() {
+            let message = format!("Refusing to listen on {} without a token", address);
+            return Err(SlateError::IO(io::Error::new(io::ErrorKind::InvalidInput, message)));
+        }
+    }
+    if is_running(socket) {
+        let message = format!("A daemon is already running on {}", socket.display());
+        return Err(SlateError::IO(io::Error::new(io::ErrorKind::AddrInUse, message)));
+    }
+    // A socket left by a daemon that is not running anymore.
+    let _ = fs::remove_file(socket);
+
+    let listener = try!(UnixListener::bind(socket));
+    let server = Arc::new(Server {
+        config: Config { socket: None, ..config.clone() },
+        token: token.map(|token| token.to_owned()),
+        lock: Mutex::new(()),
+        subscribers: Mutex::new(Vec::new()),
+    });
+
+    if let Some(address) = tcp {
+        let listener = try!(TcpListener::bind(address));
+        let server = server.clone();
+        thread::spawn(move || for stream in listener.incoming().filter_map(|s| s.ok()) {
+            accept(&server, stream, false);
+        });
+    }
+
+    {
+        let server = server.clone();
+        thread::spawn(move || watch(&server));
+    }
+
+    for stream in listener.incoming() {
+        accept(&server, try!(stream), true);
+    }
+
+    Ok(())
+}
+
+/// Handle a connection in its own thread. Connections that are not
+/// `authenticated` must send the token first.
+fn accept<C: Connection>(server: &Arc<Server>, stream: C, authenticated: bool) {
+    let server = server.clone();
+    thread::spawn(move || {
+        let _ = handle(&server, stream, authenticated);
+    });
+}
+
+fn handle<C: Connection>(server: &Server, stream: C, mut authenticated: bool) -> io::Result<()> {
+    let mut writer = try!(stream.duplicate());
+    let mut reader = BufReader::new(stream);
+
+    loop {
+        let mut line = String::new();
+        if try!(reader.read_line(&mut line)) == 0 {
+            break;
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let (id, outcome) = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => {
+                let outcome = if request.method == "authenticate" {
+                    let token = request.params.and_then(|params| params.token);
+                    if token.is_some() && token == server.token {
+                        authenticated = true;
+                        Ok(Value::Bool(true))
+                    } else {
+                        Err(RpcError::new(UNAUTHORIZED, "Invalid token"))
+                    }
+                } else if !authenticated {
+                    Err(RpcError::new(UNAUTHORIZED, "Missing token, call authenticate first"))
+                } else if request.method == "subscribe" {
+                    let subscriber = try!(reader.get_ref().duplicate());
+                    server.subscribers.lock().unwrap_or_else(PoisonError::into_inner).push(subscriber);
+                    Ok(Value::Bool(true))
+                } else {
+                    call(server, &request.method, request.params.unwrap_or_default())
+                };
+                (request.id, outcome)
+            }
+            Err(e) => (None, Err(RpcError::new(PARSE_ERROR, &format!("{}", e)))),
+        };
+
+        let response = match outcome {
+            Ok(result) => response(id, Some(result), None),
+            Err(error) => response(id, None, Some(error)),
+        };
+        if send(&mut writer, &response).is_err() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+fn response(id: Option<Value>, result: Option<Value>, error: Option<RpcError>) -> Response {
+    Response {
+        jsonrpc: JSONRPC.to_string(),
+        id: id,
+        method: None,
+        result: result,
+        error: error,
+    }
+}
+
+fn send<W: Write + ?Sized, T: ::serde::Serialize>(writer: &mut W, message: &T) -> io::Result<()> {
+    let encoded = try!(serde_json::to_string(message)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)));
+    // A single write, so responses and notifications don't mix.
+    try!(writer.write_all((encoded + "\n").as_bytes()));
+
+    writer.flush()
+}
+
+/// Call a method, one at a time. The output of the hooks goes in
+/// the error when the call fails, and to stderr otherwise.
+fn call(server: &Server, method: &str, params: Params) -> Result<Value, RpcError> {
+    let output: hooks::Output = Default::default();
+    let config = Config { hook_output: Some(output.clone()), ..server.config.clone() };
+    let result = {
+        // A request that panicked doesn't stop the others.
+        let _guard = server.lock.lock().unwrap_or_else(PoisonError::into_inner);
+        dispatch(&config, method, params)
+    };
+    let output = output.lock().unwrap_or_else(PoisonError::into_inner).clone();
+
+    match result {
+        Ok(value) => {
+            try!(io::stderr().write_all(&output).map_err(SlateError::from));
+            Ok(value)
+        }
+        Err(mut error) => {
+            if let Some(ref mut data) = error.data {
+                if !output.is_empty() {
+                    data.output = Some(String::from_utf8_lossy(&output).into_owned());
+                }
+            }
+            Err(error)
+        }
+    }
+}
+
+fn dispatch(config: &Config, method: &str, params: Params) -> Result<Value, RpcError> {
+    let slate: Slate = From::from(config);
+
+    let value = match method {
+        "get" => Value::String(try!(slate.get(&try!(param(params.key, "key"))))),
+        "entry" => try!(serde_json::to_value(try!(slate.entry(&try!(param(params.key, "key")))))),
+        "raw" => {
+            let key = try!(param(params.key, "key"));
+            try!(serde_json::to_value(try!(try!(store::open(config)).get(&key))))
+        }
+        "set" => {
+            let key = try!(param(params.key, "key"));
+            let entry = match params.entry {
+                Some(entry) => entry,
+                None => Entry::new(&try!(param(params.value, "value"))),
+            };
+            try!(slate.set_entry(&key, &entry));
+            Value::Null
+        }
+        "update" => {
+            let key = try!(param(params.key, "key"));
+            let entry = try!(param(params.entry, "entry"));
+            let expected = try!(param(params.expected, "expected"));
+            try!(slate.update_key(&key, |current| {
+                if current != expected.get(&key) {
+                    return Err(SlateError::Changed(key.clone()));
+                }
+
+                Ok(entry.clone())
+            }));
+            Value::Null
+        }
+        "gc" => Value::from(try!(slate.gc())),
+        "remove" => {
+            try!(slate.remove(&try!(param(params.key, "key"))));
+            Value::Null
+        }
+        "rename" => {
+            try!(slate.rename(&try!(param(params.src, "src")),
+                              &try!(param(params.dst, "dst")),
+                              params.force.unwrap_or(false)));
+            Value::Null
+        }
+        "list" => keys(try!(slate.list())),
+        "search" => keys(try!(slate.search(&try!(param(params.query, "query"))))),
+        "read" => try!(serde_json::to_value(try!(try!(store::open(config)).read()))),
+        "write" => {
+            let contents = try!(param(params.contents, "contents"));
+            try!(try!(store::open(config)).write(&contents));
+            Value::Null
+        }
+        "commit" => {
+            let contents = try!(param(params.contents, "contents"));
+            let touched = try!(param(params.touched, "touched"));
+            match params.expected {
+                Some(expected) => try!(compare_and_commit(&slate, &contents, &touched, &expected)),
+                None => try!(try!(store::open(config)).commit(&contents, &touched)),
+            }
+            Value::Null
+        }
+        _ => return Err(RpcError::new(METHOD_NOT_FOUND, &format!("Unknown method: {}", method))),
+    };
+
+    Ok(value)
+}
+
+/// Apply the changes of a client's transaction if the keys still
+/// have the entries it read, with the hooks and the history of
+/// any other transaction.
+fn compare_and_commit(slate: &Slate,
+                      contents: &Contents,
+                      touched: &HashSet<String>,
+                      expected: &Contents)
+                      -> SlateResult<()> {
+    slate.transaction(|tx| {
+        for key in touched {
+            if tx.entry(key) != expected.get(key) {
+                return Err(SlateError::Changed(key.to_owned()));
+            }
+        }
+        for key in touched {
+            match contents.get(key) {
+                Some(entry) => tx.set_entry(key, entry),
+                None => tx.remove(key),
+            }
+        }
+
+        Ok(())
+    })
+}
+
+fn param<T>(value: Option<T>, name: &str) -> Result<T, RpcError> {
+    value.ok_or_else(|| RpcError::new(INVALID_PARAMS, &format!("Missing param: {}", name)))
+}
+
+fn keys(keys: Vec<String>) -> Value {
+    Value::Array(keys.into_iter().map(Value::String).collect())
+}
+
+/// Notify the subscribers every time the store file changes.
+fn watch(server: &Server) {
+    let stamp = |path: &Path| -> Option<(SystemTime, u64)> {
+        fs::metadata(path).ok().and_then(|m| m.modified().ok().map(|t| (t, m.len())))
+    };
+    let mut last = stamp(&server.config.filepath);
+
+    loop {
+        thread::sleep(Duration::from_millis(POLL_INTERVAL_MS));
+
+        let current = stamp(&server.config.filepath);
+        if current == last {
+            continue;
+        }
+        last = current;
+
+        let notification = Notification {
+            jsonrpc: JSONRPC.to_string(),
+            method: "changed".to_string(),
+        };
+        let mut subscribers = server.subscribers.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut alive = Vec::new();
+        for mut subscriber in subscribers.drain(..) {
+            if send(&mut subscriber, &notification).is_ok() {
+                alive.push(subscriber);
+            }
+        }
+        *subscribers = alive;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use store::Store;
+    use store::remote::RemoteStore;
+    use rand::{thread_rng, Rng};
+
+    fn start() -> (Config, PathBuf) {
+        let random_name: String = thread_rng().gen_ascii_chars().take(10).collect();
+        let mut filepath = env::temp_dir();
+        filepath.push(random_name + ".json");
+
+        let config = Config { filepath: filepath, ..Default::default() };
+        let socket = socket_path(&config);
+        {
+            let (config, socket) = (config.clone(), socket.clone());
+            thread::spawn(move || serve(&config, &socket, None, None));
+        }
+        while !is_running(&socket) {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        (config, socket)
+    }
+
+    fn key(key: &str) -> Params {
+        Params { key: Some(key.to_string()), ..Default::default() }
+    }
+
+    #[test]
+    fn it_serves_the_store() {
+        let (config, socket) = start();
+        let mut client = Client::connect(&socket).unwrap();
+
+        let params = Params { value: Some("bar".to_string()), ..key("foo") };
+        client.call("set", params).unwrap();
+
+        assert_eq!(Value::from("bar"), client.call("get", key("foo")).unwrap());
+        assert_eq!("bar", Slate::from(&config).get(&"foo".to_string()).unwrap());
+
+        match client.call("get", key("missing")) {
+            Err(SlateError::NotFound(ref key)) => assert_eq!("missing", key),
+            other => panic!("Expected a missing key, got {:?}", other),
+        }
+        assert!(client.call("unknown", Default::default()).is_err());
+    }
+
+    #[test]
+    fn it_refuses_to_serve_twice() {
+        let (config, socket) = start();
+
+        assert!(serve(&config, &socket, None, None).is_err());
+        assert!(serve(&config, &socket, Some("127.0.0.1:7777"), None).is_err());
+    }
+
+    #[test]
+    fn it_needs_the_token_in_tcp_connections() {
+        let config = Config { filepath: env::temp_dir().join("unused.json"), ..Default::default() };
+        let server = Arc::new(Server {
+            config: config,
+            token: Some("secret".to_string()),
+            lock: Mutex::new(()),
+            subscribers: Mutex::new(Vec::new()),
+        });
+        let (client, stream) = UnixStream::pair().unwrap();
+        accept(&server, stream, false);
+        let mut client = Client {
+            reader: BufReader::new(client.try_clone().unwrap()),
+            writer: client,
+            next_id: 1,
+            hook_output: None,
+        };
+
+        assert!(client.call("list", Default::default()).is_err());
+        let token = |token: &str| Params { token: Some(token.to_string()), ..Default::default() };
+        assert!(client.call("authenticate", token("wrong")).is_err());
+        assert!(client.call("list", Default::default()).is_err());
+        assert_eq!(Value::Bool(true), client.call("authenticate", token("secret")).unwrap());
+        assert!(client.call("list", Default::default()).is_ok());
+    }
+
+    #[test]
+    fn it_only_listens_on_loopback_addresses() {
+        assert!(check_loopback("127.0.0.1:7777").is_ok());
+        assert!(check_loopback("[::1]:7777").is_ok());
+        assert!(check_loopback("0.0.0.0:7777").is_err());
+        assert!(check_loopback("192.168.1.1:7777").is_err());
+    }
+
+    #[test]
+    fn it_gets_raw_entries() {
+        let (config, socket) = start();
+        let slate = Slate::from(&config);
+        slate.set(&"target".to_string(), &"bar".to_string()).unwrap();
+        slate.alias(&"foo".to_string(), &"target".to_string(), false).unwrap();
+        let mut client = Client::connect(&socket).unwrap();
+
+        let raw = client.call("raw", key("foo")).unwrap();
+        assert_eq!(Entry::alias("target"), serde_json::from_value(raw).unwrap());
+        assert_eq!(Value::Null, client.call("raw", key("missing")).unwrap());
+    }
+
+    #[test]
+    fn it_refuses_commits_of_changed_keys() {
+        let (config, socket) = start();
+        Slate::from(&config).set(&"foo".to_string(), &"new".to_string()).unwrap();
+        let mut client = Client::connect(&socket).unwrap();
+
+        let mut contents = Contents::new();
+        contents.insert("foo".to_string(), Entry::new("mine"));
+        let mut expected = Contents::new();
+        expected.insert("foo".to_string(), Entry::new("old"));
+        let params = Params {
+            contents: Some(contents.clone()),
+            touched: Some(vec!["foo".to_string()].into_iter().collect()),
+            expected: Some(expected),
+            ..Default::default()
+        };
+        match client.call("commit", params) {
+            Err(SlateError::Changed(ref key)) => assert_eq!("foo", key),
+            other => panic!("Expected a changed key, got {:?}", other),
+        }
+        assert_eq!("new", Slate::from(&config).get(&"foo".to_string()).unwrap());
+
+        let params = Params {
+            contents: Some(contents),
+            touched: Some(vec!["foo".to_string()].into_iter().collect()),
+            expected: Some(store::open(&config).unwrap().read().unwrap()),
+            ..Default::default()
+        };
+        client.call("commit", params).unwrap();
+        assert_eq!("mine", Slate::from(&config).get(&"foo".to_string()).unwrap());
+    }
+
+    #[test]
+    fn it_updates_entries_of_remote_stores() {
+        let (config, socket) = start();
+        let store = RemoteStore::new(&socket);
+
+        let lines: Vec<thread::JoinHandle<()>> = (0..4)
+            .map(|n| {
+                let socket = socket.clone();
+                thread::spawn(move || {
+                    RemoteStore::new(&socket)
+                        .update("log", &mut |old| {
+                            let value = old.map(|e| e.value.clone()).unwrap_or_default();
+                            Ok(Entry::new(&format!("{}{}\n", value, n)))
+                        })
+                        .unwrap();
+                })
+            })
+            .collect();
+        for line in lines {
+            line.join().unwrap();
+        }
+        assert_eq!(4, store.get("log").unwrap().unwrap().value.lines().count());
+
+        let params = Params {
+            entry: Some(Entry::new("mine")),
+            expected: Some(Contents::new()),
+            ..key("log")
+        };
+        match Client::connect(&socket).unwrap().call("update", params) {
+            Err(SlateError::Changed(ref key)) => assert_eq!("log", key),
+            other => panic!("Expected a changed key, got {:?}", other),
+        }
+        assert_eq!(4, Slate::from(&config).get(&"log".to_string()).unwrap().lines().count());
+    }
+
+    #[test]
+    fn it_notifies_changes() {
+        let (config, socket) = start();
+        let mut subscriber = Client::connect(&socket).unwrap();
+        subscriber.subscribe().unwrap();
+
+        Slate::from(&config).set(&"foo".to_string(), &"bar".to_string()).unwrap();
+
+        subscriber.wait_change().unwrap();
+    }
+}
@@ -0,0 +1,250 @@
+//! Find keys with the same, or almost the same, value.
+//!
+//! Exact duplicates are found by the hash of their values. Near
+//! duplicates are found comparing the values with the Levenshtein
+//! distance, as a ratio of the length of the longest one, so the
+//! values of different keys are compared once, and values whose
+//! lengths are too different are not compared. Near duplicates are
+//! grouped only if all their values are similar to each other, so a
+//! chain of small changes doesn't end up in a single group.
+
+use std::collections::hash_map::DefaultHasher;
+use std::cmp::{max, min};
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::mem;
+use strsim;
+
+use store::Contents;
+
+/// Keys with the same or similar values.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Group {
+    /// The keys, sorted.
+    pub keys: Vec<String>,
+    /// The lowest similarity between the values of the keys, from
+    /// 0 to 1. Exact duplicates have a similarity of 1.
+    pub similarity: f64,
+}
+
+impl Group {
+    /// Whether all the keys have the same value.
+    pub fn is_exact(&self) -> bool {
+        self.similarity >= 1.0
+    }
+}
+
+/// Similarity of two values, from 0 for completely different ones
+/// to 1 for equal ones.
+///
+/// # Example
+///
+/// ```rust
+/// use slate::dedupe;
+///
+/// assert_eq!(1.0, dedupe::similarity("kubectl get pods", "kubectl get pods"));
+/// assert_eq!(0.9375, dedupe::similarity("kubectl get pods", "kubectl get pod"));
+/// ```
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let longest = max(a.chars().count(), b.chars().count());
+    if longest == 0 {
+        return 1.0;
+    }
+
+    1.0 - strsim::levenshtein(a, b) as f64 / longest as f64
+}
+
+/// Find the groups of keys with the same value, and then the groups
+/// of values with at least the given similarity. Near duplicates
+/// are found between the first keys of the exact groups and the rest
+/// of keys, and binary values are only compared by their hash.
+/// Every pair of values in a near group has at least the given
+/// similarity. Aliases are not duplicates.
+///
+/// Groups are sorted by their first key, exact ones first.
+pub fn duplicates(contents: &Contents, now: u64, threshold: f64) -> Vec<Group> {
+    let mut keys: Vec<&String> = contents.iter()
+        .filter(|&(_, entry)| !entry.is_expired(now) && !entry.is_alias())
+        .map(|(key, _)| key)
+        .collect();
+    keys.sort();
+
+    let mut hashes: HashMap<(u64, &str), Vec<String>> = HashMap::new();
+    let mut distinct: Vec<&String> = Vec::new();
+    for key in keys {
+        let value = &contents[key].value;
+        let keys = hashes.entry((hash(value), value)).or_insert_with(Vec::new);
+        if keys.is_empty() {
+            distinct.push(key);
+        }
+        keys.push(key.clone());
+    }
+
+    let mut exact: Vec<Group> = hashes.into_iter()
+        .map(|(_, keys)| keys)
+        .filter(|keys| keys.len() > 1)
+        .map(|keys| Group { keys: keys, similarity: 1.0 })
+        .collect();
+    exact.sort_by(|a, b| a.keys.cmp(&b.keys));
+
+    let texts: Vec<&String> = distinct.into_iter().filter(|key| !contents[*key].is_binary()).collect();
+    let lengths: Vec<usize> = texts.iter().map(|key| contents[*key].value.chars().count()).collect();
+    // Only the links of near values are kept, for each value. Values
+    // whose lengths differ too much cannot be near, so they are not
+    // compared.
+    let mut links: Vec<HashMap<usize, f64>> = vec![HashMap::new(); texts.len()];
+    for i in 0..texts.len() {
+        for j in (i + 1)..texts.len() {
+            // The distance is at least the difference of the lengths.
+            let (shortest, longest) = (min(lengths[i], lengths[j]), max(lengths[i], lengths[j]));
+            let highest = if longest == 0 { 1.0 } else { 1.0 - (longest - shortest) as f64 / longest as f64 };
+            if highest < threshold {
+                continue;
+            }
+            let ratio = similarity(&contents[texts[i]].value, &contents[texts[j]].value);
+            if ratio >= threshold {
+                links[i].insert(j, ratio);
+                links[j].insert(i, ratio);
+            }
+        }
+    }
+
+    // Each key starts in its own group, and the two most similar
+    // groups are joined while all their values are similar enough.
+    // The link of two groups is the lowest similarity between their
+    // values, so every pair of values in a group is near, and groups
+    // without a link are not.
+    let mut groups: Vec<Vec<usize>> = (0..texts.len()).map(|i| vec![i]).collect();
+    let mut lowest: Vec<f64> = vec![1.0; texts.len()];
+    loop {
+        let mut best: Option<(usize, usize, f64)> = None;
+        for a in 0..links.len() {
+            for (&b, &link) in links[a].iter().filter(|&(&b, _)| b > a) {
+                let better = match best {
+                    Some((i, j, most)) => link > most || (link == most && (a, b) < (i, j)),
+                    None => true,
+                };
+                if better {
+                    best = Some((a, b, link));
+                }
+            }
+        }
+        let (a, b, link) = match best {
+            Some(best) => best,
+            None => break,
+        };
+
+        let joined = mem::replace(&mut groups[b], Vec::new());
+        groups[a].extend(joined);
+        lowest[a] = lowest[a].min(lowest[b]).min(link);
+
+        let (old_a, old_b) = (mem::replace(&mut links[a], HashMap::new()),
+                              mem::replace(&mut links[b], HashMap::new()));
+        for other in old_a.keys().chain(old_b.keys()) {
+            links[*other].remove(&a);
+            links[*other].remove(&b);
+        }
+        for (other, link_a) in old_a {
+            if let Some(&link_b) = old_b.get(&other) {
+                let link = link_a.min(link_b);
+                links[a].insert(other, link);
+                links[other].insert(a, link);
+            }
+        }
+        links[a].remove(&b);
+    }
+
+    let mut near: Vec<Group> = groups.into_iter()
+        .zip(lowest)
+        .filter(|&(ref indexes, _)| indexes.len() > 1)
+        .map(|(mut indexes, lowest)| {
+            indexes.sort();
+            Group {
+                keys: indexes.into_iter().map(|i| texts[i].clone()).collect(),
+                similarity: lowest,
+            }
+        })
+        .collect();
+    near.sort_by(|a, b| a.keys.cmp(&b.keys));
+
+    exact.extend(near);
+
+    exact
+}
+
+fn hash(value: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use store::Entry;
+
+    #[test]
+    fn it_finds_duplicates() {
+        let mut contents = Contents::new();
+        for &(key, value) in &[("pods", "kubectl get pods -A"),
+                               ("k8s/pods", "kubectl get pods -A"),
+                               ("all-pods", "kubectl get pods -A"),
+                               ("nodes", "kubectl get nodes -o wide"),
+                               ("pod", "kubectl get pod -A"),
+                               ("vpn", "sudo openvpn work.ovpn"),
+                               ("work-vpn", "sudo openvpn work.ovpn")] {
+            contents.insert(key.to_string(), Entry::new(value));
+        }
+        let expired = Entry { expires: Some(1), ..Entry::new("sudo openvpn work.ovpn") };
+        contents.insert("old".to_string(), expired);
+        contents.insert("p".to_string(), Entry::alias("pods"));
+        contents.insert("v".to_string(), Entry::alias("vpn"));
+
+        let groups = duplicates(&contents, 10, 0.9);
+
+        assert_eq!(vec![Group {
+                            keys: vec!["all-pods".to_string(), "k8s/pods".to_string(), "pods".to_string()],
+                            similarity: 1.0,
+                        },
+                        Group {
+                            keys: vec!["vpn".to_string(), "work-vpn".to_string()],
+                            similarity: 1.0,
+                        },
+                        Group {
+                            keys: vec!["all-pods".to_string(), "pod".to_string()],
+                            similarity: 1.0 - 1.0 / 19.0,
+                        }],
+                   groups);
+        assert!(groups[0].is_exact());
+        assert!(!groups[2].is_exact());
+    }
+
+    #[test]
+    fn it_joins_similar_values() {
+        let mut contents = Contents::new();
+        contents.insert("a".to_string(), Entry::new("make deploy ENV=prod"));
+        contents.insert("b".to_string(), Entry::new("make deploy ENV=pro"));
+        contents.insert("c".to_string(), Entry::new("make deploy ENV=pr"));
+
+        let groups = duplicates(&contents, 0, 0.9);
+
+        assert_eq!(1, groups.len());
+        assert_eq!(vec!["a", "b", "c"], groups[0].keys);
+        assert_eq!(0.9, groups[0].similarity);
+    }
+
+    #[test]
+    fn it_does_not_join_chains_of_similar_values() {
+        let mut contents = Contents::new();
+        contents.insert("a".to_string(), Entry::new("make deploy ENV=prod"));
+        contents.insert("b".to_string(), Entry::new("make deploy ENV=pro"));
+        contents.insert("c".to_string(), Entry::new("make deploy ENV=pr"));
+
+        // b is near a and c, but a and c are not near each other.
+        let groups = duplicates(&contents, 0, 0.94);
+
+        assert_eq!(vec![Group { keys: vec!["a".to_string(), "b".to_string()], similarity: 0.95 }],
+                   groups);
+    }
+}
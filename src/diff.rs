@@ -0,0 +1,310 @@
+//! Differences between values and between stores.
+//!
+//! Values are compared by lines, finding their longest common
+//! subsequence, and shown as a unified diff like `diff -u` does.
+
+use Slate;
+use results::SlateResult;
+use store::{self, Contents};
+use time;
+
+/// Lines of context around the changes of a unified diff.
+pub const CONTEXT: usize = 3;
+
+/// A line of a diff.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Line<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Keys that differ between two stores.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Changes {
+    /// Keys only in the second store.
+    pub added: Vec<String>,
+    /// Keys only in the first store.
+    pub removed: Vec<String>,
+    /// Keys in both stores with different values or attributes.
+    pub changed: Vec<String>,
+}
+
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Compare two texts by lines. The lines are compared with their
+/// ends, so a last line without a newline differs from the same
+/// line with one.
+///
+/// # Example
+///
+/// ```rust
+/// use slate::diff::{self, Line};
+///
+/// assert_eq!(vec![Line::Same("a"), Line::Removed("b"), Line::Added("c")],
+///            diff::lines("a\nb", "a\nc"));
+/// ```
+pub fn lines<'a>(from: &'a str, to: &'a str) -> Vec<Line<'a>> {
+    let a: Vec<&str> = from.split_inclusive('\n').collect();
+    let b: Vec<&str> = to.split_inclusive('\n').collect();
+
+    // Length of the longest common subsequence of the ends of both
+    // texts, starting at each pair of lines.
+    let mut common = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            common[i][j] = if a[i] == b[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                ::std::cmp::max(common[i + 1][j], common[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            diff.push(Line::Same(content(a[i])));
+            i += 1;
+            j += 1;
+        } else if j == b.len() || (i < a.len() && common[i + 1][j] >= common[i][j + 1]) {
+            diff.push(Line::Removed(content(a[i])));
+            i += 1;
+        } else {
+            diff.push(Line::Added(content(b[j])));
+            j += 1;
+        }
+    }
+
+    diff
+}
+
+/// Show the differences of two texts as a unified diff, with the
+/// given names in its header. It is empty if the texts are equal.
+/// A last line without a newline is followed by a
+/// `\ No newline at end of file` line, like `diff -u` does.
+///
+/// # Example
+///
+/// ```rust
+/// use slate::diff;
+///
+/// assert_eq!("--- old\n+++ new\n@@ -1,2 +1,2 @@\n a\n-b\n+c\n",
+///            diff::unified("a\nb\n", "a\nc\n", "old", "new"));
+/// ```
+pub fn unified(from: &str, to: &str, from_name: &str, to_name: &str) -> String {
+    let diff = lines(from, to);
+    let changed: Vec<usize> = diff.iter()
+        .enumerate()
+        .filter(|&(_, line)| match *line {
+            Line::Same(_) => false,
+            _ => true,
+        })
+        .map(|(index, _)| index)
+        .collect();
+    if changed.is_empty() {
+        return String::new();
+    }
+
+    // Changes closer than twice the context go in the same hunk.
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for index in changed {
+        let start = index.saturating_sub(CONTEXT);
+        let end = ::std::cmp::min(index + CONTEXT + 1, diff.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let (from_end, to_end) = (missing_newline(from), missing_newline(to));
+
+    let mut text = format!("--- {}\n+++ {}\n", from_name, to_name);
+    for (start, end) in hunks {
+        // Lines of each text before the hunk.
+        let before_from = diff[..start].iter().filter(|line| !is_added(line)).count();
+        let before_to = diff[..start].iter().filter(|line| !is_removed(line)).count();
+        let hunk = &diff[start..end];
+        let count_from = hunk.iter().filter(|line| !is_added(line)).count();
+        let count_to = hunk.iter().filter(|line| !is_removed(line)).count();
+
+        text.push_str(&format!("@@ -{} +{} @@\n",
+                               range(before_from, count_from),
+                               range(before_to, count_to)));
+        let (mut at_from, mut at_to) = (before_from, before_to);
+        for line in hunk {
+            let (sign, line, last) = match *line {
+                Line::Same(line) => {
+                    at_from += 1;
+                    at_to += 1;
+                    (' ', line, from_end == Some(at_from))
+                }
+                Line::Removed(line) => {
+                    at_from += 1;
+                    ('-', line, from_end == Some(at_from))
+                }
+                Line::Added(line) => {
+                    at_to += 1;
+                    ('+', line, to_end == Some(at_to))
+                }
+            };
+            text.push(sign);
+            text.push_str(line);
+            text.push('\n');
+            if last {
+                text.push_str("\\ No newline at end of file\n");
+            }
+        }
+    }
+
+    text
+}
+
+/// Compare the keys of two stores. The second one is only read,
+/// without creating or upgrading it, see `store::open_read_only`.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use slate::config::Config;
+/// use slate::diff;
+/// use slate::Slate;
+/// use std::env;
+///
+/// let mut temp = env::temp_dir();
+/// temp.push(".slate");
+/// let mut export = env::temp_dir();
+/// export.push("team.json");
+///
+/// let config = Config { filepath: temp, ..Default::default() };
+/// let other = config.for_file(export);
+/// let (mine, theirs): (Slate, Slate) = (From::from(&config), From::from(&other));
+///
+/// let changes = diff::stores(&mine, &theirs).unwrap();
+/// println!("{} keys would be added", changes.added.len());
+/// ```
+pub fn stores(from: &Slate, to: &Slate) -> SlateResult<Changes> {
+    let now = time::now();
+    let a = try!(try!(from.store()).read());
+    let b = try!(try!(store::open_read_only(to.config)).read());
+
+    Ok(contents(&a, &b, now))
+}
+
+/// Compare the keys of the contents of two stores, without the
+/// expired ones.
+pub fn contents(from: &Contents, to: &Contents, now: u64) -> Changes {
+    let alive = |contents: &Contents| -> Vec<String> {
+        let mut keys: Vec<String> = contents.iter()
+            .filter(|&(_, entry)| !entry.is_expired(now))
+            .map(|(key, _)| key.clone())
+            .collect();
+        keys.sort();
+        keys
+    };
+    let (a, b) = (alive(from), alive(to));
+
+    Changes {
+        added: b.iter().filter(|key| !a.contains(key)).cloned().collect(),
+        removed: a.iter().filter(|key| !b.contains(key)).cloned().collect(),
+        changed: a.iter().filter(|key| b.contains(key) && from[*key] != to[*key]).cloned().collect(),
+    }
+}
+
+/// Number of the last line of a text, if it has no newline.
+fn missing_newline(text: &str) -> Option<usize> {
+    if text.is_empty() || text.ends_with('\n') {
+        None
+    } else {
+        Some(text.lines().count())
+    }
+}
+
+/// A line without its end, like the lines of `str::lines`.
+fn content(line: &str) -> &str {
+    if line.ends_with("\r\n") {
+        &line[..line.len() - 2]
+    } else if line.ends_with('\n') {
+        &line[..line.len() - 1]
+    } else {
+        line
+    }
+}
+
+fn is_added(line: &Line) -> bool {
+    match *line {
+        Line::Added(_) => true,
+        _ => false,
+    }
+}
+
+fn is_removed(line: &Line) -> bool {
+    match *line {
+        Line::Removed(_) => true,
+        _ => false,
+    }
+}
+
+/// Range of a hunk, like `diff -u`: the first line and the number
+/// of lines, or the line before the hunk if it has none.
+fn range(before: usize, count: usize) -> String {
+    match count {
+        0 => format!("{},0", before),
+        1 => format!("{}", before + 1),
+        _ => format!("{},{}", before + 1, count),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use store::Entry;
+
+    #[test]
+    fn it_shows_unified_diffs() {
+        let from = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n";
+        let to = "0\n1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\neleven\n";
+
+        assert_eq!("--- a\n+++ b\n\
+                    @@ -1,3 +1,4 @@\n+0\n 1\n 2\n 3\n\
+                    @@ -9,4 +10,4 @@\n 9\n 10\n 11\n-12\n+eleven\n",
+                   unified(from, to, "a", "b"));
+        assert_eq!("", unified(from, from, "a", "b"));
+    }
+
+    #[test]
+    fn it_shows_missing_newlines_at_the_end() {
+        assert_eq!("--- a\n+++ b\n@@ -1,2 +1,2 @@\n 1\n-2\n\\ No newline at end of file\n+2\n",
+                   unified("1\n2", "1\n2\n", "a", "b"));
+        assert_eq!("--- a\n+++ b\n@@ -1 +1 @@\n-1\n+2\n\\ No newline at end of file\n",
+                   unified("1\n", "2", "a", "b"));
+        assert_eq!("--- a\n+++ b\n@@ -1,2 +1,2 @@\n-1\n+0\n 2\n\\ No newline at end of file\n",
+                   unified("1\n2", "0\n2", "a", "b"));
+        assert_eq!("", unified("1\n2", "1\n2", "a", "b"));
+    }
+
+    #[test]
+    fn it_compares_contents() {
+        let mut from = Contents::new();
+        from.insert("same".to_string(), Entry::new("1"));
+        from.insert("changed".to_string(), Entry::new("2"));
+        from.insert("removed".to_string(), Entry::new("3"));
+        let mut to = from.clone();
+        to.remove("removed");
+        to.insert("changed".to_string(), Entry { tags: vec!["x".to_string()], ..Entry::new("2") });
+        to.insert("added".to_string(), Entry::new("4"));
+        to.insert("expired".to_string(), Entry { expires: Some(1), ..Entry::new("5") });
+
+        let changes = contents(&from, &to, 10);
+
+        assert_eq!(vec!["added"], changes.added);
+        assert_eq!(vec!["removed"], changes.removed);
+        assert_eq!(vec!["changed"], changes.changed);
+        assert!(contents(&from, &from, 10).is_empty());
+    }
+}
@@ -0,0 +1,454 @@
+//! Checks of the health of a store and the files next to it.
+//!
+//! The store is never created or changed by the checks: problems
+//! are reported, and some of them can be fixed afterwards with
+//! `fix`.
+
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+#[cfg(unix)]
+use std::process::{Command, Stdio};
+use std::time::{Duration, SystemTime};
+
+use config::{Backend, Config};
+use files;
+use history;
+use links;
+use results::SlateResult;
+use store::{self, Store};
+use store::json::JsonStore;
+use store::sqlite;
+use usage;
+
+/// First bytes of every SQLite database.
+const SQLITE_HEADER: &'static [u8] = b"SQLite format 3\0";
+
+/// How a problem can be fixed.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Fix {
+    /// Make the file readable only by its owner.
+    Permissions(PathBuf),
+    /// Remove a file that is not used.
+    Remove(PathBuf),
+    /// Remove the history of the keys that don't exist.
+    PruneHistory,
+    /// Remove the usage of the keys that don't exist.
+    PruneUsage,
+    /// Stop using a linked directory.
+    Unlink(PathBuf),
+    /// Add the columns of the current version to the database.
+    Upgrade,
+}
+
+/// A problem found in a store.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Problem {
+    /// Name of the check that found it: format, permissions or orphans.
+    pub check: &'static str,
+    pub message: String,
+    pub fix: Option<Fix>,
+}
+
+impl Problem {
+    fn new(check: &'static str, message: String, fix: Option<Fix>) -> Problem {
+        Problem {
+            check: check,
+            message: message,
+            fix: fix,
+        }
+    }
+}
+
+/// Check the store of the configuration, and get the problems found.
+///
+/// # Example
+///
+/// ```rust
+/// use slate::config::Config;
+/// use slate::doctor;
+/// use std::env;
+///
+/// let mut temp = env::temp_dir();
+/// temp.push(".slate");
+///
+/// let config = Config { filepath: temp, ..Default::default() };
+///
+/// for problem in doctor::check(&config) {
+///   println!("{}: {}", problem.check, problem.message);
+/// }
+/// ```
+pub fn check(config: &Config) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let keys = format(config, &mut problems);
+    permissions(config, &mut problems);
+    orphans(config, keys, &mut problems);
+
+    problems
+}
+
+/// Fix a problem, if it can be fixed.
+pub fn fix(config: &Config, problem: &Problem) -> SlateResult<()> {
+    let fix = match problem.fix {
+        Some(ref fix) => fix,
+        None => return Ok(()),
+    };
+
+    match *fix {
+        Fix::Permissions(ref path) => try!(restrict(path)),
+        Fix::Remove(ref path) => try!(fs::remove_file(path)),
+        Fix::PruneHistory => {
+            let keys = try!(keys(config));
+            history::update(config, |history| history.retain(|key, _| keys.contains(key)));
+        }
+        Fix::PruneUsage => {
+            let keys = try!(keys(config));
+            usage::update(config, |stats| stats.retain(|key, _| keys.contains(key)));
+        }
+        Fix::Unlink(ref dir) => {
+            let dirs = links::read(config).into_iter().filter(|linked| linked != dir).collect();
+            try!(links::write(config, &dirs));
+        }
+        Fix::Upgrade => {
+            try!(store::open(config));
+        }
+    }
+
+    Ok(())
+}
+
+/// Check that the store can be read by its backend, and get its
+/// keys if it can.
+fn format(config: &Config, problems: &mut Vec<Problem>) -> Option<HashSet<String>> {
+    let path = &config.filepath;
+    if !path.exists() {
+        return Some(HashSet::new());
+    }
+
+    let mut header = Vec::new();
+    let _ = File::open(path).and_then(|file| file.take(SQLITE_HEADER.len() as u64).read_to_end(&mut header));
+    let is_sqlite = header == SQLITE_HEADER;
+
+    match (config.backend, is_sqlite) {
+        (Backend::Json, true) => {
+            let message = format!("{} is a SQLite database, use it with SLATE_BACKEND=sqlite",
+                                  path.display());
+            problems.push(Problem::new("format", message, None));
+            return None;
+        }
+        (Backend::Sqlite, false) if !header.is_empty() => {
+            let message = format!("{} is not a SQLite database, use it with SLATE_BACKEND=json",
+                                  path.display());
+            problems.push(Problem::new("format", message, None));
+            return None;
+        }
+        (Backend::Sqlite, _) => {
+            match sqlite::missing_columns(path) {
+                Ok(ref columns) if !columns.is_empty() => {
+                    let message = format!("{} has the format of an older version, without: {}",
+                                          path.display(),
+                                          columns.join(", "));
+                    problems.push(Problem::new("format", message, Some(Fix::Upgrade)));
+                    // The keys can't be read without changing it.
+                    return None;
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    problems.push(Problem::new("format", format!("{}", e), None));
+                    return None;
+                }
+            }
+        }
+        (Backend::Json, false) => {
+            if let Err(e) = JsonStore::new(path).read() {
+                problems.push(Problem::new("format", format!("{}", e), None));
+                return None;
+            }
+        }
+    }
+
+    keys(config).ok()
+}
+
+/// Check that the files with values can only be read by their owner.
+#[cfg(unix)]
+fn permissions(config: &Config, problems: &mut Vec<Problem>) {
+    use std::os::unix::fs::PermissionsExt;
+
+    for path in &[config.filepath.clone(), history::path(config)] {
+        let mode = match fs::metadata(path) {
+            Ok(metadata) => metadata.permissions().mode() & 0o777,
+            Err(_) => continue,
+        };
+        if mode & 0o077 != 0 {
+            let message = format!("{} can be read by other users (mode {:o})", path.display(), mode);
+            problems.push(Problem::new("permissions", message, Some(Fix::Permissions(path.clone()))));
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn permissions(_config: &Config, _problems: &mut Vec<Problem>) {}
+
+#[cfg(unix)]
+fn restrict(path: &Path) -> ::std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+}
+
+#[cfg(not(unix))]
+fn restrict(_path: &Path) -> ::std::io::Result<()> {
+    Ok(())
+}
+
+/// Check the files left next to the store: locks and temporary files
+/// of processes that didn't finish, and sidecars with data of keys
+/// that don't exist.
+fn orphans(config: &Config, keys: Option<HashSet<String>>, problems: &mut Vec<Problem>) {
+    for path in &[config.filepath.clone(), usage::path(config), history::path(config)] {
+        let lock = files::lock_path(path);
+        if is_stale_lock(&lock) {
+            let message = format!("{} is a lock left by a process that didn't finish",
+                                  lock.display());
+            problems.push(Problem::new("orphans", message, Some(Fix::Remove(lock))));
+        }
+    }
+
+    let dir = match config.filepath.parent() {
+        Some(dir) if dir.as_os_str().is_empty() => Path::new("."),
+        Some(dir) => dir,
+        None => Path::new("/"),
+    };
+    let mut temps: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| {
+            entries.filter_map(|entry| entry.ok())
+                .map(|entry| config.filepath.with_file_name(entry.file_name()))
+                .filter(|path| files::is_temp_of(path, &config.filepath))
+                .collect()
+        })
+        .unwrap_or_default();
+    temps.sort();
+    for temp in temps.into_iter().filter(|temp| is_stale(temp)) {
+        let message = format!("{} is a temporary file left by a process that didn't finish",
+                              temp.display());
+        problems.push(Problem::new("orphans", message, Some(Fix::Remove(temp))));
+    }
+
+    let sidecars: Vec<(PathBuf, Vec<String>, Fix)> =
+        vec![(history::path(config), history::read(config).keys().cloned().collect(), Fix::PruneHistory),
+             (usage::path(config), usage::read(config).keys().cloned().collect(), Fix::PruneUsage)];
+    for (path, saved, prune) in sidecars {
+        if !path.exists() {
+            continue;
+        }
+        if !config.filepath.exists() {
+            let message = format!("{} belongs to a store that doesn't exist", path.display());
+            problems.push(Problem::new("orphans", message, Some(Fix::Remove(path))));
+            continue;
+        }
+        if let Some(ref keys) = keys {
+            let missing = saved.iter().filter(|key| !keys.contains(*key)).count();
+            if missing > 0 {
+                let message = format!("{} has data of {} keys that don't exist",
+                                      path.display(),
+                                      missing);
+                problems.push(Problem::new("orphans", message, Some(prune)));
+            }
+        }
+    }
+
+    for dir in links::read(config).into_iter().filter(|dir| !dir.is_dir()) {
+        let message = format!("The linked directory {} doesn't exist", dir.display());
+        problems.push(Problem::new("orphans", message, Some(Fix::Unlink(dir))));
+    }
+}
+
+/// Whether a lock exists and its owner is not running anymore. Locks
+/// without the id of their owner, or on systems where it cannot be
+/// checked, are stale once they are older than any process would
+/// keep them.
+fn is_stale_lock(path: &Path) -> bool {
+    let mut owner = String::new();
+    if File::open(path).and_then(|mut file| file.read_to_string(&mut owner)).is_err() {
+        return false;
+    }
+
+    match owner.trim().parse().ok().and_then(is_running) {
+        Some(running) => !running,
+        None => is_stale(path),
+    }
+}
+
+/// Whether a process is running, if it can be told.
+#[cfg(unix)]
+fn is_running(pid: u32) -> Option<bool> {
+    if Path::new("/proc/self").exists() {
+        return Some(Path::new("/proc").join(pid.to_string()).exists());
+    }
+
+    Command::new("kill")
+        .arg("-0")
+        .arg(pid.to_string())
+        .stderr(Stdio::null())
+        .status()
+        .ok()
+        .map(|status| status.success())
+}
+
+#[cfg(not(unix))]
+fn is_running(_pid: u32) -> Option<bool> {
+    None
+}
+
+/// Whether a temporary file, or a lock, exists and is older than any
+/// process would keep it.
+fn is_stale(path: &Path) -> bool {
+    let modified = match fs::metadata(path).and_then(|metadata| metadata.modified()) {
+        Ok(modified) => modified,
+        Err(_) => return false,
+    };
+    let age = SystemTime::now().duration_since(modified).unwrap_or_default();
+
+    age > Duration::from_millis(files::LOCK_TIMEOUT_MS)
+}
+
+/// Get all the keys of the store, expired or not.
+fn keys(config: &Config) -> SlateResult<HashSet<String>> {
+    let store = try!(store::open(config));
+
+    Ok(try!(store.read()).into_iter().map(|(key, _)| key).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use rand::{thread_rng, Rng};
+    use history::Revision;
+
+    fn temp_config(body: &str) -> Config {
+        let random_name: String = thread_rng().gen_ascii_chars().take(10).collect();
+        let mut temp = env::temp_dir();
+        temp.push(random_name + ".json");
+        fs::write(&temp, body).unwrap();
+
+        Config { filepath: temp, ..Default::default() }
+    }
+
+    #[test]
+    fn it_finds_no_problems_in_healthy_stores() {
+        let config = temp_config("{\"a\":\"1\"}");
+        restrict(&config.filepath).unwrap();
+
+        assert_eq!(Vec::<Problem>::new(), check(&config));
+    }
+
+    #[test]
+    fn it_reports_corrupted_stores() {
+        let config = temp_config("{\"a\":");
+        restrict(&config.filepath).unwrap();
+
+        let problems = check(&config);
+        assert_eq!(1, problems.len());
+        assert_eq!("format", problems[0].check);
+        assert_eq!(None, problems[0].fix);
+    }
+
+    #[test]
+    fn it_fixes_orphaned_files() {
+        let config = temp_config("{\"a\":\"1\"}");
+        restrict(&config.filepath).unwrap();
+        let revision = Revision { value: "0".to_string(), time: None };
+        history::update(&config, |history| {
+            history.insert("a".to_string(), vec![revision.clone()]);
+            history.insert("b".to_string(), vec![revision.clone()]);
+        });
+        restrict(&history::path(&config)).unwrap();
+        let dir = config.filepath.with_extension("missing");
+        links::write(&config, &vec![dir.clone()]).unwrap();
+
+        let problems = check(&config);
+        assert_eq!(vec![Some(Fix::PruneHistory), Some(Fix::Unlink(dir))],
+                   problems.iter().map(|problem| problem.fix.clone()).collect::<Vec<_>>());
+
+        for problem in &problems {
+            fix(&config, problem).unwrap();
+        }
+        assert_eq!(Vec::<Problem>::new(), check(&config));
+        assert!(history::read(&config).contains_key("a"));
+    }
+
+    #[test]
+    fn it_removes_old_temporary_files() {
+        let config = temp_config("{\"a\":\"1\"}");
+        restrict(&config.filepath).unwrap();
+        let old = files::temp_path(&usage::path(&config));
+        let new = files::temp_path(&config.filepath);
+        fs::write(&old, "{}").unwrap();
+        fs::write(&new, "{}").unwrap();
+        let an_hour_ago = SystemTime::now() - Duration::from_secs(3600);
+        File::options().write(true).open(&old).unwrap().set_modified(an_hour_ago).unwrap();
+
+        let problems = check(&config);
+        assert_eq!(vec![Some(Fix::Remove(old.clone()))],
+                   problems.iter().map(|problem| problem.fix.clone()).collect::<Vec<_>>());
+
+        fix(&config, &problems[0]).unwrap();
+        assert!(!old.exists());
+        assert!(new.exists());
+    }
+
+    #[test]
+    fn it_removes_locks_of_processes_that_are_not_running() {
+        let config = temp_config("{\"a\":\"1\"}");
+        restrict(&config.filepath).unwrap();
+        let lock = files::lock_path(&config.filepath);
+        let an_hour_ago = SystemTime::now() - Duration::from_secs(3600);
+
+        // Held for long by a running process, like during slow hooks.
+        fs::write(&lock, ::std::process::id().to_string()).unwrap();
+        File::options().write(true).open(&lock).unwrap().set_modified(an_hour_ago).unwrap();
+        assert_eq!(Vec::<Problem>::new(), check(&config));
+
+        // Without the id of its owner it is only stale when it is old.
+        fs::write(&lock, "").unwrap();
+        assert_eq!(Vec::<Problem>::new(), check(&config));
+        File::options().write(true).open(&lock).unwrap().set_modified(an_hour_ago).unwrap();
+        assert_eq!(vec![Some(Fix::Remove(lock.clone()))],
+                   check(&config).iter().map(|problem| problem.fix.clone()).collect::<Vec<_>>());
+
+        #[cfg(unix)]
+        {
+            let mut child = Command::new("true").spawn().unwrap();
+            let pid = child.id();
+            child.wait().unwrap();
+            fs::write(&lock, pid.to_string()).unwrap();
+            let problems = check(&config);
+            assert_eq!(vec![Some(Fix::Remove(lock.clone()))],
+                       problems.iter().map(|problem| problem.fix.clone()).collect::<Vec<_>>());
+            fix(&config, &problems[0]).unwrap();
+            assert!(!lock.exists());
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn it_fixes_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let config = temp_config("{\"a\":\"1\"}");
+        fs::set_permissions(&config.filepath, fs::Permissions::from_mode(0o644)).unwrap();
+
+        let problems = check(&config);
+        assert_eq!(vec![Problem::new("permissions",
+                                     format!("{} can be read by other users (mode 644)",
+                                             config.filepath.display()),
+                                     Some(Fix::Permissions(config.filepath.clone())))],
+                   problems);
+
+        fix(&config, &problems[0]).unwrap();
+        assert_eq!(Vec::<Problem>::new(), check(&config));
+    }
+}
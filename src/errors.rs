@@ -1,12 +1,83 @@
+use docopt;
+use rusqlite;
 use serde_json;
 use std::error;
 use std::fmt;
 use std::io;
 
+/// Exit code for invalid arguments or options.
+pub const EXIT_USAGE: i32 = 1;
+/// Exit code when a key doesn't exist.
+pub const EXIT_NOT_FOUND: i32 = 2;
+/// Exit code when a key already exists.
+pub const EXIT_CONFLICT: i32 = 3;
+/// Exit code when the store cannot be parsed.
+pub const EXIT_CORRUPTED: i32 = 4;
+/// Exit code for I/O and other storage errors.
+pub const EXIT_IO: i32 = 5;
+/// Exit code when the store is locked by someone else for too long.
+pub const EXIT_LOCK_TIMEOUT: i32 = 6;
+/// Exit code when a hook rejects a change.
+pub const EXIT_VETOED: i32 = 7;
+/// Exit code when `slate doctor` finds problems that are not fixed.
+pub const EXIT_PROBLEMS: i32 = 8;
+
 #[derive(Debug)]
 pub enum SlateError {
     IO(io::Error),
     JSON(serde_json::Error),
+    SQLite(rusqlite::Error),
+    NotFound(String),
+    /// A line number past the end of the value of a key.
+    LineNotFound(String, usize),
+    /// A line edit of a key with a binary value, which has no lines.
+    Binary(String),
+    Conflict(String),
+    /// A key changed by someone else while it was being changed.
+    Changed(String),
+    /// Aliases that point to each other, from the first one to itself.
+    Cycle(Vec<String>),
+    Corrupted(String),
+    LockTimeout,
+    Vetoed(String),
+}
+
+impl SlateError {
+    /// Code used when exiting the program because of this error.
+    pub fn exit_code(&self) -> i32 {
+        match *self {
+            SlateError::IO(_) |
+            SlateError::JSON(_) |
+            SlateError::SQLite(_) => EXIT_IO,
+            SlateError::Binary(_) => EXIT_USAGE,
+            SlateError::NotFound(_) |
+            SlateError::LineNotFound(_, _) => EXIT_NOT_FOUND,
+            SlateError::Conflict(_) |
+            SlateError::Changed(_) |
+            SlateError::Cycle(_) => EXIT_CONFLICT,
+            SlateError::Corrupted(_) => EXIT_CORRUPTED,
+            SlateError::LockTimeout => EXIT_LOCK_TIMEOUT,
+            SlateError::Vetoed(_) => EXIT_VETOED,
+        }
+    }
+
+    /// Short name of the kind of error, for machine readable output.
+    pub fn kind(&self) -> &'static str {
+        match *self {
+            SlateError::IO(_) => "io",
+            SlateError::JSON(_) |
+            SlateError::SQLite(_) => "store",
+            SlateError::NotFound(_) |
+            SlateError::LineNotFound(_, _) => "not_found",
+            SlateError::Binary(_) => "binary",
+            SlateError::Conflict(_) => "conflict",
+            SlateError::Changed(_) => "changed",
+            SlateError::Cycle(_) => "cycle",
+            SlateError::Corrupted(_) => "corrupted",
+            SlateError::LockTimeout => "lock_timeout",
+            SlateError::Vetoed(_) => "vetoed",
+        }
+    }
 }
 
 impl fmt::Display for SlateError {
@@ -14,6 +85,24 @@ impl fmt::Display for SlateError {
         match *self {
             SlateError::IO(ref err) => write!(f, "{}", err),
             SlateError::JSON(ref err) => write!(f, "{}", err),
+            SlateError::SQLite(ref err) => write!(f, "{}", err),
+            SlateError::NotFound(ref key) => write!(f, "The key {} doesn't exist", key),
+            SlateError::LineNotFound(ref key, line) => {
+                write!(f, "The value of {} has no line {}", key, line)
+            }
+            SlateError::Binary(ref key) => {
+                write!(f, "The value of {} is binary, its lines can't be changed", key)
+            }
+            SlateError::Conflict(ref key) => write!(f, "The key {} already exists", key),
+            SlateError::Changed(ref key) => {
+                write!(f, "The key {} has been changed by someone else", key)
+            }
+            SlateError::Cycle(ref keys) => {
+                write!(f, "The aliases make a cycle: {}", keys.join(" -> "))
+            }
+            SlateError::Corrupted(ref reason) => write!(f, "The store is corrupted: {}", reason),
+            SlateError::LockTimeout => write!(f, "The store is locked by another process"),
+            SlateError::Vetoed(ref hook) => write!(f, "The hook {} rejected the change", hook),
         }
     }
 }
@@ -23,6 +112,16 @@ impl error::Error for SlateError {
         match *self {
             SlateError::IO(ref err) => err.description(),
             SlateError::JSON(ref err) => err.description(),
+            SlateError::SQLite(ref err) => err.description(),
+            SlateError::NotFound(_) => "key not found",
+            SlateError::LineNotFound(_, _) => "line not found",
+            SlateError::Binary(_) => "binary value",
+            SlateError::Conflict(_) => "key already exists",
+            SlateError::Changed(_) => "key changed by someone else",
+            SlateError::Cycle(_) => "cycle of aliases",
+            SlateError::Corrupted(_) => "corrupted store",
+            SlateError::LockTimeout => "lock timeout",
+            SlateError::Vetoed(_) => "change rejected by a hook",
         }
     }
 
@@ -30,6 +129,16 @@ impl error::Error for SlateError {
         match *self {
             SlateError::IO(ref err) => Some(err),
             SlateError::JSON(ref err) => Some(err),
+            SlateError::SQLite(ref err) => Some(err),
+            SlateError::NotFound(_) => None,
+            SlateError::LineNotFound(_, _) => None,
+            SlateError::Binary(_) => None,
+            SlateError::Conflict(_) => None,
+            SlateError::Changed(_) => None,
+            SlateError::Cycle(_) => None,
+            SlateError::Corrupted(_) => None,
+            SlateError::LockTimeout => None,
+            SlateError::Vetoed(_) => None,
         }
     }
 }
@@ -46,11 +155,64 @@ impl From<serde_json::Error> for SlateError {
     }
 }
 
+impl From<rusqlite::Error> for SlateError {
+    /// Busy and corrupted databases are reported with their
+    /// own errors.
+    fn from(err: rusqlite::Error) -> SlateError {
+        let code = match err {
+            rusqlite::Error::SqliteFailure(ref failure, _) => Some(failure.code),
+            _ => None,
+        };
+
+        match code {
+            Some(rusqlite::ErrorCode::DatabaseBusy) |
+            Some(rusqlite::ErrorCode::DatabaseLocked) => SlateError::LockTimeout,
+            Some(rusqlite::ErrorCode::DatabaseCorrupt) |
+            Some(rusqlite::ErrorCode::NotADatabase) => SlateError::Corrupted(format!("{}", err)),
+            _ => SlateError::SQLite(err),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum CommandError {
     IO(io::Error),
     Slate(SlateError),
     Argument(String),
+    NotFound(String, Option<String>),
+    /// Keys that already exist where they were going to be saved.
+    Conflicts(Vec<String>),
+    /// Problems found in the store, and how many of them can be fixed.
+    Problems(usize, usize),
+    Usage(docopt::Error),
+}
+
+impl CommandError {
+    /// Code used when exiting the program because of this error.
+    pub fn exit_code(&self) -> i32 {
+        match *self {
+            CommandError::IO(_) => EXIT_IO,
+            CommandError::Slate(ref err) => err.exit_code(),
+            CommandError::Argument(_) => EXIT_USAGE,
+            CommandError::NotFound(_, _) => EXIT_NOT_FOUND,
+            CommandError::Conflicts(_) => EXIT_CONFLICT,
+            CommandError::Problems(_, _) => EXIT_PROBLEMS,
+            CommandError::Usage(ref err) => if err.fatal() { EXIT_USAGE } else { 0 },
+        }
+    }
+
+    /// Short name of the kind of error, for machine readable output.
+    pub fn kind(&self) -> &'static str {
+        match *self {
+            CommandError::IO(_) => "io",
+            CommandError::Slate(ref err) => err.kind(),
+            CommandError::Argument(_) => "usage",
+            CommandError::NotFound(_, _) => "not_found",
+            CommandError::Conflicts(_) => "conflict",
+            CommandError::Problems(_, _) => "problems",
+            CommandError::Usage(_) => "usage",
+        }
+    }
 }
 
 impl fmt::Display for CommandError {
@@ -59,6 +221,24 @@ impl fmt::Display for CommandError {
             CommandError::IO(ref err) => write!(f, "{}", err),
             CommandError::Slate(ref err) => write!(f, "{}", err),
             CommandError::Argument(ref string) => write!(f, "{}", string),
+            CommandError::NotFound(ref key, None) => write!(f, "The key {} doesn't exist", key),
+            CommandError::NotFound(ref key, Some(ref similar)) => {
+                write!(f, "The key {} doesn't exist. Did you mean {}?", key, similar)
+            }
+            CommandError::Conflicts(ref keys) => {
+                write!(f,
+                       "These keys already exist: {}. Use --force to overwrite them",
+                       keys.join(", "))
+            }
+            CommandError::Problems(total, fixable) => {
+                let plural = if total == 1 { "" } else { "s" };
+                try!(write!(f, "Found {} problem{}", total, plural));
+                if fixable > 0 {
+                    try!(write!(f, ", {} can be fixed with --fix", fixable));
+                }
+                Ok(())
+            }
+            CommandError::Usage(ref err) => write!(f, "{}", err),
         }
     }
 }
@@ -69,6 +249,10 @@ impl error::Error for CommandError {
             CommandError::IO(ref err) => err.description(),
             CommandError::Slate(ref err) => err.description(),
             CommandError::Argument(ref string) => string,
+            CommandError::NotFound(_, _) => "key not found",
+            CommandError::Conflicts(_) => "keys already exist",
+            CommandError::Problems(_, _) => "problems found",
+            CommandError::Usage(ref err) => err.description(),
         }
     }
 
@@ -77,6 +261,10 @@ impl error::Error for CommandError {
             CommandError::IO(ref err) => Some(err),
             CommandError::Slate(ref err) => Some(err),
             CommandError::Argument(_) => None,
+            CommandError::NotFound(_, _) => None,
+            CommandError::Conflicts(_) => None,
+            CommandError::Problems(_, _) => None,
+            CommandError::Usage(ref err) => Some(err),
         }
     }
 }
@@ -98,3 +286,9 @@ impl From<String> for CommandError {
         CommandError::Argument(err)
     }
 }
+
+impl From<docopt::Error> for CommandError {
+    fn from(err: docopt::Error) -> CommandError {
+        CommandError::Usage(err)
+    }
+}
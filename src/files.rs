@@ -0,0 +1,155 @@
+//! Changes of files shared by several processes, like the store and
+//! its sidecars.
+//!
+//! A lock file next to a file makes the processes that change it
+//! wait for each other. Files are replaced through a temporary file
+//! with a name of its own, so they are never read half written and
+//! two processes never write the same temporary file.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use errors::SlateError;
+use results::SlateResult;
+
+/// Time to wait for other processes changing a file.
+pub const LOCK_TIMEOUT_MS: u64 = 5000;
+/// Time between the attempts to take a lock.
+const LOCK_RETRY_MS: u64 = 20;
+
+/// Temporary files written by this process, to name the next one.
+static TEMPS: AtomicUsize = AtomicUsize::new(0);
+
+/// Path of the lock file of a file, like `~/.slate.lock`.
+pub fn lock_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+    name.push(".lock");
+
+    path.with_file_name(name)
+}
+
+/// A lock file held while a file is read and written, so other
+/// processes wait for the changes. It is removed when dropped.
+pub struct Lock {
+    path: PathBuf,
+}
+
+impl Lock {
+    /// Take the lock of a file, waiting for other processes.
+    ///
+    /// It returns a `SlateError::LockTimeout` error if the lock is
+    /// not released in time.
+    pub fn acquire(path: &Path) -> SlateResult<Lock> {
+        let path = lock_path(path);
+        let start = Instant::now();
+
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    // The owner tells `slate doctor` whether the lock is held.
+                    let _ = write!(file, "{}", process::id());
+                    return Ok(Lock { path: path });
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    if start.elapsed() >= Duration::from_millis(LOCK_TIMEOUT_MS) {
+                        return Err(SlateError::LockTimeout);
+                    }
+                    thread::sleep(Duration::from_millis(LOCK_RETRY_MS));
+                }
+                Err(e) => return Err(From::from(e)),
+            }
+        }
+    }
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Path of a new temporary file next to a file, with the process id
+/// and a number of its own, like `~/.slate.1234-0-3f2a.tmp`.
+pub fn temp_path(path: &Path) -> PathBuf {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.subsec_nanos()).unwrap_or(0);
+    let mut name = path.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+    name.push(format!(".{}-{}-{:x}.tmp",
+                      process::id(),
+                      TEMPS.fetch_add(1, Ordering::SeqCst),
+                      nanos));
+
+    path.with_file_name(name)
+}
+
+/// Whether a file is a temporary file of another one, or of one of
+/// its sidecars.
+pub fn is_temp_of(temp: &Path, path: &Path) -> bool {
+    let (temp, name) = match (temp.file_name(), path.file_name()) {
+        (Some(temp), Some(name)) => (temp.to_string_lossy(), name.to_string_lossy()),
+        _ => return false,
+    };
+
+    temp.starts_with(&format!("{}.", name)) && temp.ends_with(".tmp")
+}
+
+/// Replace the contents of a file, writing them into a temporary
+/// file that is moved over it. The temporary file gets the given
+/// permissions before anything is written into it.
+pub fn replace(path: &Path, bytes: &[u8], permissions: Option<fs::Permissions>) -> io::Result<()> {
+    let temp = temp_path(path);
+
+    let result = write_new(&temp, bytes, permissions).and_then(|_| fs::rename(&temp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+
+    result
+}
+
+fn write_new(path: &Path, bytes: &[u8], permissions: Option<fs::Permissions>) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = try!(options.open(path));
+    if let Some(permissions) = permissions {
+        try!(file.set_permissions(permissions));
+    }
+    try!(file.write_all(bytes));
+
+    file.sync_all()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use rand::{thread_rng, Rng};
+
+    #[test]
+    fn it_replaces_files_through_temporary_files() {
+        let random_name: String = thread_rng().gen_ascii_chars().take(10).collect();
+        let mut path = env::temp_dir();
+        path.push(random_name + ".json");
+        fs::write(&path, "old").unwrap();
+
+        let temp = temp_path(&path);
+        assert!(is_temp_of(&temp, &path));
+        assert!(temp_path(&path) != temp);
+        assert!(!is_temp_of(&path, &path));
+
+        replace(&path, b"new", None).unwrap();
+        assert_eq!("new", fs::read_to_string(&path).unwrap());
+        let dir = path.parent().unwrap();
+        assert!(!fs::read_dir(dir).unwrap().any(|entry| is_temp_of(&entry.unwrap().path(), &path)));
+    }
+}
@@ -0,0 +1,663 @@
+//! Syntax highlighting of values in the terminal.
+//!
+//! Every value can have a language, set explicitly or detected
+//! from its shebang or its contents. Highlighting is done with a
+//! small lexer per language that knows about keywords, strings,
+//! comments, numbers, variables and keys, and colors them with
+//! ANSI escape codes.
+
+use serde_json;
+use std::env;
+use std::str::FromStr;
+
+use store::Entry;
+
+/// When to use colors.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Color {
+    /// Only in terminals, unless the `NO_COLOR` env var is set.
+    Auto,
+    Always,
+    Never,
+}
+
+impl Color {
+    /// Whether to use colors, given whether the output is a terminal.
+    pub fn enabled(&self, terminal: bool) -> bool {
+        match *self {
+            Color::Always => true,
+            Color::Never => false,
+            Color::Auto => {
+                terminal && env::var_os("NO_COLOR").map(|value| value.is_empty()).unwrap_or(true)
+            }
+        }
+    }
+}
+
+impl Default for Color {
+    fn default() -> Color {
+        Color::Auto
+    }
+}
+
+impl FromStr for Color {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Color, String> {
+        match s {
+            "auto" => Ok(Color::Auto),
+            "always" => Ok(Color::Always),
+            "never" => Ok(Color::Never),
+            _ => Err(format!("Unknown color mode: {}", s)),
+        }
+    }
+}
+
+/// Kinds of tokens of a value.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    Plain,
+    Keyword,
+    Str,
+    Comment,
+    Number,
+    Variable,
+    Key,
+    /// Lines added in a diff.
+    Added,
+    /// Lines removed in a diff.
+    Removed,
+}
+
+/// Colors of each kind of token, as ANSI SGR codes like `1;35`.
+///
+/// Themes are chosen by name (`default`, `light` or `mono`), or
+/// built from the default one with a list of changes like
+/// `keyword=1;35,comment=2`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    pub keyword: String,
+    pub string: String,
+    pub comment: String,
+    pub number: String,
+    pub variable: String,
+    pub key: String,
+    pub added: String,
+    pub removed: String,
+}
+
+impl Theme {
+    fn new(codes: [&str; 8]) -> Theme {
+        Theme {
+            keyword: codes[0].to_string(),
+            string: codes[1].to_string(),
+            comment: codes[2].to_string(),
+            number: codes[3].to_string(),
+            variable: codes[4].to_string(),
+            key: codes[5].to_string(),
+            added: codes[6].to_string(),
+            removed: codes[7].to_string(),
+        }
+    }
+
+    /// Code used for a kind of token. It is empty for plain text.
+    pub fn code(&self, kind: Kind) -> &str {
+        match kind {
+            Kind::Plain => "",
+            Kind::Keyword => &self.keyword,
+            Kind::Str => &self.string,
+            Kind::Comment => &self.comment,
+            Kind::Number => &self.number,
+            Kind::Variable => &self.variable,
+            Kind::Key => &self.key,
+            Kind::Added => &self.added,
+            Kind::Removed => &self.removed,
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme::new(["35", "32", "90", "33", "36", "34", "32", "31"])
+    }
+}
+
+impl FromStr for Theme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Theme, String> {
+        match s {
+            "" | "default" => return Ok(Theme::default()),
+            "light" => return Ok(Theme::new(["1;34", "31", "2", "35", "36", "34", "32", "31"])),
+            "mono" => return Ok(Theme::new(["1", "", "2", "", "4", "1", "1", "2"])),
+            _ => (),
+        }
+
+        let invalid = || format!("Unknown theme: {}", s);
+        let mut theme = Theme::default();
+        for change in s.split(',') {
+            let mut parts = change.splitn(2, '=');
+            let (name, code) = match (parts.next(), parts.next()) {
+                (Some(name), Some(code)) => (name.trim(), code.trim()),
+                _ => return Err(invalid()),
+            };
+            if !code.chars().all(|c| c.is_digit(10) || c == ';') {
+                return Err(invalid());
+            }
+
+            let field = match name {
+                "keyword" => &mut theme.keyword,
+                "string" => &mut theme.string,
+                "comment" => &mut theme.comment,
+                "number" => &mut theme.number,
+                "variable" => &mut theme.variable,
+                "key" => &mut theme.key,
+                "added" => &mut theme.added,
+                "removed" => &mut theme.removed,
+                _ => return Err(invalid()),
+            };
+            *field = code.to_string();
+        }
+
+        Ok(theme)
+    }
+}
+
+/// How to split the values of a language in tokens.
+struct Syntax {
+    name: &'static str,
+    keywords: &'static [&'static str],
+    comments: &'static [&'static str],
+    quotes: &'static str,
+    ignore_case: bool,
+    variables: bool,
+    keys: bool,
+}
+
+const SYNTAXES: &'static [Syntax] = &[Syntax {
+                                          name: "shell",
+                                          keywords: &["if", "then", "else", "elif", "fi", "for",
+                                                      "while", "until", "do", "done", "case",
+                                                      "esac", "in", "function", "return",
+                                                      "export", "local", "break", "continue",
+                                                      "exit"],
+                                          comments: &["#"],
+                                          quotes: "\"'`",
+                                          ignore_case: false,
+                                          variables: true,
+                                          keys: false,
+                                      },
+                                      Syntax {
+                                          name: "sql",
+                                          keywords: &["select", "from", "where", "insert",
+                                                      "into", "values", "update", "set",
+                                                      "delete", "create", "table", "drop",
+                                                      "alter", "index", "join", "left", "right",
+                                                      "inner", "outer", "on", "and", "or",
+                                                      "not", "null", "is", "as", "order", "by",
+                                                      "group", "having", "limit", "offset",
+                                                      "union", "all", "distinct", "with",
+                                                      "primary", "key", "default", "references",
+                                                      "begin", "commit", "rollback", "case",
+                                                      "when", "then", "else", "end", "exists",
+                                                      "in", "like", "between", "asc", "desc"],
+                                          comments: &["--"],
+                                          quotes: "'\"",
+                                          ignore_case: true,
+                                          variables: false,
+                                          keys: false,
+                                      },
+                                      Syntax {
+                                          name: "yaml",
+                                          keywords: &["true", "false", "null", "yes", "no",
+                                                      "on", "off"],
+                                          comments: &["#"],
+                                          quotes: "\"'",
+                                          ignore_case: false,
+                                          variables: false,
+                                          keys: true,
+                                      },
+                                      Syntax {
+                                          name: "json",
+                                          keywords: &["true", "false", "null"],
+                                          comments: &[],
+                                          quotes: "\"",
+                                          ignore_case: false,
+                                          variables: false,
+                                          keys: true,
+                                      },
+                                      Syntax {
+                                          name: "python",
+                                          keywords: &["def", "class", "return", "if", "elif",
+                                                      "else", "for", "while", "in", "import",
+                                                      "from", "as", "with", "try", "except",
+                                                      "finally", "raise", "pass", "break",
+                                                      "continue", "lambda", "yield", "and",
+                                                      "or", "not", "is", "None", "True",
+                                                      "False", "global", "nonlocal", "assert",
+                                                      "del", "async", "await"],
+                                          comments: &["#"],
+                                          quotes: "\"'",
+                                          ignore_case: false,
+                                          variables: false,
+                                          keys: false,
+                                      },
+                                      Syntax {
+                                          name: "ruby",
+                                          keywords: &["def", "class", "module", "end", "if",
+                                                      "elsif", "else", "unless", "while",
+                                                      "until", "for", "in", "do", "return",
+                                                      "yield", "begin", "rescue", "ensure",
+                                                      "raise", "require", "nil", "true",
+                                                      "false", "self", "and", "or", "not",
+                                                      "then", "case", "when"],
+                                          comments: &["#"],
+                                          quotes: "\"'",
+                                          ignore_case: false,
+                                          variables: false,
+                                          keys: false,
+                                      },
+                                      Syntax {
+                                          name: "javascript",
+                                          keywords: &["function", "return", "if", "else",
+                                                      "for", "while", "do", "var", "let",
+                                                      "const", "new", "class", "extends",
+                                                      "import", "export", "from", "default",
+                                                      "try", "catch", "finally", "throw",
+                                                      "switch", "case", "break", "continue",
+                                                      "typeof", "instanceof", "null",
+                                                      "undefined", "true", "false", "this",
+                                                      "async", "await", "of", "in"],
+                                          comments: &["//"],
+                                          quotes: "\"'`",
+                                          ignore_case: false,
+                                          variables: false,
+                                          keys: false,
+                                      }];
+
+/// Commands that usually start a shell snippet.
+const SHELL_COMMANDS: &'static [&'static str] = &["sudo", "cd", "ls", "git", "docker",
+                                                  "kubectl", "curl", "wget", "ssh", "scp",
+                                                  "rsync", "export", "echo", "cat", "grep",
+                                                  "find", "sed", "awk", "make", "npm", "yarn",
+                                                  "cargo", "brew", "apt", "apt-get",
+                                                  "systemctl", "tar", "ps", "kill", "source",
+                                                  "alias", "psql", "mysql", "redis-cli"];
+
+/// Words that usually start a SQL snippet.
+const SQL_STATEMENTS: &'static [&'static str] = &["select", "insert", "update", "delete",
+                                                  "create", "alter", "drop", "with",
+                                                  "explain"];
+
+/// Names of the languages that can be highlighted.
+pub fn languages() -> Vec<&'static str> {
+    SYNTAXES.iter().map(|syntax| syntax.name).collect()
+}
+
+/// Get the usual name of a language, like `shell` for `bash`.
+///
+/// # Example
+///
+/// ```rust
+/// use slate::highlight::normalize;
+///
+/// assert_eq!("shell", normalize("Bash"));
+/// assert_eq!("yaml", normalize("yml"));
+/// assert_eq!("haskell", normalize("haskell"));
+/// ```
+pub fn normalize(language: &str) -> String {
+    let language = language.trim().to_lowercase();
+    let name = match language.as_str() {
+        "sh" | "bash" | "zsh" | "dash" | "ksh" | "fish" => "shell",
+        "py" | "python2" | "python3" => "python",
+        "rb" => "ruby",
+        "js" | "node" | "nodejs" => "javascript",
+        "yml" => "yaml",
+        _ => return language,
+    };
+
+    name.to_string()
+}
+
+/// Language of an entry: the one set explicitly or the detected one.
+pub fn language(entry: &Entry) -> Option<String> {
+    entry.language.clone().or_else(|| detect(&entry.value).map(|name| name.to_string()))
+}
+
+/// Guess the language of a value from its shebang or its contents.
+///
+/// # Example
+///
+/// ```rust
+/// use slate::highlight::detect;
+///
+/// assert_eq!(Some("python"), detect("#!/usr/bin/env python3\nprint(1)"));
+/// assert_eq!(Some("sql"), detect("SELECT * FROM users"));
+/// assert_eq!(Some("yaml"), detect("name: slate\nversion: 1"));
+/// assert_eq!(None, detect("just some text"));
+/// ```
+pub fn detect(value: &str) -> Option<&'static str> {
+    let trimmed = value.trim_left();
+
+    if trimmed.starts_with("#!") {
+        return shebang(trimmed.lines().next().unwrap_or(""));
+    }
+    if (trimmed.starts_with('{') || trimmed.starts_with('[')) &&
+       serde_json::from_str::<serde_json::Value>(value).is_ok() {
+        return Some("json");
+    }
+
+    let first = trimmed.split_whitespace().next().unwrap_or("").to_lowercase();
+    if SQL_STATEMENTS.contains(&first.as_str()) {
+        return Some("sql");
+    }
+    if trimmed.starts_with("---") || is_yaml(trimmed) {
+        return Some("yaml");
+    }
+    if SHELL_COMMANDS.contains(&first.as_str()) || trimmed.starts_with("./") ||
+       value.contains(" | ") || value.contains(" && ") || value.contains("$(") ||
+       value.contains("; do") || value.contains("; then") {
+        return Some("shell");
+    }
+
+    None
+}
+
+/// Language of the interpreter in a shebang line.
+fn shebang(line: &str) -> Option<&'static str> {
+    let mut words = line.trim_left_matches("#!").split_whitespace();
+    let mut program = words.next().unwrap_or("");
+    if program.ends_with("/env") {
+        program = words.find(|word| !word.starts_with('-')).unwrap_or("");
+    }
+
+    let name = program.rsplit('/').next().unwrap_or("");
+    let name = name.trim_right_matches(|c: char| c.is_digit(10) || c == '.');
+    match normalize(name).as_str() {
+        "shell" => Some("shell"),
+        "python" => Some("python"),
+        "ruby" => Some("ruby"),
+        "javascript" => Some("javascript"),
+        _ => None,
+    }
+}
+
+/// Whether every line looks like a YAML key, list item or nested
+/// value, with at least one key.
+fn is_yaml(value: &str) -> bool {
+    let mut keys = 0;
+
+    for (index, line) in value.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if trimmed == "-" || trimmed.starts_with("- ") {
+            continue;
+        }
+        if index > 0 && line.starts_with(char::is_whitespace) {
+            continue;
+        }
+        if key_length(trimmed).is_none() {
+            return false;
+        }
+        keys += 1;
+    }
+
+    keys > 0
+}
+
+/// Length of the key at the start of a YAML line, like `name` in
+/// `name: slate`.
+fn key_length(line: &str) -> Option<usize> {
+    let index = match line.find(':') {
+        Some(index) if index > 0 => index,
+        _ => return None,
+    };
+    let key = &line[..index];
+    let valid = !key.contains(char::is_whitespace) && !key.contains('"') && !key.contains('#');
+    let separated = line[index + 1..].chars().next().map(char::is_whitespace).unwrap_or(true);
+
+    if valid && separated { Some(index) } else { None }
+}
+
+/// Highlight a value with the theme, if its language is known.
+///
+/// # Example
+///
+/// ```rust
+/// use slate::highlight::{highlight, Theme};
+///
+/// let theme = Theme::default();
+/// assert_eq!("\x1b[35mif\x1b[0m true", highlight("if true", "shell", &theme));
+/// assert_eq!("if true", highlight("if true", "unknown", &theme));
+/// ```
+pub fn highlight(value: &str, language: &str, theme: &Theme) -> String {
+    let language = normalize(language);
+    let syntax = match SYNTAXES.iter().find(|syntax| syntax.name == language) {
+        Some(syntax) => syntax,
+        None => return value.to_owned(),
+    };
+
+    let mut painted = String::with_capacity(value.len());
+    for (kind, text) in tokenize(value, syntax) {
+        painted.push_str(&paint(text, kind, theme));
+    }
+
+    painted
+}
+
+/// Paint a text with the color of a kind of token.
+///
+/// # Example
+///
+/// ```rust
+/// use slate::highlight::{paint, Kind, Theme};
+///
+/// assert_eq!("\x1b[31m-old\x1b[0m", paint("-old", Kind::Removed, &Theme::default()));
+/// ```
+pub fn paint(text: &str, kind: Kind, theme: &Theme) -> String {
+    let code = theme.code(kind);
+    if code.is_empty() {
+        text.to_owned()
+    } else {
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    }
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Split a value in tokens.
+fn tokenize<'a>(value: &'a str, syntax: &Syntax) -> Vec<(Kind, &'a str)> {
+    let chars: Vec<(usize, char)> = value.char_indices().collect();
+    let offset = |i: usize| if i < chars.len() { chars[i].0 } else { value.len() };
+    let mut tokens = Vec::new();
+    let mut plain = 0;
+    let mut line_start = true;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let (start, c) = chars[i];
+        let rest = &value[start..];
+        let previous = if i > 0 { Some(chars[i - 1].1) } else { None };
+        let after_space = previous.map(char::is_whitespace).unwrap_or(true);
+
+        let mut end = i + 1;
+        let kind = if after_space && syntax.comments.iter().any(|prefix| rest.starts_with(prefix)) {
+            while end < chars.len() && chars[end].1 != '\n' {
+                end += 1;
+            }
+            Kind::Comment
+        } else if syntax.quotes.contains(c) {
+            while end < chars.len() && chars[end].1 != c {
+                end += if chars[end].1 == '\\' { 2 } else { 1 };
+            }
+            end = ::std::cmp::min(end + 1, chars.len());
+            let next = chars[end..].iter().map(|&(_, c)| c).find(|c| *c != ' ');
+            if syntax.keys && next == Some(':') { Kind::Key } else { Kind::Str }
+        } else if syntax.variables && c == '$' && end < chars.len() {
+            let next = chars[end].1;
+            if next == '{' {
+                while end < chars.len() && chars[end].1 != '}' {
+                    end += 1;
+                }
+                end = ::std::cmp::min(end + 1, chars.len());
+            } else if is_word(next) {
+                while end < chars.len() && is_word(chars[end].1) {
+                    end += 1;
+                }
+            } else if "?#@*!$-".contains(next) {
+                end += 1;
+            }
+            if end > i + 1 { Kind::Variable } else { Kind::Plain }
+        } else if syntax.keys && line_start && !c.is_whitespace() && c != '-' &&
+                  key_length(rest.lines().next().unwrap_or("")).is_some() {
+            let length = key_length(rest).unwrap_or(0);
+            while end < chars.len() && chars[end].0 < start + length {
+                end += 1;
+            }
+            Kind::Key
+        } else if c.is_digit(10) && !previous.map(is_word).unwrap_or(false) {
+            while end < chars.len() && (is_word(chars[end].1) || chars[end].1 == '.') {
+                end += 1;
+            }
+            Kind::Number
+        } else if is_word(c) && !previous.map(is_word).unwrap_or(false) {
+            while end < chars.len() && is_word(chars[end].1) {
+                end += 1;
+            }
+            let word = &value[start..offset(end)];
+            let keyword = if syntax.ignore_case {
+                syntax.keywords.contains(&word.to_lowercase().as_str())
+            } else {
+                syntax.keywords.contains(&word)
+            };
+            if keyword { Kind::Keyword } else { Kind::Plain }
+        } else {
+            Kind::Plain
+        };
+
+        if c == '\n' {
+            line_start = true;
+        } else if !(c.is_whitespace() || (c == '-' && syntax.keys)) {
+            line_start = false;
+        }
+
+        let end = ::std::cmp::min(end, chars.len());
+        if kind != Kind::Plain {
+            if plain < start {
+                tokens.push((Kind::Plain, &value[plain..start]));
+            }
+            tokens.push((kind, &value[start..offset(end)]));
+            plain = offset(end);
+        }
+        i = end;
+    }
+
+    if plain < value.len() {
+        tokens.push((Kind::Plain, &value[plain..]));
+    }
+
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds<'a>(value: &'a str, language: &str) -> Vec<(Kind, &'a str)> {
+        let syntax = SYNTAXES.iter().find(|syntax| syntax.name == language).unwrap();
+
+        tokenize(value, syntax).into_iter().filter(|&(kind, _)| kind != Kind::Plain).collect()
+    }
+
+    #[test]
+    fn it_tokenizes_shell() {
+        assert_eq!(vec![(Kind::Keyword, "for"),
+                        (Kind::Keyword, "in"),
+                        (Kind::Variable, "$FILES"),
+                        (Kind::Keyword, "do"),
+                        (Kind::Str, "\"a b\""),
+                        (Kind::Variable, "${f}"),
+                        (Kind::Keyword, "done"),
+                        (Kind::Comment, "# loop")],
+                   kinds("for f in $FILES; do echo \"a b\" ${f}; done # loop", "shell"));
+        assert_eq!(Vec::<(Kind, &str)>::new(), kinds("echo a#b", "shell"));
+    }
+
+    #[test]
+    fn it_tokenizes_sql_ignoring_case() {
+        assert_eq!(vec![(Kind::Keyword, "SELECT"),
+                        (Kind::Keyword, "from"),
+                        (Kind::Keyword, "WHERE"),
+                        (Kind::Number, "42"),
+                        (Kind::Comment, "-- all")],
+                   kinds("SELECT id from users WHERE age > 42 -- all", "sql"));
+    }
+
+    #[test]
+    fn it_tokenizes_keys() {
+        assert_eq!(vec![(Kind::Key, "name"),
+                        (Kind::Str, "\"slate\""),
+                        (Kind::Key, "debug"),
+                        (Kind::Keyword, "true"),
+                        (Kind::Key, "port"),
+                        (Kind::Number, "80")],
+                   kinds("name: \"slate\"\ndebug: true\n- port: 80", "yaml"));
+        assert_eq!(vec![(Kind::Key, "\"a\""),
+                        (Kind::Number, "1"),
+                        (Kind::Key, "\"c\""),
+                        (Kind::Str, "\"b\"")],
+                   kinds("{\"a\": 1, \"c\": \"b\"}", "json"));
+    }
+
+    #[test]
+    fn it_keeps_the_text_when_highlighting() {
+        let value = "#!/bin/sh\necho \"ünïcödé\" $HOME 'unterminated";
+        let painted = highlight(value, "sh", &Theme::default());
+
+        let mut plain = String::new();
+        let mut escaping = false;
+        for c in painted.chars() {
+            match c {
+                '\x1b' => escaping = true,
+                'm' if escaping => escaping = false,
+                _ if escaping => (),
+                _ => plain.push(c),
+            }
+        }
+        assert_eq!(value, plain);
+    }
+
+    #[test]
+    fn it_detects_languages() {
+        assert_eq!(Some("shell"), detect("#!/bin/bash\nls"));
+        assert_eq!(Some("javascript"), detect("#!/usr/bin/env -S node --harmony\n"));
+        assert_eq!(Some("json"), detect("{\"a\": [1, 2]}"));
+        assert_eq!(Some("shell"), detect("docker ps -a | grep redis"));
+        assert_eq!(Some("shell"), detect("for f in *; do echo $f; done"));
+        assert_eq!(Some("yaml"), detect("a:\n  - 1\n  - 2\nb: c"));
+        assert_eq!(None, detect("Call me: maybe later"));
+        assert_eq!(None, detect("#!/usr/bin/perl"));
+    }
+
+    #[test]
+    fn it_parses_themes() {
+        assert_eq!(Ok(Theme::default()), "default".parse());
+        let theme: Theme = "keyword=1;31, comment=2".parse().unwrap();
+        assert_eq!("1;31", theme.keyword);
+        assert_eq!("2", theme.comment);
+        assert_eq!(Theme::default().string, theme.string);
+        assert!("neon".parse::<Theme>().is_err());
+        assert!("keyword=red".parse::<Theme>().is_err());
+    }
+
+    #[test]
+    fn it_never_colors_unless_asked() {
+        assert!(!Color::Never.enabled(true));
+        assert!(Color::Always.enabled(false));
+        assert!(!Color::Auto.enabled(false));
+    }
+}
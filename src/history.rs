@@ -0,0 +1,137 @@
+//! Revisions of the values of the keys.
+//!
+//! Every value saved with `Slate::set` is added to the history of
+//! its key, in a sidecar file next to the store like
+//! `~/.slate.history`. Only the last `LIMIT` revisions are kept.
+//! Like the usage, the history is not essential: if the sidecar
+//! cannot be written the changes are still saved in the store.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use config::Config;
+use sidecar;
+
+/// Number of revisions kept for each key.
+pub const LIMIT: usize = 50;
+
+/// A value that a key had at some point.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Revision {
+    pub value: String,
+    /// When the value was saved, in seconds since the Unix epoch,
+    /// if it is known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time: Option<u64>,
+}
+
+/// Revisions of every key, oldest first.
+pub type History = HashMap<String, Vec<Revision>>;
+
+/// Path of the sidecar file with the history of a store.
+pub fn path(config: &Config) -> PathBuf {
+    sidecar::path(config, "history")
+}
+
+/// Read the history of a store.
+///
+/// It is empty if the sidecar doesn't exist or cannot be read.
+pub fn read(config: &Config) -> History {
+    sidecar::read(&path(config))
+}
+
+/// Change the history of a store.
+///
+/// Errors are ignored, so a store can be used even if the sidecar
+/// cannot be written. When a daemon is serving the store it keeps
+/// the history instead, so nothing is done.
+pub fn update<F>(config: &Config, f: F)
+    where F: FnOnce(&mut History)
+{
+    if config.socket.is_some() {
+        return;
+    }
+
+    let _ = sidecar::update(&path(config), f);
+}
+
+/// Add a new value to the history of a key.
+///
+/// The previous value is added first, without time, if it is not
+/// the last revision, like values saved by older versions.
+pub fn record(history: &mut History, key: &str, previous: Option<&str>, value: &str, now: u64) {
+    let revisions = history.entry(key.to_owned()).or_insert_with(Vec::new);
+    if revisions.last().map(|last| last.value == value).unwrap_or(false) {
+        return;
+    }
+
+    if let Some(previous) = previous {
+        if revisions.last().map(|last| last.value != previous).unwrap_or(true) {
+            revisions.push(Revision { value: previous.to_owned(), time: None });
+        }
+    }
+    revisions.push(Revision { value: value.to_owned(), time: Some(now) });
+
+    let extra = revisions.len().saturating_sub(LIMIT);
+    revisions.drain(..extra);
+}
+
+/// Get the revisions of a key with the given current value.
+///
+/// The current value is always the last revision, even when it
+/// was saved without going through `Slate`.
+pub fn revisions(history: &History, key: &str, current: &str) -> Vec<Revision> {
+    let mut revisions = history.get(key).cloned().unwrap_or_default();
+
+    if revisions.last().map(|last| last.value != current).unwrap_or(true) {
+        revisions.push(Revision { value: current.to_owned(), time: None });
+    }
+
+    revisions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(revisions: &Vec<Revision>) -> Vec<&str> {
+        revisions.iter().map(|revision| revision.value.as_str()).collect()
+    }
+
+    #[test]
+    fn it_records_revisions() {
+        let mut history = History::new();
+
+        record(&mut history, "a", None, "1", 10);
+        record(&mut history, "a", Some("1"), "1", 20);
+        record(&mut history, "a", Some("1"), "2", 30);
+        record(&mut history, "b", Some("old"), "new", 40);
+
+        assert_eq!(vec!["1", "2"], values(&history["a"]));
+        assert_eq!(Some(30), history["a"][1].time);
+        assert_eq!(vec!["old", "new"], values(&history["b"]));
+        assert_eq!(None, history["b"][0].time);
+    }
+
+    #[test]
+    fn it_keeps_the_last_revisions() {
+        let mut history = History::new();
+
+        for i in 0..(LIMIT + 5) {
+            record(&mut history, "a", None, &i.to_string(), i as u64);
+        }
+
+        assert_eq!(LIMIT, history["a"].len());
+        assert_eq!("5", history["a"][0].value);
+    }
+
+    #[test]
+    fn it_adds_the_current_value_to_the_revisions() {
+        let mut history = History::new();
+        record(&mut history, "a", None, "1", 10);
+
+        assert_eq!(vec!["1"], values(&revisions(&history, "a", "1")));
+        assert_eq!(vec!["1", "2"], values(&revisions(&history, "a", "2")));
+        assert_eq!(vec!["3"], values(&revisions(&history, "b", "3")));
+    }
+}
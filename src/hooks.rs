@@ -0,0 +1,394 @@
+//! Lifecycle hooks run around the changes of a store.
+//!
+//! Hooks are executables inside the directory set in the env var
+//! `SLATE_HOOKS`, named after the hook (`pre-set`, `post-set`,
+//! `pre-remove`, `post-remove` and `post-rename`). They get the
+//! change in env vars, and the new value in their stdin too. A
+//! pre hook that exits with an error cancels the change.
+//!
+//! Values larger than `MAX_VAR_LENGTH`, or with NUL bytes, can't be
+//! passed in env vars, so they are left out of them. The new value
+//! is always in the stdin of the hook.
+
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use config::Config;
+use errors::SlateError;
+use plugins::is_executable;
+use results::SlateResult;
+
+/// Largest value passed to the hooks in an env var, in bytes.
+pub const MAX_VAR_LENGTH: usize = 32 * 1024;
+
+/// Buffer where the output of the hooks can be collected, see
+/// `Config::hook_output`.
+pub type Output = Arc<Mutex<Vec<u8>>>;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Hook {
+    PreSet,
+    PostSet,
+    PreRemove,
+    PostRemove,
+    PostRename,
+}
+
+impl Hook {
+    /// Name of the hook, also used as name of its executable.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Hook::PreSet => "pre-set",
+            Hook::PostSet => "post-set",
+            Hook::PreRemove => "pre-remove",
+            Hook::PostRemove => "post-remove",
+            Hook::PostRename => "post-rename",
+        }
+    }
+
+    /// Whether the hook runs before the change, and so can veto it.
+    pub fn is_pre(&self) -> bool {
+        match *self {
+            Hook::PreSet | Hook::PreRemove => true,
+            _ => false,
+        }
+    }
+}
+
+/// A change in a store, as seen by the hooks.
+#[derive(Debug, Default)]
+pub struct Event<'a> {
+    pub key: &'a str,
+    /// The new name of the key, when renamed.
+    pub new_key: Option<&'a str>,
+    pub old_value: Option<&'a str>,
+    pub new_value: Option<&'a str>,
+}
+
+/// Find the executable of a hook, if it is configured.
+///
+/// Changes sent to a daemon run the hooks of the daemon instead.
+pub fn find(config: &Config, hook: Hook) -> Option<PathBuf> {
+    if config.socket.is_some() {
+        return None;
+    }
+
+    match config.hooks {
+        Some(ref dir) => {
+            let path = dir.join(hook.name());
+            if is_executable(&path) { Some(path) } else { None }
+        }
+        None => None,
+    }
+}
+
+/// Whether any of the hooks is configured.
+pub fn any(config: &Config, hooks: &[Hook]) -> bool {
+    hooks.iter().any(|&hook| find(config, hook).is_some())
+}
+
+/// Run a hook, if it is configured.
+///
+/// The env vars `SLATE_HOOK`, `SLATE_KEY`, `SLATE_NEW_KEY`,
+/// `SLATE_OLD_VALUE` and `SLATE_NEW_VALUE` describe the change.
+/// The output of the hook goes to the output of the configuration,
+/// or to stderr if it has none. It returns a `SlateError::Vetoed`
+/// error when a pre hook fails; the exit status of post hooks is
+/// ignored.
+pub fn run(config: &Config, hook: Hook, event: &Event) -> SlateResult<()> {
+    let executable = match find(config, hook) {
+        Some(executable) => executable,
+        None => return Ok(()),
+    };
+
+    let mut command = Command::new(executable);
+    command.envs(config.vars())
+        .env("SLATE_HOOK", hook.name())
+        .env("SLATE_KEY", event.key)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(new_key) = event.new_key {
+        command.env("SLATE_NEW_KEY", new_key);
+    }
+    if let Some(old_value) = event.old_value.filter(|value| fits(value)) {
+        command.env("SLATE_OLD_VALUE", old_value);
+    }
+    if let Some(new_value) = event.new_value.filter(|value| fits(value)) {
+        command.env("SLATE_NEW_VALUE", new_value);
+    }
+
+    let mut child = try!(command.spawn());
+    // The value is written from another thread while the output is
+    // read, so hooks that write a lot before reading it don't block.
+    let input = event.new_value.unwrap_or("").as_bytes().to_vec();
+    let writer = child.stdin.take().map(|mut stdin| {
+        thread::spawn(move || {
+            // The hook may exit without reading its input.
+            let _ = stdin.write_all(&input);
+        })
+    });
+    let output = try!(child.wait_with_output());
+    if let Some(writer) = writer {
+        let _ = writer.join();
+    }
+
+    try!(forward(config.hook_output.as_ref(), &output.stdout));
+    try!(forward(config.hook_output.as_ref(), &output.stderr));
+
+    if hook.is_pre() && !output.status.success() {
+        return Err(SlateError::Vetoed(hook.name().to_string()));
+    }
+
+    Ok(())
+}
+
+/// Write the output of hooks in a buffer, or in stderr if there
+/// is none.
+pub fn forward(buffer: Option<&Output>, output: &[u8]) -> io::Result<()> {
+    match buffer {
+        Some(buffer) => {
+            if let Ok(mut buffer) = buffer.lock() {
+                buffer.extend_from_slice(output);
+            }
+
+            Ok(())
+        }
+        None => io::stderr().write_all(output),
+    }
+}
+
+/// Whether a value can be passed in an env var.
+fn fits(value: &str) -> bool {
+    value.len() <= MAX_VAR_LENGTH && !value.contains('\0')
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::prelude::*;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
+    use rand::{thread_rng, Rng};
+    use Slate;
+    use daemon;
+    use history;
+    use transfer::{self, Mode};
+
+    fn temp_path(extension: &str) -> PathBuf {
+        let random_name: String = thread_rng().gen_ascii_chars().take(10).collect();
+        let mut temp = env::temp_dir();
+        temp.push(random_name + extension);
+
+        temp
+    }
+
+    fn create_hook(dir: &Path, hook: Hook, body: &str) {
+        let path = dir.join(hook.name());
+        File::create(&path).unwrap().write_all(body.as_bytes()).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    fn read(path: &Path) -> String {
+        let mut contents = String::new();
+        File::open(path).unwrap().read_to_string(&mut contents).unwrap();
+
+        contents
+    }
+
+    fn config_with_hooks() -> Config {
+        let dir = temp_path("");
+        fs::create_dir(&dir).unwrap();
+
+        Config {
+            filepath: temp_path(".json"),
+            hooks: Some(dir),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn it_runs_hooks_around_changes() {
+        let config = config_with_hooks();
+        let dir = config.hooks.clone().unwrap();
+        let log = dir.join("log");
+        let body = format!("#!/bin/sh\necho \"$SLATE_HOOK $SLATE_KEY $SLATE_NEW_KEY \
+                            $SLATE_OLD_VALUE $SLATE_NEW_VALUE\" >> {}\n",
+                           log.display());
+        for hook in &[Hook::PostSet, Hook::PostRemove, Hook::PostRename] {
+            create_hook(&dir, *hook, &body);
+        }
+        let slate: Slate = From::from(&config);
+
+        slate.set(&"a".to_string(), &"1".to_string()).unwrap();
+        slate.set(&"a".to_string(), &"2".to_string()).unwrap();
+        slate.rename(&"a".to_string(), &"b".to_string(), false).unwrap();
+        slate.remove(&"b".to_string()).unwrap();
+
+        assert_eq!("post-set a   1\npost-set a  1 2\npost-rename a b 2 2\npost-remove b  2 \n",
+                   read(&log));
+    }
+
+    #[test]
+    fn it_cancels_changes_rejected_by_pre_hooks() {
+        let config = config_with_hooks();
+        let dir = config.hooks.clone().unwrap();
+        create_hook(&dir, Hook::PreSet, "#!/bin/sh\n! grep -q TODO\n");
+        create_hook(&dir, Hook::PreRemove, "#!/bin/sh\n[ \"$SLATE_KEY\" != keep ]\n");
+        let slate: Slate = From::from(&config);
+
+        slate.set(&"keep".to_string(), &"done".to_string()).unwrap();
+        match slate.set(&"keep".to_string(), &"TODO".to_string()) {
+            Err(SlateError::Vetoed(ref hook)) => assert_eq!("pre-set", hook),
+            other => panic!("Expected a vetoed change, got {:?}", other),
+        }
+        assert!(slate.remove(&"keep".to_string()).is_err());
+
+        assert_eq!("done", slate.get(&"keep".to_string()).unwrap());
+    }
+
+    #[test]
+    fn it_runs_hooks_around_transactions() {
+        let config = config_with_hooks();
+        let dir = config.hooks.clone().unwrap();
+        let log = dir.join("log");
+        create_hook(&dir, Hook::PreSet, "#!/bin/sh\n! grep -q TODO\n");
+        create_hook(&dir,
+                    Hook::PostSet,
+                    &format!("#!/bin/sh\necho \"$SLATE_KEY\" >> {}\n", log.display()));
+        let slate: Slate = From::from(&config);
+        let (a, b) = ("a".to_string(), "b".to_string());
+
+        let vetoed = slate.transaction(|tx| {
+            tx.set(&a, &"done".to_string());
+            tx.set(&b, &"TODO".to_string());
+            Ok(())
+        });
+        match vetoed {
+            Err(SlateError::Vetoed(ref hook)) => assert_eq!("pre-set", hook),
+            other => panic!("Expected a vetoed transaction, got {:?}", other),
+        }
+        assert!(slate.list().unwrap().is_empty());
+
+        slate.transaction(|tx| {
+                tx.set(&a, &"1".to_string());
+                tx.set(&b, &"2".to_string());
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!("a\nb\n", read(&log));
+    }
+
+    #[test]
+    fn it_runs_hooks_when_moving_keys() {
+        let config = config_with_hooks();
+        let dir = config.hooks.clone().unwrap();
+        let log = dir.join("log");
+        create_hook(&dir, Hook::PreRemove, "#!/bin/sh\ntest \"$SLATE_KEY\" != locked\n");
+        create_hook(&dir,
+                    Hook::PostRemove,
+                    &format!("#!/bin/sh\necho \"$SLATE_KEY\" >> {}\n", log.display()));
+        let other = Config { filepath: temp_path(".json"), ..config.clone() };
+        let (from, to): (Slate, Slate) = (From::from(&config), From::from(&other));
+        let (a, locked) = ("a".to_string(), "locked".to_string());
+        from.set(&a, &"1".to_string()).unwrap();
+        from.set(&locked, &"2".to_string()).unwrap();
+
+        let keys = vec![a.clone(), locked.clone()];
+        match transfer::transfer(&from, &to, &keys, Mode::Move, false) {
+            Err(SlateError::Vetoed(ref hook)) => assert_eq!("pre-remove", hook),
+            other => panic!("Expected a vetoed transfer, got {:?}", other),
+        }
+        assert_eq!(vec!["a", "locked"], from.list().unwrap());
+        assert!(to.list().unwrap().is_empty());
+
+        transfer::transfer(&from, &to, &keys[..1], Mode::Move, false).unwrap();
+        assert_eq!(vec!["a"], to.list().unwrap());
+        assert_eq!("a\n", read(&log));
+    }
+
+    #[test]
+    fn it_runs_hooks_when_moving_keys_served_by_a_daemon() {
+        let config = config_with_hooks();
+        let dir = config.hooks.clone().unwrap();
+        let log = dir.join("log");
+        let body = format!("#!/bin/sh\necho \"$SLATE_HOOK $SLATE_KEY\" >> {}\n", log.display());
+        for hook in &[Hook::PreSet, Hook::PostSet, Hook::PreRemove, Hook::PostRemove] {
+            create_hook(&dir, *hook, &body);
+        }
+        let socket = daemon::socket_path(&config);
+        {
+            let (config, socket) = (config.clone(), socket.clone());
+            thread::spawn(move || daemon::serve(&config, &socket, None, None));
+        }
+        while !daemon::is_running(&socket) {
+            thread::sleep(::std::time::Duration::from_millis(10));
+        }
+        let a = "a".to_string();
+        Slate::from(&config).set(&a, &"1".to_string()).unwrap();
+        fs::remove_file(&log).unwrap();
+
+        let client = Config { socket: Some(socket), ..config.clone() };
+        let other = Config { filepath: temp_path(".json"), ..config.clone() };
+        let (from, to): (Slate, Slate) = (From::from(&client), From::from(&other));
+        transfer::transfer(&from, &to, &[a.clone()], Mode::Move, false).unwrap();
+
+        assert_eq!("pre-set a\npre-remove a\npost-set a\npost-remove a\n", read(&log));
+        assert!(history::read(&config).get(&a).is_none());
+        assert!(history::read(&other).get(&a).is_some());
+    }
+
+    #[test]
+    fn it_gives_large_values_in_stdin() {
+        let mut config = config_with_hooks();
+        let dir = config.hooks.clone().unwrap();
+        create_hook(&dir,
+                    Hook::PostSet,
+                    "#!/bin/sh\necho \"${SLATE_NEW_VALUE:-none}\"\nwc -c | tr -d ' '\n");
+        let output: Output = Default::default();
+        config.hook_output = Some(output.clone());
+        let slate: Slate = From::from(&config);
+
+        slate.set(&"small".to_string(), &"1".to_string()).unwrap();
+        slate.set(&"large".to_string(), &"x".repeat(200 * 1024)).unwrap();
+        slate.set(&"nul".to_string(), &"a\0b".to_string()).unwrap();
+
+        assert_eq!("1\n1\nnone\n204800\nnone\n3\n",
+                   String::from_utf8(output.lock().unwrap().clone()).unwrap());
+    }
+
+    #[test]
+    fn it_sends_the_output_of_rejecting_hooks_to_daemon_clients() {
+        let config = config_with_hooks();
+        create_hook(config.hooks.as_ref().unwrap(),
+                    Hook::PreSet,
+                    "#!/bin/sh\necho \"$SLATE_KEY is read-only\" >&2\nexit 1\n");
+        let socket = daemon::socket_path(&config);
+        {
+            let (config, socket) = (config.clone(), socket.clone());
+            thread::spawn(move || daemon::serve(&config, &socket, None, None));
+        }
+        while !daemon::is_running(&socket) {
+            thread::sleep(::std::time::Duration::from_millis(10));
+        }
+
+        let output: Output = Default::default();
+        let client = Config {
+            socket: Some(socket),
+            hook_output: Some(output.clone()),
+            ..config.clone()
+        };
+        let slate: Slate = From::from(&client);
+
+        match slate.set(&"prod".to_string(), &"x".to_string()) {
+            Err(SlateError::Vetoed(ref hook)) => assert_eq!("pre-set", hook),
+            other => panic!("Expected a vetoed change, got {:?}", other),
+        }
+        assert_eq!("prod is read-only\n", String::from_utf8(output.lock().unwrap().clone()).unwrap());
+    }
+}
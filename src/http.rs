@@ -0,0 +1,438 @@
+//! A REST interface over the store.
+//!
+//! Routes:
+//!
+//! * `GET /keys?prefix=<prefix>`: JSON array with the keys.
+//! * `GET /keys/<key>`: the value, as plain text.
+//! * `PUT /keys/<key>`: set the value to the body of the request,
+//!   which can be binary.
+//! * `DELETE /keys/<key>`: remove the key.
+//! * `POST /rename`: rename a key, with a JSON body like
+//!   `{"from": "old", "to": "new", "force": false}`.
+//!
+//! Values have an `ETag`, a hash of the saved value that doesn't
+//! change between versions of slate. Changes can be made conditional with the
+//! `If-Match` and `If-None-Match` headers, and fail with `412` when
+//! the value was changed by someone else. The check and the change
+//! are made in a single locked operation of the store.
+//!
+//! Without a token, the server only listens on loopback addresses.
+
+use serde_json;
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs};
+use tiny_http;
+
+use config::Config;
+use errors::SlateError;
+use message::Change;
+use store::Entry;
+use results::SlateResult;
+use Slate;
+
+/// A request, with the parts the API uses.
+#[derive(Debug, Default)]
+pub struct Request {
+    pub method: String,
+    pub url: String,
+    pub authorization: Option<String>,
+    pub if_match: Option<String>,
+    pub if_none_match: Option<String>,
+    pub body: Vec<u8>,
+}
+
+/// A response to send back.
+#[derive(Debug, PartialEq)]
+pub struct Reply {
+    pub status: u16,
+    pub body: String,
+    pub json: bool,
+    pub etag: Option<String>,
+}
+
+impl Reply {
+    fn text(status: u16, body: &str) -> Reply {
+        Reply {
+            status: status,
+            body: body.to_string(),
+            json: false,
+            etag: None,
+        }
+    }
+
+    fn json(status: u16, body: String) -> Reply {
+        Reply {
+            status: status,
+            body: body,
+            json: true,
+            etag: None,
+        }
+    }
+
+    fn error(status: u16, code: &str, message: &str) -> Reply {
+        let report = ErrorReport {
+            error: ErrorDetails {
+                code: code,
+                message: message.to_string(),
+            },
+        };
+
+        Reply::json(status, serde_json::to_string(&report).unwrap_or_default())
+    }
+
+    fn etag(mut self, value: &str) -> Reply {
+        self.etag = Some(etag(value));
+        self
+    }
+}
+
+impl From<SlateError> for Reply {
+    fn from(err: SlateError) -> Reply {
+        let status = match err {
+            SlateError::NotFound(_) |
+            SlateError::LineNotFound(_, _) => 404,
+            SlateError::Conflict(_) |
+            SlateError::Cycle(_) => 409,
+            SlateError::Changed(_) => 412,
+            SlateError::Binary(_) => 400,
+            SlateError::Vetoed(_) => 403,
+            SlateError::LockTimeout => 503,
+            _ => 500,
+        };
+
+        Reply::error(status, err.kind(), &format!("{}", err))
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorReport<'a> {
+    error: ErrorDetails<'a>,
+}
+
+#[derive(Serialize)]
+struct ErrorDetails<'a> {
+    code: &'a str,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct Rename {
+    from: String,
+    to: String,
+    #[serde(default)]
+    force: bool,
+}
+
+/// The HTTP server.
+pub struct Server {
+    config: Config,
+    token: Option<String>,
+    server: tiny_http::Server,
+}
+
+impl Server {
+    /// Listen on an address, like `127.0.0.1:7777`.
+    ///
+    /// When a token is given, every request must include it
+    /// in an `Authorization: Bearer <token>` header. Without one,
+    /// only loopback addresses are allowed.
+    pub fn bind(config: &Config, address: &str, token: Option<String>) -> SlateResult<Server> {
+        if token.is_none() && !is_loopback(address) {
+            let message = format!("Refusing to listen on {} without a token", address);
+            return Err(SlateError::IO(io::Error::new(io::ErrorKind::InvalidInput, message)));
+        }
+
+        let server = match tiny_http::Server::http(address) {
+            Ok(server) => server,
+            Err(e) => return Err(SlateError::IO(io::Error::new(io::ErrorKind::Other, e.to_string()))),
+        };
+
+        Ok(Server {
+            config: config.clone(),
+            token: token,
+            server: server,
+        })
+    }
+
+    /// Address the server is listening on.
+    pub fn address(&self) -> Option<SocketAddr> {
+        self.server.server_addr().to_ip()
+    }
+
+    /// Handle requests until the process ends.
+    pub fn run(&self) {
+        for mut incoming in self.server.incoming_requests() {
+            let mut request = Request {
+                method: incoming.method().as_str().to_uppercase(),
+                url: incoming.url().to_string(),
+                ..Default::default()
+            };
+            for header in incoming.headers() {
+                let value = Some(header.value.as_str().to_string());
+                if header.field.equiv("Authorization") {
+                    request.authorization = value;
+                } else if header.field.equiv("If-Match") {
+                    request.if_match = value;
+                } else if header.field.equiv("If-None-Match") {
+                    request.if_none_match = value;
+                }
+            }
+
+            let reply = match incoming.as_reader().read_to_end(&mut request.body) {
+                Ok(_) => self.handle(&request),
+                Err(e) => Reply::error(400, "usage", &format!("{}", e)),
+            };
+
+            let content_type = if reply.json { "application/json" } else { "text/plain; charset=utf-8" };
+            let mut response = tiny_http::Response::from_string(reply.body)
+                .with_status_code(reply.status)
+                .with_header(header("Content-Type", content_type));
+            if let Some(ref etag) = reply.etag {
+                response = response.with_header(header("ETag", etag));
+            }
+            if reply.status == 401 {
+                response = response.with_header(header("WWW-Authenticate", "Bearer"));
+            }
+
+            let _ = incoming.respond(response);
+        }
+    }
+
+    /// Get the reply to a request.
+    pub fn handle(&self, request: &Request) -> Reply {
+        if let Some(ref token) = self.token {
+            let expected = format!("Bearer {}", token);
+            let given = request.authorization.as_ref().map(|a| a.as_str()).unwrap_or("");
+            if !same(given, &expected) {
+                return Reply::error(401, "unauthorized", "Missing or invalid token");
+            }
+        }
+
+        let (path, query) = match request.url.find('?') {
+            Some(index) => (&request.url[..index], &request.url[index + 1..]),
+            None => (&request.url[..], ""),
+        };
+        let slate: Slate = From::from(&self.config);
+
+        let result = match (request.method.as_str(), path) {
+            ("GET", "/keys") => list(&slate, query),
+            ("POST", "/rename") => rename(&slate, request),
+            (method, _) if path.starts_with("/keys/") && path.len() > "/keys/".len() => {
+                let key = decode(&path["/keys/".len()..]);
+                match method {
+                    "GET" => get(&slate, &key, request),
+                    "PUT" => put(&slate, &key, request),
+                    "DELETE" => delete(&slate, &key, request),
+                    _ => Ok(Reply::error(405, "usage", "Method not allowed")),
+                }
+            }
+            _ => Ok(Reply::error(404, "not_found", "Unknown route")),
+        };
+
+        match result {
+            Ok(reply) => reply,
+            Err(e) => From::from(e),
+        }
+    }
+}
+
+fn list(slate: &Slate, query: &str) -> SlateResult<Reply> {
+    let prefix = query.split('&')
+        .filter_map(|pair| {
+            let mut parts = pair.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some("prefix"), Some(value)) => Some(decode(&value.replace("+", " "))),
+                _ => None,
+            }
+        })
+        .next()
+        .unwrap_or_default();
+
+    let keys: Vec<String> = try!(slate.list())
+        .into_iter()
+        .filter(|key| key.starts_with(&prefix))
+        .collect();
+
+    Ok(Reply::json(200, try!(serde_json::to_string(&keys))))
+}
+
+fn get(slate: &Slate, key: &String, request: &Request) -> SlateResult<Reply> {
+    let value = try!(slate.get(key));
+
+    if let Some(ref header) = request.if_none_match {
+        if matches(header, Some(&value)) {
+            return Ok(Reply::text(304, "").etag(&value));
+        }
+    }
+
+    Ok(Reply::text(200, &value).etag(&value))
+}
+
+fn put(slate: &Slate, key: &String, request: &Request) -> SlateResult<Reply> {
+    let (old, new) = try!(slate.update(key, |old| {
+        try!(precondition(key, request, old.map(|entry| &entry.value)));
+
+        Ok(Entry::from_bytes(request.body.clone()))
+    }));
+    let status = if old.is_some() { 200 } else { 201 };
+
+    Ok(Reply::text(status, "").etag(&new.value))
+}
+
+fn delete(slate: &Slate, key: &String, request: &Request) -> SlateResult<Reply> {
+    try!(slate.transaction(|tx| {
+        let current = try!(tx.get(key));
+        try!(precondition(key, request, Some(&current)));
+
+        tx.remove(key);
+        Ok(())
+    }));
+
+    Ok(Reply::text(204, ""))
+}
+
+fn rename(slate: &Slate, request: &Request) -> SlateResult<Reply> {
+    let rename: Rename = match serde_json::from_slice(&request.body) {
+        Ok(rename) => rename,
+        Err(e) => return Ok(Reply::error(400, "usage", &format!("{}", e))),
+    };
+
+    try!(slate.rename(&rename.from, &rename.to, rename.force));
+    let change = Change::new("rename").key(&rename.to).from(&rename.from);
+
+    Ok(Reply::json(200, try!(serde_json::to_string(&change))))
+}
+
+/// Whether an address only resolves to loopback addresses.
+fn is_loopback(address: &str) -> bool {
+    match address.to_socket_addrs() {
+        Ok(addresses) => {
+            let addresses: Vec<SocketAddr> = addresses.collect();
+            !addresses.is_empty() && addresses.iter().all(|a| a.ip().is_loopback())
+        }
+        Err(_) => false,
+    }
+}
+
+/// Check the conditional headers of a change against the current
+/// value, failing with a `SlateError::Changed` error.
+fn precondition(key: &String, request: &Request, current: Option<&String>) -> SlateResult<()> {
+    let failed = match (&request.if_match, &request.if_none_match) {
+        (&Some(ref header), _) if !matches(header, current) => true,
+        (_, &Some(ref header)) if matches(header, current) => true,
+        _ => false,
+    };
+
+    if failed {
+        Err(SlateError::Changed(key.to_owned()))
+    } else {
+        Ok(())
+    }
+}
+
+/// Whether an `If-Match` or `If-None-Match` header matches a value.
+fn matches(header: &str, value: Option<&String>) -> bool {
+    let value = match value {
+        Some(value) => value,
+        None => return false,
+    };
+    if header.trim() == "*" {
+        return true;
+    }
+
+    let tag = etag(value);
+    header.split(',').any(|candidate| candidate.trim().trim_left_matches("W/") == tag)
+}
+
+/// Compare secrets in constant time.
+fn same(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Entity tag of a value, with the 64 bits FNV-1a hash of its
+/// bytes, which is the same in every build.
+///
+/// # Example
+///
+/// ```rust
+/// use slate::http;
+///
+/// assert_eq!("\"003934191339461a\"", http::etag("bar"));
+/// ```
+pub fn etag(value: &str) -> String {
+    let hash = value.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+
+    format!("\"{:016x}\"", hash)
+}
+
+/// Decode percent-encoded parts of a URL.
+fn decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = String::from_utf8_lossy(&bytes[i + 1..i + 3]);
+            if let Ok(byte) = u8::from_str_radix(&hex, 16) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn header(field: &str, value: &str) -> tiny_http::Header {
+    tiny_http::Header::from_bytes(field.as_bytes(), value.as_bytes()).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_decodes_urls() {
+        assert_eq!("a b/c", decode("a%20b%2Fc"));
+        assert_eq!("100%", decode("100%"));
+        assert_eq!("%zz", decode("%zz"));
+    }
+
+    #[test]
+    fn it_matches_etags() {
+        let value = "bar".to_string();
+        let header = format!("\"other\", {}", etag(&value));
+
+        assert!(matches(&header, Some(&value)));
+        assert!(matches("*", Some(&value)));
+        assert!(!matches("*", None));
+        assert!(!matches("\"other\"", Some(&value)));
+    }
+
+    #[test]
+    fn it_puts_binary_values() {
+        let filepath = ::std::env::temp_dir().join("slate-http-binary.json");
+        let _ = ::std::fs::remove_file(&filepath);
+        let config = Config { filepath: filepath, ..Default::default() };
+        let server = Server::bind(&config, "127.0.0.1:0", None).unwrap();
+        let request = Request {
+            method: "PUT".to_string(),
+            url: "/keys/logo".to_string(),
+            body: vec![0x89, b'P', b'N', b'G', 0xff],
+            ..Default::default()
+        };
+
+        let reply = server.handle(&request);
+
+        assert_eq!(201, reply.status);
+        let slate: Slate = From::from(&config);
+        assert_eq!(vec![0x89, b'P', b'N', b'G', 0xff], slate.get_bytes(&"logo".to_string()).unwrap());
+        assert_eq!(Some(etag(&slate.get(&"logo".to_string()).unwrap())), reply.etag);
+    }
+}
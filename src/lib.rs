@@ -3,6 +3,9 @@ extern crate serde_derive;
 extern crate serde_json;
 extern crate serde;
 extern crate docopt;
+extern crate rusqlite;
+extern crate strsim;
+extern crate tiny_http;
 
 #[cfg(test)]
 extern crate rand;
@@ -13,13 +16,46 @@ pub mod message;
 pub mod errors;
 pub mod results;
 pub mod config;
+pub mod completions;
+pub mod plugins;
+pub mod hooks;
+#[cfg(unix)]
+pub mod daemon;
+pub mod http;
+pub mod store;
+pub mod transaction;
+pub mod transfer;
+pub mod watch;
+pub mod time;
+pub mod base64;
+pub mod files;
+pub mod sidecar;
+pub mod usage;
+pub mod history;
+pub mod links;
+pub mod stats;
+pub mod doctor;
+pub mod dedupe;
+pub mod diff;
+pub mod highlight;
 
-use std::path::PathBuf;
-use std::io::prelude::*;
-use std::fs::File;
-use std::collections::HashMap;
+use std::cmp;
+use std::collections::HashSet;
+use std::io;
+use std::mem;
+use std::path::{Path, PathBuf};
 use results::SlateResult;
+use errors::SlateError;
 use config::Config;
+use store::{Contents, Entry, Store};
+use hooks::{Event, Hook};
+use transaction::Transaction;
+use watch::Subscription;
+use usage::Sort;
+use history::Revision;
+use links::Linked;
+use stats::Summary;
+use dedupe::Group;
 
 /// The main Key-Value structure.
 #[derive(Clone)]
@@ -40,6 +76,11 @@ impl<'s> Slate<'s> {
 
     /// Set a key with its value.
     ///
+    /// Aliases are followed, so the key they point to is the one
+    /// changed. The `pre-set` and `post-set` hooks are run around
+    /// the change, if configured. It returns a `SlateError::Vetoed`
+    /// error if the `pre-set` hook rejects it.
+    ///
     /// # Example
     ///
     /// ```rust
@@ -50,7 +91,7 @@ impl<'s> Slate<'s> {
     /// let mut temp = env::temp_dir();
     /// temp.push(".slate");
     ///
-    /// let config = Config { filepath: temp };
+    /// let config = Config { filepath: temp, ..Default::default() };
     /// let slate: Slate = From::from(&config);
     /// let key = "foo".to_string();
     /// let value = "bar".to_string();
@@ -61,15 +102,212 @@ impl<'s> Slate<'s> {
     /// };
     /// ```
     pub fn set(&self, key: &String, value: &String) -> SlateResult<()> {
-        let mut contents = try!(self.read());
+        self.set_entry(key, &Entry::new(value))
+    }
+
+    /// Set a key with its value and attributes, like its expiration.
+    ///
+    /// It follows aliases and runs the same hooks as `Slate::set`.
+    /// Entries that are aliases themselves replace the key as it is.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use slate::config::Config;
+    /// use slate::store::Entry;
+    /// use slate::{time, Slate};
+    /// use std::env;
+    ///
+    /// let mut temp = env::temp_dir();
+    /// temp.push(".slate");
+    ///
+    /// let config = Config { filepath: temp, ..Default::default() };
+    /// let slate: Slate = From::from(&config);
+    /// let entry = Entry { expires: Some(time::now() + 3600), ..Entry::new("secret") };
+    ///
+    /// match slate.set_entry(&"token".to_string(), &entry) {
+    ///   Ok(_) => println!("Saved for one hour"),
+    ///   Err(e) => panic!("{}", e),
+    /// };
+    /// ```
+    pub fn set_entry(&self, key: &String, entry: &Entry) -> SlateResult<()> {
+        let store = try!(self.store());
+        let (chain, old) = if entry.is_alias() {
+            (vec![key.to_owned()], try!(store.get(key)))
+        } else {
+            try!(store::follow(&*store, key))
+        };
+        let key = &chain[chain.len() - 1];
+        let event = Event {
+            key: key,
+            old_value: old.as_ref().map(|e| e.value.as_str()),
+            new_value: Some(&entry.value),
+            ..Default::default()
+        };
 
-        contents.insert(key.to_owned(), value.to_owned());
+        try!(hooks::run(self.config, Hook::PreSet, &event));
+        try!(store.set(key, entry));
 
-        self.write(&contents)
+        let now = time::now();
+        usage::update(self.config, |stats| usage::changed(stats, key, old.is_none(), now));
+        history::update(self.config, |history| {
+            history::record(history, key, event.old_value, &entry.value, now);
+        });
+        hooks::run(self.config, Hook::PostSet, &event)
+    }
+
+    /// Set a key with any bytes as its value, like the contents of
+    /// an image or a keystore.
+    ///
+    /// Values that are not valid UTF-8 are saved with base64. It
+    /// runs the same hooks as `Slate::set`, which get the encoded
+    /// value.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use slate::config::Config;
+    /// use slate::Slate;
+    /// use std::env;
+    ///
+    /// let mut temp = env::temp_dir();
+    /// temp.push(".slate");
+    ///
+    /// let config = Config { filepath: temp, ..Default::default() };
+    /// let slate: Slate = From::from(&config);
+    /// let key = "pixel".to_string();
+    ///
+    /// slate.set_bytes(&key, &[0x89, 0x50, 0x4e, 0x47]).unwrap();
+    /// assert_eq!(vec![0x89, 0x50, 0x4e, 0x47], slate.get_bytes(&key).unwrap());
+    /// ```
+    pub fn set_bytes(&self, key: &String, bytes: &[u8]) -> SlateResult<()> {
+        self.set_entry(key, &Entry::from_bytes(bytes.to_vec()))
+    }
+
+    /// Add some text at the end of the value of a key.
+    ///
+    /// The text goes in a new line if the value doesn't end with
+    /// one. The key is created if it doesn't exist, and otherwise
+    /// it keeps its attributes. The store is read and written once,
+    /// locked, so values can be built up from several processes.
+    /// It runs the same hooks as `Slate::set`. Binary values have no
+    /// lines, so they are refused with a `SlateError::Binary` error.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use slate::config::Config;
+    /// use slate::Slate;
+    /// use std::env;
+    ///
+    /// let mut temp = env::temp_dir();
+    /// temp.push(".slate");
+    ///
+    /// let config = Config { filepath: temp, ..Default::default() };
+    /// let slate: Slate = From::from(&config);
+    ///
+    /// match slate.append(&"hosts".to_string(), &"web-3.example.com".to_string()) {
+    ///   Ok(_) => println!("Added"),
+    ///   Err(e) => panic!("{}", e),
+    /// };
+    /// ```
+    pub fn append(&self, key: &String, text: &String) -> SlateResult<()> {
+        try!(self.update(key, |old| {
+            let entry = try!(text_entry(key, old)).unwrap_or_default();
+            Ok(Entry { value: join_lines(&entry.value, text), ..entry })
+        }));
+
+        Ok(())
+    }
+
+    /// Add some text at the beginning of the value of a key.
+    ///
+    /// It works like `Slate::append`: the text goes in its own
+    /// line, and the key is created if it doesn't exist.
+    pub fn prepend(&self, key: &String, text: &String) -> SlateResult<()> {
+        try!(self.update(key, |old| {
+            let entry = try!(text_entry(key, old)).unwrap_or_default();
+            Ok(Entry { value: join_lines(text, &entry.value), ..entry })
+        }));
+
+        Ok(())
+    }
+
+    /// Replace a line of the value of a key. Lines are numbered
+    /// from 1.
+    ///
+    /// It returns a `SlateError::NotFound` error if the key doesn't
+    /// exist, and a `SlateError::LineNotFound` error if the value
+    /// doesn't have that line. The store is read and written once,
+    /// locked, like with `Slate::append`.
+    pub fn set_line(&self, key: &String, number: usize, line: &String) -> SlateResult<()> {
+        try!(self.update(key, |old| {
+            let entry = match try!(text_entry(key, old)) {
+                Some(entry) => entry,
+                None => return Err(SlateError::NotFound(key.to_owned())),
+            };
+            match replace_line(&entry.value, number, line) {
+                Some(value) => Ok(Entry { value: value, ..entry }),
+                None => Err(SlateError::LineNotFound(key.to_owned(), number)),
+            }
+        }));
+
+        Ok(())
+    }
+
+    /// Change the entry of a key in a single locked read and write
+    /// of the store, running the same hooks as `Slate::set`. Aliases
+    /// are followed, so the key they point to is the one changed. It
+    /// returns the previous and the new entries.
+    fn update<F>(&self, key: &String, f: F) -> SlateResult<(Option<Entry>, Entry)>
+        where F: FnMut(Option<&Entry>) -> SlateResult<Entry>
+    {
+        let store = try!(self.store());
+        let (chain, _) = try!(store::follow(&*store, key));
+
+        self.update_key(&chain[chain.len() - 1], f)
+    }
+
+    /// Change the entry of a key like `Slate::update`, without
+    /// following aliases, as the daemon does for remote stores that
+    /// already followed them.
+    fn update_key<F>(&self, key: &String, mut f: F) -> SlateResult<(Option<Entry>, Entry)>
+        where F: FnMut(Option<&Entry>) -> SlateResult<Entry>
+    {
+        let store = try!(self.store());
+        let (old, new) = try!(store.update(key, &mut |old| {
+            let entry = try!(f(old));
+            let event = Event {
+                key: key,
+                old_value: old.map(|e| e.value.as_str()),
+                new_value: Some(&entry.value),
+                ..Default::default()
+            };
+            try!(hooks::run(self.config, Hook::PreSet, &event));
+
+            Ok(entry)
+        }));
+        let event = Event {
+            key: key,
+            old_value: old.as_ref().map(|e| e.value.as_str()),
+            new_value: Some(&new.value),
+            ..Default::default()
+        };
+
+        let now = time::now();
+        usage::update(self.config, |stats| usage::changed(stats, key, old.is_none(), now));
+        history::update(self.config, |history| {
+            history::record(history, key, event.old_value, &new.value, now);
+        });
+        try!(hooks::run(self.config, Hook::PostSet, &event));
+
+        Ok((old, new))
     }
 
     /// Get the value of a key
     ///
+    /// It returns a `SlateError::NotFound` error if the key
+    /// doesn't exist.
     ///
     /// # Example
     ///
@@ -81,82 +319,720 @@ impl<'s> Slate<'s> {
     /// let mut temp = env::temp_dir();
     /// temp.push(".slate");
     ///
-    /// let config = Config { filepath: temp };
+    /// let config = Config { filepath: temp, ..Default::default() };
     /// let slate: Slate = From::from(&config);
     /// let key = "foo".to_string();
     ///
     /// match slate.get(&key) {
     ///   Ok(value) => println!("{}", value), //=> bar
-    ///   Err(e) => panic!("{}", e),
+    ///   Err(e) => println!("{}", e), //=> The key foo doesn't exist
     /// };
     /// ```
     pub fn get(&self, key: &String) -> SlateResult<String> {
-        let contents = try!(self.read());
+        let entry = try!(self.entry(key));
+
+        Ok(entry.value)
+    }
+
+    /// Get the value of a key as bytes, decoding binary values.
+    ///
+    /// It returns a `SlateError::NotFound` error if the key doesn't
+    /// exist, and a `SlateError::Corrupted` error if the value
+    /// cannot be decoded.
+    pub fn get_bytes(&self, key: &String) -> SlateResult<Vec<u8>> {
+        let entry = try!(self.entry(key));
+
+        match entry.bytes() {
+            Some(bytes) => Ok(bytes),
+            None => Err(SlateError::Corrupted(format!("The value of {} cannot be decoded", key))),
+        }
+    }
+
+    /// Get the value of a key with its attributes.
+    ///
+    /// It returns a `SlateError::NotFound` error if the key
+    /// doesn't exist or has expired. Aliases are followed to the key
+    /// they point to, and keys of the linked directories are read
+    /// from their files.
+    pub fn entry(&self, key: &String) -> SlateResult<Entry> {
+        let store = try!(self.store());
+        let (chain, entry) = try!(store::follow(&*store, key));
+        let last = &chain[chain.len() - 1];
+
+        match entry {
+            Some(entry) => Ok(entry),
+            None => {
+                match links::find(&links::read(self.config), last) {
+                    Some(linked) => Ok(try!(linked.entry())),
+                    None => Err(SlateError::NotFound(last.to_owned())),
+                }
+            }
+        }
+    }
+
+    /// Make a key an alias of another one, so it has the value of the
+    /// other key.
+    ///
+    /// The target can be an alias too. It returns a
+    /// `SlateError::NotFound` error if the target doesn't exist, a
+    /// `SlateError::Cycle` error if the aliases would point to each
+    /// other, and a `SlateError::Conflict` error if the alias is
+    /// already in use, unless `force` is `true`. It runs the same
+    /// hooks as `Slate::set`, which get the value of the target.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use slate::config::Config;
+    /// use slate::Slate;
+    /// use std::env;
+    ///
+    /// let mut temp = env::temp_dir();
+    /// temp.push(".slate");
+    ///
+    /// let config = Config { filepath: temp, ..Default::default() };
+    /// let slate: Slate = From::from(&config);
+    /// let (alias, target) = ("pods".to_string(), "k8s/pods".to_string());
+    ///
+    /// slate.alias(&alias, &target, false).unwrap();
+    /// assert_eq!(slate.get(&target).unwrap(), slate.get(&alias).unwrap());
+    /// ```
+    pub fn alias(&self, alias: &String, target: &String, force: bool) -> SlateResult<()> {
+        let store = try!(self.store());
+        let (mut chain, entry) = try!(store::follow(&*store, target));
+
+        if let Some(index) = chain.iter().position(|key| key == alias) {
+            chain.truncate(index + 1);
+            chain.insert(0, alias.to_owned());
+            return Err(SlateError::Cycle(chain));
+        }
+        let last = &chain[chain.len() - 1];
+        let value = match entry {
+            Some(entry) => entry.value,
+            None => {
+                match links::find(&links::read(self.config), last) {
+                    Some(linked) => try!(linked.entry()).value,
+                    None => return Err(SlateError::NotFound(last.to_owned())),
+                }
+            }
+        };
+        let old = try!(store.get(alias));
+        if !force && old.is_some() {
+            return Err(SlateError::Conflict(alias.to_owned()));
+        }
+        let event = Event {
+            key: alias,
+            old_value: old.as_ref().map(|e| e.value.as_str()),
+            new_value: Some(&value),
+            ..Default::default()
+        };
+
+        try!(hooks::run(self.config, Hook::PreSet, &event));
+        try!(store.set(alias, &Entry::alias(target)));
+        let now = time::now();
+        usage::update(self.config, |stats| usage::changed(stats, alias, old.is_none(), now));
+        history::update(self.config, |history| {
+            history.remove(alias);
+        });
+        hooks::run(self.config, Hook::PostSet, &event)
+    }
+
+    /// Get the aliases of the store with the keys they point to,
+    /// sorted by alias.
+    pub fn aliases(&self) -> SlateResult<Vec<(String, String)>> {
+        let store = try!(self.store());
+
+        let mut aliases: Vec<(String, String)> = try!(store.read())
+            .into_iter()
+            .filter_map(|(key, entry)| entry.alias.map(|target| (key, target)))
+            .collect();
+        aliases.sort();
+
+        Ok(aliases)
+    }
+
+    /// Find the existing key with the closest name to the given one.
+    ///
+    /// Only keys within a small edit distance are considered, so
+    /// it returns `None` if nothing is similar enough.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use slate::config::Config;
+    /// use slate::Slate;
+    /// use std::env;
+    ///
+    /// let mut temp = env::temp_dir();
+    /// temp.push(".slate");
+    ///
+    /// let config = Config { filepath: temp, ..Default::default() };
+    /// let slate: Slate = From::from(&config);
+    /// let key = "fooo".to_string();
+    ///
+    /// if let Ok(Some(similar)) = slate.suggest(&key) {
+    ///   println!("Did you mean {}?", similar); //=> Did you mean foo?
+    /// }
+    /// ```
+    pub fn suggest(&self, key: &String) -> SlateResult<Option<String>> {
+        let keys = try!(self.list());
+        let threshold = cmp::max(1, key.chars().count() / 3);
+
+        let mut candidates: Vec<(usize, String)> = keys.into_iter()
+            .map(|candidate| (strsim::levenshtein(key, &candidate), candidate))
+            .filter(|&(distance, _)| distance <= threshold)
+            .collect();
+        candidates.sort();
+
+        Ok(candidates.into_iter().next().map(|(_, candidate)| candidate))
+    }
+
+    /// Remove completely a key with its value.
+    ///
+    /// The `pre-remove` and `post-remove` hooks are run around the
+    /// change, if configured. It returns a `SlateError::Vetoed`
+    /// error if the `pre-remove` hook rejects it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use slate::config::Config;
+    /// use slate::Slate;
+    /// use std::env;
+    ///
+    /// let mut temp = env::temp_dir();
+    /// temp.push(".slate");
+    ///
+    /// let config = Config { filepath: temp, ..Default::default() };
+    /// let slate: Slate = From::from(&config);
+    /// let key = "foo".to_string();
+    ///
+    /// match slate.remove(&key) {
+    ///   Ok(_) => println!("Key removed"),
+    ///   Err(e) => panic!("{}", e),
+    /// };
+    /// ```
+    pub fn remove(&self, key: &String) -> SlateResult<()> {
+        let store = try!(self.store());
+        let old = if hooks::any(self.config, &[Hook::PreRemove, Hook::PostRemove]) {
+            try!(store.get(key))
+        } else {
+            None
+        };
+        let event = Event {
+            key: key,
+            old_value: old.as_ref().map(|e| e.value.as_str()),
+            ..Default::default()
+        };
+
+        try!(hooks::run(self.config, Hook::PreRemove, &event));
+        try!(store.remove(key));
+        usage::update(self.config, |stats| {
+            stats.remove(key);
+        });
+        history::update(self.config, |history| {
+            history.remove(key);
+        });
+        hooks::run(self.config, Hook::PostRemove, &event)
+    }
+
+    /// Remove all keys.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use slate::config::Config;
+    /// use slate::Slate;
+    /// use std::env;
+    ///
+    /// let mut temp = env::temp_dir();
+    /// temp.push(".slate");
+    ///
+    /// let config = Config { filepath: temp, ..Default::default() };
+    /// let slate: Slate = From::from(&config);
+    ///
+    /// match slate.clear() {
+    ///   Ok(_) => println!("Keys removed"),
+    ///   Err(e) => panic!("{}", e),
+    /// };
+    /// ```
+    pub fn clear(&self) -> SlateResult<()> {
+        let store = try!(self.store());
+
+        try!(store.clear());
+        usage::update(self.config, |stats| stats.clear());
+        history::update(self.config, |history| history.clear());
+
+        Ok(())
+    }
+
+    /// Rename a key.
+    ///
+    /// The key is renamed in a single read and write of the store.
+    /// It returns a `SlateError::NotFound` error if the key doesn't
+    /// exist, and a `SlateError::Conflict` error if the new name is
+    /// already in use, unless `force` is `true`. Renaming a key to
+    /// itself does nothing. The aliases of the key are changed to
+    /// point to the new name in the same change of the store. The
+    /// `post-rename` hook is run after the change, if configured.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use slate::config::Config;
+    /// use slate::Slate;
+    /// use std::env;
+    ///
+    /// let mut temp = env::temp_dir();
+    /// temp.push(".slate");
+    ///
+    /// let config = Config { filepath: temp, ..Default::default() };
+    /// let slate: Slate = From::from(&config);
+    /// let old = "foo".to_string();
+    /// let new = "bar".to_string();
+    ///
+    /// match slate.rename(&old, &new, false) {
+    ///   Ok(_) => println!("Renamed!"),
+    ///   Err(e) => println!("{}", e),
+    /// };
+    /// ```
+    pub fn rename(&self, src: &String, dts: &String, force: bool) -> SlateResult<()> {
+        let store = try!(self.store());
+
+        try!(store.rename(src, dts, force));
+        if src != dts {
+            usage::update(self.config, |stats| {
+                if let Some(usage) = stats.remove(src) {
+                    stats.insert(dts.to_owned(), usage);
+                }
+            });
+            history::update(self.config, |history| {
+                if let Some(revisions) = history.remove(src) {
+                    history.insert(dts.to_owned(), revisions);
+                }
+            });
+        }
+
+        if hooks::any(self.config, &[Hook::PostRename]) {
+            let value = try!(store.get(dts));
+            let event = Event {
+                key: src,
+                new_key: Some(dts),
+                old_value: value.as_ref().map(|e| e.value.as_str()),
+                new_value: value.as_ref().map(|e| e.value.as_str()),
+            };
+            try!(hooks::run(self.config, Hook::PostRename, &event));
+        }
+
+        Ok(())
+    }
+
+    /// Copy a key, with its attributes and history, to a new name.
+    ///
+    /// Unlike `Slate::rename`, the source is kept. It returns a
+    /// `SlateError::NotFound` error if the key doesn't exist, and a
+    /// `SlateError::Conflict` error if the new name is already in
+    /// use, unless `force` is `true`. Copying a key to itself does
+    /// nothing. The `pre-set` and `post-set` hooks are run for the
+    /// new key.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use slate::config::Config;
+    /// use slate::Slate;
+    /// use std::env;
+    ///
+    /// let mut temp = env::temp_dir();
+    /// temp.push(".slate");
+    ///
+    /// let config = Config { filepath: temp, ..Default::default() };
+    /// let slate: Slate = From::from(&config);
+    /// let src = "foo".to_string();
+    /// let dst = "foo-backup".to_string();
+    ///
+    /// match slate.copy(&src, &dst, false) {
+    ///   Ok(_) => println!("Copied!"),
+    ///   Err(e) => println!("{}", e),
+    /// };
+    /// ```
+    pub fn copy(&self, src: &String, dst: &String, force: bool) -> SlateResult<()> {
+        let store = try!(self.store());
+        let entry = match try!(store.get(src)) {
+            Some(entry) => entry,
+            None => return Err(SlateError::NotFound(src.to_owned())),
+        };
+        if src == dst {
+            return Ok(());
+        }
+        let old = try!(store.get(dst));
+        if old.is_some() && !force {
+            return Err(SlateError::Conflict(dst.to_owned()));
+        }
+        let event = Event {
+            key: dst,
+            old_value: old.as_ref().map(|e| e.value.as_str()),
+            new_value: Some(&entry.value),
+            ..Default::default()
+        };
+
+        try!(hooks::run(self.config, Hook::PreSet, &event));
+        try!(store.set(dst, &entry));
+
+        let now = time::now();
+        usage::update(self.config, |stats| usage::changed(stats, dst, old.is_none(), now));
+        history::update(self.config, |history| {
+            let revisions = history::revisions(history, src, &entry.value);
+            history.insert(dst.to_owned(), revisions);
+        });
+        hooks::run(self.config, Hook::PostSet, &event)
+    }
+
+    /// Get a list of all keys.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use slate::config::Config;
+    /// use slate::Slate;
+    /// use std::env;
+    ///
+    /// let mut temp = env::temp_dir();
+    /// temp.push(".slate");
+    ///
+    /// let config = Config { filepath: temp, ..Default::default() };
+    /// let slate: Slate = From::from(&config);
+    /// let list = match slate.list() {
+    ///   Ok(all) => all,
+    ///   Err(e) => panic!("{}", e),
+    /// };
+    ///
+    /// for key in &list {
+    ///   println!("{}", key);
+    /// }
+    /// ```
+    pub fn list(&self) -> SlateResult<Vec<String>> {
+        let store = try!(self.store());
+
+        store.keys()
+    }
+
+    /// Get a list of all keys in the given order.
+    ///
+    /// Orders other than `Sort::Name` use the usage recorded in the
+    /// sidecar of the store.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use slate::config::Config;
+    /// use slate::usage::Sort;
+    /// use slate::Slate;
+    /// use std::env;
+    ///
+    /// let mut temp = env::temp_dir();
+    /// temp.push(".slate");
+    ///
+    /// let config = Config { filepath: temp, ..Default::default() };
+    /// let slate: Slate = From::from(&config);
+    ///
+    /// for key in slate.list_by(Sort::Frecency).unwrap_or_default() {
+    ///   println!("{}", key);
+    /// }
+    /// ```
+    pub fn list_by(&self, order: Sort) -> SlateResult<Vec<String>> {
+        if order == Sort::Name {
+            return self.list();
+        }
+
+        let store = try!(self.store());
+        let now = time::now();
+        let contents = try!(store.read());
+        let mut keys: Vec<String> = contents.iter()
+            .filter(|&(_, entry)| !entry.is_expired(now))
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        usage::sort(&mut keys, order, &contents, &usage::read(self.config), now);
+
+        Ok(keys)
+    }
+
+    /// Get the last used keys, most recent first.
+    pub fn recent(&self, limit: usize) -> SlateResult<Vec<String>> {
+        let stats = usage::read(self.config);
+        let mut keys: Vec<String> = try!(self.list())
+            .into_iter()
+            .filter(|key| stats.get(key).and_then(|usage| usage.used).is_some())
+            .collect();
+
+        usage::sort(&mut keys, Sort::Recent, &Default::default(), &stats, time::now());
+        keys.truncate(limit);
+
+        Ok(keys)
+    }
+
+    /// Record that a key has been used, for `Slate::list_by` and
+    /// `Slate::recent`.
+    ///
+    /// Nothing is recorded if tracking is disabled in the
+    /// configuration, or if the usage cannot be saved.
+    pub fn record_use(&self, key: &String) {
+        let now = time::now();
+
+        usage::update(self.config, |stats| {
+            let usage = stats.entry(key.to_owned()).or_insert_with(Default::default);
+            usage.count += 1;
+            usage.used = Some(now);
+        });
+    }
+
+    /// Get the revisions of the value of a key, oldest first. The
+    /// last one is the current value.
+    ///
+    /// It returns a `SlateError::NotFound` error if the key
+    /// doesn't exist.
+    pub fn history(&self, key: &String) -> SlateResult<Vec<Revision>> {
+        let entry = try!(self.entry(key));
+
+        Ok(history::revisions(&history::read(self.config), key, &entry.value))
+    }
+
+    /// Get a list of the keys whose values match a query.
+    ///
+    /// Every word of the query must be present in the value. With
+    /// the SQLite backend the full-text index is used, so words are
+    /// matched as prefixes of the words in the values.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use slate::config::Config;
+    /// use slate::Slate;
+    /// use std::env;
+    ///
+    /// let mut temp = env::temp_dir();
+    /// temp.push(".slate");
+    ///
+    /// let config = Config { filepath: temp, ..Default::default() };
+    /// let slate: Slate = From::from(&config);
+    /// let query = "daemon".to_string();
+    /// let list = match slate.search(&query) {
+    ///   Ok(found) => found,
+    ///   Err(e) => panic!("{}", e),
+    /// };
+    ///
+    /// for key in &list {
+    ///   println!("{}", key);
+    /// }
+    /// ```
+    pub fn search(&self, query: &String) -> SlateResult<Vec<String>> {
+        let store = try!(self.store());
+
+        store.search(query)
+    }
+
+    /// Link a directory as a read-only namespace, and get its full
+    /// path.
+    ///
+    /// Every file inside it is a key, see the `links` module. It
+    /// returns an I/O error if the path is not a directory. Linking
+    /// a directory again does nothing.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use slate::config::Config;
+    /// use slate::Slate;
+    /// use std::env;
+    /// use std::path::Path;
+    ///
+    /// let mut temp = env::temp_dir();
+    /// temp.push(".slate");
+    ///
+    /// let config = Config { filepath: temp, ..Default::default() };
+    /// let slate: Slate = From::from(&config);
+    ///
+    /// match slate.link(Path::new("snippets")) {
+    ///   Ok(path) => println!("Linked {}", path.display()),
+    ///   Err(e) => panic!("{}", e),
+    /// };
+    /// ```
+    pub fn link(&self, dir: &Path) -> SlateResult<PathBuf> {
+        let path = try!(dir.canonicalize());
+        if !path.is_dir() {
+            let message = format!("{} is not a directory", dir.display());
+            return Err(SlateError::IO(io::Error::new(io::ErrorKind::InvalidInput, message)));
+        }
+
+        let mut dirs = links::read(self.config);
+        if !dirs.contains(&path) {
+            dirs.push(path.clone());
+            try!(links::write(self.config, &dirs));
+        }
+
+        Ok(path)
+    }
+
+    /// Stop using a linked directory.
+    ///
+    /// It returns a `SlateError::NotFound` error if the directory
+    /// is not linked.
+    pub fn unlink(&self, dir: &Path) -> SlateResult<()> {
+        let path = dir.canonicalize().unwrap_or(dir.to_path_buf());
+        let mut dirs = links::read(self.config);
+        if !dirs.contains(&path) {
+            return Err(SlateError::NotFound(format!("{}", dir.display())));
+        }
+
+        dirs.retain(|linked| *linked != path);
+        try!(links::write(self.config, &dirs));
+
+        Ok(())
+    }
+
+    /// Get the linked directories.
+    pub fn links(&self) -> Vec<PathBuf> {
+        links::read(self.config)
+    }
+
+    /// Get the keys of the linked directories, sorted, except the
+    /// ones hidden by keys of the store.
+    pub fn linked(&self) -> SlateResult<Vec<Linked>> {
+        let dirs = links::read(self.config);
+        if dirs.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let keys = try!(self.list());
+
+        Ok(links::keys(&dirs).into_iter().filter(|linked| !keys.contains(&linked.key)).collect())
+    }
+
+    /// Get the linked directory of a key, if it comes from one and
+    /// is not hidden by a key of the store.
+    pub fn link_of(&self, key: &String) -> SlateResult<Option<Linked>> {
+        let store = try!(self.store());
+        if try!(store.get(key)).is_some() {
+            return Ok(None);
+        }
+
+        Ok(links::find(&links::read(self.config), key))
+    }
+
+    /// Get the keys of the linked directories whose values match a
+    /// query, like `Slate::search` does with the keys of the store.
+    pub fn search_linked(&self, query: &String) -> SlateResult<Vec<Linked>> {
+        let linked = try!(self.linked());
+
+        Ok(linked.into_iter()
+            .filter(|linked| {
+                linked.entry()
+                    .map(|entry| !entry.is_binary() && store::contains_words(&entry.value, query))
+                    .unwrap_or(false)
+            })
+            .collect())
+    }
+
+    /// Get the keys that will expire, with their expiration time,
+    /// sorted by the time they have left.
+    pub fn expiring(&self) -> SlateResult<Vec<(String, u64)>> {
+        let store = try!(self.store());
+        let now = time::now();
+
+        let mut expiring: Vec<(String, u64)> = try!(store.read())
+            .into_iter()
+            .filter(|&(_, ref entry)| !entry.is_expired(now))
+            .filter_map(|(key, entry)| entry.expires.map(|expires| (key, expires)))
+            .collect();
+        expiring.sort_by(|a, b| (a.1, &a.0).cmp(&(b.1, &b.0)));
+
+        Ok(expiring)
+    }
 
-        match contents.get(key) {
-            Some(value) => Ok(value.to_string()),
-            None => Ok(String::new()),
-        }
+    /// Get the statistics of the keys of the store, see
+    /// `stats::summarize`.
+    pub fn stats(&self, stale_days: u64, top: usize) -> SlateResult<Summary> {
+        let store = try!(self.store());
+        let contents = try!(store.read());
+
+        Ok(stats::summarize(&contents, &usage::read(self.config), time::now(), stale_days, top))
     }
 
-    /// Remove completely a key with its value.
-    ///
-    /// # Example
-    ///
-    /// ```rust
-    /// use slate::config::Config;
-    /// use slate::Slate;
-    /// use std::env;
-    ///
-    /// let mut temp = env::temp_dir();
-    /// temp.push(".slate");
-    ///
-    /// let config = Config { filepath: temp };
-    /// let slate: Slate = From::from(&config);
-    /// let key = "foo".to_string();
+    /// Get the groups of keys with the same value, or with values of
+    /// at least the given similarity, see `dedupe::duplicates`.
+    pub fn duplicates(&self, similarity: f64) -> SlateResult<Vec<Group>> {
+        let store = try!(self.store());
+        let contents = try!(store.read());
+
+        Ok(dedupe::duplicates(&contents, time::now(), similarity))
+    }
+
+    /// Remove the expired keys from the store, and get how many
+    /// there were.
     ///
-    /// match slate.remove(&key) {
-    ///   Ok(_) => println!("Key removed"),
-    ///   Err(e) => panic!("{}", e),
-    /// };
-    /// ```
-    pub fn remove(&self, key: &String) -> SlateResult<()> {
-        let mut contents = try!(self.read());
+    /// Expired keys are hidden, and removed with any other change.
+    /// This removes them without changing anything else, and drops
+    /// the usage and the history of the keys that don't exist
+    /// anymore, including the ones removed by other changes.
+    pub fn gc(&self) -> SlateResult<usize> {
+        let store = try!(self.store());
+        let removed = try!(store.gc());
 
-        contents.remove(key);
+        let mut known: HashSet<String> = try!(store.keys()).into_iter().collect();
+        known.extend(try!(self.linked()).into_iter().map(|linked| linked.key));
+        usage::update(self.config, |stats| stats.retain(|key, _| known.contains(key)));
+        history::update(self.config, |history| history.retain(|key, _| known.contains(key)));
 
-        self.write(&contents)
+        Ok(removed)
     }
 
-    /// Remove all keys.
+    /// Copy all the keys and values into another store.
+    ///
+    /// The destination is described by its own configuration, so
+    /// this can be used to move the contents between backends.
+    /// Returns the number of keys copied.
     ///
     /// # Example
     ///
     /// ```rust
-    /// use slate::config::Config;
+    /// use slate::config::{Backend, Config};
     /// use slate::Slate;
     /// use std::env;
     ///
     /// let mut temp = env::temp_dir();
     /// temp.push(".slate");
+    /// let mut db = env::temp_dir();
+    /// db.push(".slate.db");
     ///
-    /// let config = Config { filepath: temp };
+    /// let config = Config { filepath: temp, ..Default::default() };
+    /// let destination = Config { filepath: db, backend: Backend::Sqlite, ..Default::default() };
     /// let slate: Slate = From::from(&config);
     ///
-    /// match slate.clear() {
-    ///   Ok(_) => println!("Keys removed"),
+    /// match slate.migrate(&destination) {
+    ///   Ok(total) => println!("{} keys migrated", total),
     ///   Err(e) => panic!("{}", e),
     /// };
     /// ```
-    pub fn clear(&self) -> SlateResult<()> {
-        let mut contents = try!(self.read());
+    pub fn migrate(&self, destination: &Config) -> SlateResult<usize> {
+        let contents = try!(try!(self.store()).read());
+        let target = try!(store::open(destination));
 
-        contents.clear();
+        try!(target.write(&contents));
 
-        self.write(&contents)
+        Ok(contents.len())
     }
 
-    /// Rename a key.
+    /// Apply several changes at once.
+    ///
+    /// The contents are loaded once and every operation made with the
+    /// given `Transaction` happens in memory. If the closure returns
+    /// `Ok` all the changes are saved atomically, otherwise nothing
+    /// is saved.
+    ///
+    /// Each operation runs the same hooks as the methods of `Slate`:
+    /// the pre hooks before anything is saved, so any of them can
+    /// cancel the whole transaction, and the post hooks after it.
     ///
     /// # Example
     ///
@@ -168,29 +1044,89 @@ impl<'s> Slate<'s> {
     /// let mut temp = env::temp_dir();
     /// temp.push(".slate");
     ///
-    /// let config = Config { filepath: temp };
+    /// let config = Config { filepath: temp, ..Default::default() };
     /// let slate: Slate = From::from(&config);
-    /// let old = "foo".to_string();
-    /// let new = "bar".to_string();
+    /// let (foo, bar) = ("foo".to_string(), "bar".to_string());
     ///
-    /// match slate.rename(&old, &new) {
-    ///   Ok(_) => println!("Renamed!"),
+    /// let result = slate.transaction(|tx| {
+    ///   tx.set(&foo, &bar);
+    ///   tx.remove(&bar);
+    ///   Ok(())
+    /// });
+    ///
+    /// match result {
+    ///   Ok(_) => println!("Saved"),
     ///   Err(e) => panic!("{}", e),
     /// };
     /// ```
-    pub fn rename(&self, src: &String, dts: &String) -> SlateResult<()> {
-        let value = try!(self.get(src));
-        try!(self.set(dts, &value));
-        try!(self.remove(src));
+    pub fn transaction<F, T>(&self, f: F) -> SlateResult<T>
+        where F: FnOnce(&mut Transaction) -> SlateResult<T>
+    {
+        let store = try!(self.store());
+        let mut f = Some(f);
+        let mut outcome = None;
+        // The hooks before the changes run while the store is locked,
+        // like with `update`, so they can still cancel them.
+        let previous = try!(store.transact(&mut |contents| {
+            let f = f.take().expect("a transaction is run once");
+            let mut tx = Transaction::new(mem::replace(contents, Contents::new()));
 
-        Ok(())
+            let result = try!(f(&mut tx));
+            let operations = tx.operations().to_vec();
+            for operation in &operations {
+                if let Some(hook) = operation.pre_hook() {
+                    try!(hooks::run(self.config, hook, &operation.event()));
+                }
+            }
+
+            let (changed, touched) = tx.into_parts();
+            *contents = changed;
+            outcome = Some((result, operations, contents.clone(), touched.clone()));
+
+            Ok(touched)
+        }));
+        let (result, operations, contents, touched) = outcome.expect("a transaction is run once");
+
+        if !touched.is_empty() {
+            let now = time::now();
+            usage::update(self.config, |stats| {
+                for key in &touched {
+                    if contents.contains_key(key) {
+                        usage::changed(stats, key, !previous.contains_key(key), now);
+                    } else {
+                        stats.remove(key);
+                    }
+                }
+            });
+            history::update(self.config, |history| {
+                for key in &touched {
+                    match contents.get(key) {
+                        Some(entry) => {
+                            let old = previous.get(key).map(|old| old.value.as_str());
+                            history::record(history, key, old, &entry.value, now);
+                        }
+                        None => {
+                            history.remove(key);
+                        }
+                    }
+                }
+            });
+        }
+        for operation in &operations {
+            try!(hooks::run(self.config, operation.post_hook(), &operation.event()));
+        }
+
+        Ok(result)
     }
 
-    /// Get a list of all keys.
+    /// Subscribe to the changes of the keys that start with a prefix.
+    ///
+    /// Use an empty prefix to see all the changes. The subscription
+    /// is an iterator that waits for the next change.
     ///
     /// # Example
     ///
-    /// ```rust
+    /// ```rust,no_run
     /// use slate::config::Config;
     /// use slate::Slate;
     /// use std::env;
@@ -198,62 +1134,60 @@ impl<'s> Slate<'s> {
     /// let mut temp = env::temp_dir();
     /// temp.push(".slate");
     ///
-    /// let config = Config { filepath: temp };
+    /// let config = Config { filepath: temp, ..Default::default() };
     /// let slate: Slate = From::from(&config);
-    /// let list = match slate.list() {
-    ///   Ok(all) => all,
-    ///   Err(e) => panic!("{}", e),
-    /// };
     ///
-    /// for key in &list {
-    ///   println!("{}", key);
+    /// for event in slate.subscribe("redis").unwrap() {
+    ///   let event = event.unwrap();
+    ///   println!("{} {}", event.kind.name(), event.key); //=> modified redis
     /// }
     /// ```
-    pub fn list(&self) -> SlateResult<Vec<String>> {
-        let contents = try!(self.read());
-
-        let mut keys: Vec<_> = contents.keys().collect();
-        keys.sort(); // sort needs a mutable instance!!
-
-        let list: Vec<_> = keys.iter().map(|&s| s.clone()).collect();
-
-        Ok(list)
+    pub fn subscribe(&self, prefix: &str) -> SlateResult<Subscription> {
+        Subscription::new(self.config, prefix)
     }
 
-    /// Read the contents of the Slate file.
-    fn read(&self) -> SlateResult<HashMap<String, String>> {
-        let mut r = match File::open(self.filepath()) {
-            Ok(file) => file,
-            Err(_) => {
-                let empty = HashMap::new();
-                match self.write(&empty) {
-                    Ok(_) => File::open(self.filepath()).unwrap(),
-                    Err(e) => return Err(e),
-                }
-            }
-        };
-
+    /// Open the store used to save the contents.
+    fn store(&self) -> SlateResult<Box<Store>> {
+        store::open(self.config)
+    }
+}
 
-        let mut buffer = String::new();
-        try!(r.read_to_string(&mut buffer));
 
-        let contents: HashMap<String, String> = serde_json::from_str(&buffer).unwrap_or(HashMap::new());
+/// The entry of a key whose lines are going to be changed. It
+/// returns a `SlateError::Binary` error for binary values.
+fn text_entry(key: &str, entry: Option<&Entry>) -> SlateResult<Option<Entry>> {
+    match entry {
+        Some(entry) if entry.is_binary() => Err(SlateError::Binary(key.to_owned())),
+        entry => Ok(entry.cloned()),
+    }
+}
 
-        Ok(contents)
+/// Join two texts, with a new line between them unless one of
+/// them is empty or the first one already ends with it.
+fn join_lines(first: &str, second: &str) -> String {
+    if first.is_empty() || second.is_empty() || first.ends_with('\n') {
+        format!("{}{}", first, second)
+    } else {
+        format!("{}\n{}", first, second)
     }
+}
 
-    /// Write to the Slate file.
-    fn write(&self, contents: &HashMap<String, String>) -> SlateResult<()> {
-        let encoded = try!(serde_json::to_string(&contents));
-        let mut f = try!(File::create(self.filepath()));
+/// Replace a line of a text, numbered from 1, keeping the rest of
+/// the lines and the final new line. It returns `None` if the text
+/// doesn't have that line.
+fn replace_line(text: &str, number: usize, line: &str) -> Option<String> {
+    let mut lines: Vec<&str> = text.split('\n').collect();
+    // A final new line doesn't start another line.
+    let total = if text.ends_with('\n') { lines.len() - 1 } else { lines.len() };
+    if number == 0 || number > total {
+        return None;
+    }
 
-        try!(f.write_all(encoded.as_bytes()));
+    lines[number - 1] = line.trim_end_matches('\n');
 
-        Ok(())
-    }
+    Some(lines.join("\n"))
 }
 
-
 /// Get the version of the library.
 pub fn version() -> String {
     let (maj, min, pat) = (option_env!("CARGO_PKG_VERSION_MAJOR"),
@@ -271,9 +1205,12 @@ mod tests {
     use super::*;
     use std::env;
     use std::path::PathBuf;
-    use std::fs::File;
+    use std::fs::{self, File};
+    use std::io::prelude::*;
     use rand::{thread_rng, Rng};
-    use config::Config;
+    use config::{Backend, Config};
+    use errors::SlateError;
+    use transfer::{self, Mode};
 
     fn create_temp_file(body: &str) -> PathBuf {
         let random_name: String = thread_rng().gen_ascii_chars().take(10).collect();
@@ -296,7 +1233,7 @@ mod tests {
     #[test]
     fn it_gets_values_from_config() {
         let path = PathBuf::from("/tmp/slate.test");
-        let config = Config { filepath: path };
+        let config = Config { filepath: path, ..Default::default() };
         let slate: Slate = From::from(&config);
 
         assert_eq!(config.filepath, *slate.filepath());
@@ -305,8 +1242,7 @@ mod tests {
     #[test]
     fn it_sets_keys_with_values() {
         let temp = create_temp_file("");
-        let mut file = File::open(&temp).unwrap();
-        let config = Config { filepath: temp };
+        let config = Config { filepath: temp, ..Default::default() };
         let slate = Slate { config: &config };
         let key = "test".to_string();
         let value = "expected".to_string();
@@ -315,6 +1251,7 @@ mod tests {
             panic!("Cannot set a value: {:?}", e);
         };
 
+        let mut file = File::open(&config.filepath).unwrap();
         let mut buffer = String::new();
         let expected = "{\"test\":\"expected\"}";
         if let Err(e) = file.read_to_string(&mut buffer) {
@@ -326,7 +1263,7 @@ mod tests {
     #[test]
     fn it_gets_keys() {
         let temp = create_temp_file("{\"test\":\"expected\"}");
-        let config = Config { filepath: temp };
+        let config = Config { filepath: temp, ..Default::default() };
         let slate = Slate { config: &config };
         let key = "test".to_string();
 
@@ -339,20 +1276,46 @@ mod tests {
     #[test]
     fn it_gets_missing_keys() {
         let temp = create_temp_file("{\"test\":\"expected\"}");
-        let config = Config { filepath: temp };
+        let config = Config { filepath: temp, ..Default::default() };
         let slate = Slate { config: &config };
         let key = "missing".to_string();
 
+        match slate.get(&key) {
+            Err(SlateError::NotFound(missing)) => assert_eq!("missing", missing),
+            other => panic!("Expected a missing key error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn it_gets_empty_values() {
+        let temp = create_temp_file("{\"test\":\"\"}");
+        let config = Config { filepath: temp, ..Default::default() };
+        let slate = Slate { config: &config };
+        let key = "test".to_string();
+
         match slate.get(&key) {
             Ok(value) => assert_eq!("", value),
             Err(e) => panic!("Cannot get a value from slate: {:?}", e),
         }
     }
 
+    #[test]
+    fn it_suggests_similar_keys() {
+        let temp = create_temp_file("{\"deploy\":\"1\",\"redis\":\"2\"}");
+        let config = Config { filepath: temp, ..Default::default() };
+        let slate = Slate { config: &config };
+
+        assert_eq!(Some("redis".to_string()),
+                   slate.suggest(&"rdis".to_string()).unwrap());
+        assert_eq!(Some("deploy".to_string()),
+                   slate.suggest(&"deplyo".to_string()).unwrap());
+        assert_eq!(None, slate.suggest(&"nginx".to_string()).unwrap());
+    }
+
     #[test]
     fn it_lists_keys() {
         let temp = create_temp_file("{\"a\":\"1\",\"b\":\"2\"}");
-        let config = Config { filepath: temp };
+        let config = Config { filepath: temp, ..Default::default() };
         let slate = Slate { config: &config };
 
         match slate.list() {
@@ -364,8 +1327,7 @@ mod tests {
     #[test]
     fn it_removes_keys() {
         let temp = create_temp_file("{\"test\":\"expected\"}");
-        let mut file = File::open(&temp).unwrap();
-        let config = Config { filepath: temp };
+        let config = Config { filepath: temp, ..Default::default() };
         let slate = Slate { config: &config };
         let key = "test".to_string();
 
@@ -373,6 +1335,7 @@ mod tests {
             panic!("Cannot remove the key: {:?}", e);
         };
 
+        let mut file = File::open(&config.filepath).unwrap();
         let mut buffer = String::new();
         let expected = "{}";
         if let Err(e) = file.read_to_string(&mut buffer) {
@@ -384,16 +1347,16 @@ mod tests {
     #[test]
     fn it_renames_keys() {
         let temp = create_temp_file("{\"test\":\"expected\"}");
-        let mut file = File::open(&temp).unwrap();
-        let config = Config { filepath: temp };
+        let config = Config { filepath: temp, ..Default::default() };
         let slate = Slate { config: &config };
         let key = "test".to_string();
         let new_key = "spec".to_string();
 
-        if let Err(e) = slate.rename(&key, &new_key) {
+        if let Err(e) = slate.rename(&key, &new_key, false) {
             panic!("Cannot move the key: {:?}", e);
         };
 
+        let mut file = File::open(&config.filepath).unwrap();
         let mut buffer = String::new();
         let expected = "{\"spec\":\"expected\"}";
         if let Err(e) = file.read_to_string(&mut buffer) {
@@ -402,17 +1365,204 @@ mod tests {
         assert_eq!(expected, buffer);
     }
 
+    #[test]
+    fn it_joins_and_replaces_lines() {
+        assert_eq!("a\nb", join_lines("a", "b"));
+        assert_eq!("a\nb\n", join_lines("a\n", "b\n"));
+        assert_eq!("b", join_lines("", "b"));
+
+        assert_eq!(Some("a\nB\nc\n".to_string()), replace_line("a\nb\nc\n", 2, "B\n"));
+        assert_eq!(Some("A".to_string()), replace_line("a", 1, "A"));
+        assert_eq!(None, replace_line("a\nb\n", 3, "c"));
+        assert_eq!(None, replace_line("a", 0, "c"));
+    }
+
+    #[test]
+    fn it_appends_and_prepends_text() {
+        let temp = create_temp_file("{\"hosts\":{\"value\":\"web-1\",\"tags\":[\"ops\"]}}");
+        let config = Config { filepath: temp, ..Default::default() };
+        let slate = Slate { config: &config };
+        let (hosts, todo) = ("hosts".to_string(), "todo".to_string());
+
+        slate.append(&hosts, &"web-2".to_string()).unwrap();
+        slate.prepend(&hosts, &"web-0".to_string()).unwrap();
+        slate.append(&todo, &"- docs".to_string()).unwrap();
+        slate.set_line(&hosts, 2, &"web-01".to_string()).unwrap();
+
+        let entry = slate.entry(&hosts).unwrap();
+        assert_eq!("web-0\nweb-01\nweb-2", entry.value);
+        assert_eq!(vec!["ops"], entry.tags);
+        assert_eq!("- docs", slate.get(&todo).unwrap());
+        assert_eq!(4, slate.history(&hosts).unwrap().len());
+
+        match slate.set_line(&hosts, 4, &"web-3".to_string()) {
+            Err(SlateError::LineNotFound(key, 4)) => assert_eq!("hosts", key),
+            other => panic!("Expected a missing line error, got {:?}", other),
+        }
+        match slate.set_line(&"missing".to_string(), 1, &"a".to_string()) {
+            Err(SlateError::NotFound(key)) => assert_eq!("missing", key),
+            other => panic!("Expected a missing key error, got {:?}", other),
+        }
+
+        let image = "image".to_string();
+        slate.set_bytes(&image, &[0x89, 0x50, 0xff]).unwrap();
+        match slate.append(&image, &"text".to_string()) {
+            Err(SlateError::Binary(key)) => assert_eq!("image", key),
+            other => panic!("Expected a binary value error, got {:?}", other),
+        }
+        assert!(slate.set_line(&image, 1, &"text".to_string()).is_err());
+        assert_eq!(vec![0x89, 0x50, 0xff], slate.get_bytes(&image).unwrap());
+    }
+
+    #[test]
+    fn it_copies_keys() {
+        let temp = create_temp_file("{\"a\":{\"value\":\"1\",\"tags\":[\"x\"]},\"b\":\"2\"}");
+        let config = Config { filepath: temp, ..Default::default() };
+        let slate = Slate { config: &config };
+        let (a, b, c) = ("a".to_string(), "b".to_string(), "c".to_string());
+
+        slate.copy(&a, &c, false).unwrap();
+        assert_eq!(slate.entry(&a).unwrap(), slate.entry(&c).unwrap());
+        assert_eq!(vec!["a", "b", "c"], slate.list().unwrap());
+
+        match slate.copy(&a, &b, false) {
+            Err(SlateError::Conflict(key)) => assert_eq!("b", key),
+            other => panic!("Expected a conflict, got {:?}", other),
+        }
+        slate.copy(&a, &b, true).unwrap();
+        assert_eq!("1", slate.get(&b).unwrap());
+    }
+
+    #[test]
+    fn it_transfers_keys_between_stores() {
+        let source = create_temp_file("{\"a\":\"1\",\"b\":\"2\"}");
+        let target = create_temp_file("{\"b\":\"3\"}");
+        let source = Config { filepath: source, ..Default::default() };
+        let target = Config { filepath: target, ..Default::default() };
+        let (from, to) = (Slate { config: &source }, Slate { config: &target });
+        let keys = vec!["a".to_string(), "b".to_string()];
+
+        let result = transfer::transfer(&from, &to, &keys, Mode::Move, false).unwrap();
+        assert_eq!(vec!["b"], result.conflicts);
+        assert!(result.keys.is_empty());
+        assert_eq!(vec!["a", "b"], from.list().unwrap());
+
+        transfer::transfer(&from, &to, &keys, Mode::Copy, true).unwrap();
+        assert_eq!("2", to.get(&keys[1]).unwrap());
+        assert_eq!(vec!["a", "b"], from.list().unwrap());
+
+        transfer::transfer(&from, &to, &keys[..1], Mode::Move, true).unwrap();
+        assert_eq!(vec!["b"], from.list().unwrap());
+        assert_eq!(vec!["a", "b"], to.list().unwrap());
+    }
+
+    #[test]
+    fn it_does_not_transfer_keys_to_the_same_store() {
+        let temp = create_temp_file("{\"a\":\"1\"}");
+        let same = temp.parent().unwrap().join(".").join(temp.file_name().unwrap());
+        let config = Config { filepath: temp, ..Default::default() };
+        let other = Config { filepath: same, ..Default::default() };
+        let (from, to) = (Slate { config: &config }, Slate { config: &other });
+
+        assert!(transfer::transfer(&from, &to, &["a".to_string()], Mode::Move, true).is_err());
+        assert_eq!("1", from.get(&"a".to_string()).unwrap());
+    }
+
+    #[test]
+    fn it_does_not_rename_missing_keys() {
+        let temp = create_temp_file("{\"test\":\"expected\"}");
+        let config = Config { filepath: temp, ..Default::default() };
+        let slate = Slate { config: &config };
+
+        match slate.rename(&"missing".to_string(), &"spec".to_string(), false) {
+            Err(SlateError::NotFound(key)) => assert_eq!("missing", key),
+            other => panic!("Expected a missing key error, got {:?}", other),
+        }
+        assert_eq!(vec!["test"], slate.list().unwrap());
+    }
+
+    #[test]
+    fn it_does_not_overwrite_keys_when_renaming() {
+        let temp = create_temp_file("{\"a\":\"1\",\"b\":\"2\"}");
+        let config = Config { filepath: temp, ..Default::default() };
+        let slate = Slate { config: &config };
+        let (a, b) = ("a".to_string(), "b".to_string());
+
+        match slate.rename(&a, &b, false) {
+            Err(SlateError::Conflict(key)) => assert_eq!("b", key),
+            other => panic!("Expected a conflict error, got {:?}", other),
+        }
+        assert_eq!("2", slate.get(&b).unwrap());
+
+        if let Err(e) = slate.rename(&a, &b, true) {
+            panic!("Cannot force the rename: {:?}", e);
+        };
+        assert_eq!(vec!["b"], slate.list().unwrap());
+        assert_eq!("1", slate.get(&b).unwrap());
+    }
+
+    #[test]
+    fn it_renames_keys_to_themselves() {
+        let temp = create_temp_file("{\"test\":\"expected\"}");
+        let config = Config { filepath: temp, ..Default::default() };
+        let slate = Slate { config: &config };
+        let key = "test".to_string();
+
+        if let Err(e) = slate.rename(&key, &key, false) {
+            panic!("Cannot rename the key to itself: {:?}", e);
+        };
+        assert_eq!("expected", slate.get(&key).unwrap());
+    }
+
+    #[test]
+    fn it_applies_transactions() {
+        let temp = create_temp_file("{\"a\":\"1\",\"b\":\"2\"}");
+        let config = Config { filepath: temp, ..Default::default() };
+        let slate = Slate { config: &config };
+        let (a, b, c) = ("a".to_string(), "b".to_string(), "c".to_string());
+
+        let result = slate.transaction(|tx| {
+            tx.set(&c, &"3".to_string());
+            tx.remove(&b);
+            try!(tx.rename(&a, &b, false));
+            tx.get(&b)
+        });
+
+        assert_eq!("1", result.unwrap());
+        assert_eq!(vec!["b", "c"], slate.list().unwrap());
+        assert_eq!("1", slate.get(&b).unwrap());
+    }
+
+    #[test]
+    fn it_does_not_save_failed_transactions() {
+        let temp = create_temp_file("{\"a\":\"1\"}");
+        let config = Config { filepath: temp, ..Default::default() };
+        let slate = Slate { config: &config };
+        let (a, b) = ("a".to_string(), "b".to_string());
+
+        let result = slate.transaction(|tx| {
+            tx.remove(&a);
+            tx.rename(&a, &b, false)
+        });
+
+        match result {
+            Err(SlateError::NotFound(key)) => assert_eq!("a", key),
+            other => panic!("Expected a missing key error, got {:?}", other),
+        }
+        assert_eq!(vec!["a"], slate.list().unwrap());
+    }
+
     #[test]
     fn it_clears_keys() {
         let temp = create_temp_file("{\"test\":\"expected\"}");
-        let mut file = File::open(&temp).unwrap();
-        let config = Config { filepath: temp };
+        let config = Config { filepath: temp, ..Default::default() };
         let slate = Slate { config: &config };
 
         if let Err(e) = slate.clear() {
             panic!("Cannot clear keys: {:?}", e);
         };
 
+        let mut file = File::open(&config.filepath).unwrap();
         let mut buffer = String::new();
         let expected = "{}";
         if let Err(e) = file.read_to_string(&mut buffer) {
@@ -420,4 +1570,264 @@ mod tests {
         };
         assert_eq!(expected, buffer);
     }
+
+    #[test]
+    fn it_searches_values() {
+        let temp = create_temp_file("{\"a\":\"Hello World\",\"b\":\"hello\",\"c\":\"bye\"}");
+        let config = Config { filepath: temp, ..Default::default() };
+        let slate = Slate { config: &config };
+
+        match slate.search(&"world hello".to_string()) {
+            Ok(list) => assert_eq!(vec!["a"], list),
+            Err(e) => panic!("Cannot search values: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn it_migrates_keys_to_other_backends() {
+        let temp = create_temp_file("{\"a\":\"1\",\"b\":\"2\"}");
+        let mut db = temp.clone();
+        db.set_extension("db");
+        let config = Config { filepath: temp, ..Default::default() };
+        let destination = Config { filepath: db, backend: Backend::Sqlite, ..Default::default() };
+        let slate = Slate { config: &config };
+
+        match slate.migrate(&destination) {
+            Ok(total) => assert_eq!(2, total),
+            Err(e) => panic!("Cannot migrate keys: {:?}", e),
+        }
+
+        let migrated = Slate { config: &destination };
+        assert_eq!(vec!["a", "b"], migrated.list().unwrap());
+        assert_eq!("2", migrated.get(&"b".to_string()).unwrap());
+    }
+
+    #[test]
+    fn it_hides_and_removes_expired_keys() {
+        let temp = create_temp_file("{\"a\":\"1\",\"b\":{\"value\":\"2\",\"expires\":1}}");
+        let config = Config { filepath: temp, ..Default::default() };
+        let slate = Slate { config: &config };
+
+        match slate.get(&"b".to_string()) {
+            Err(SlateError::NotFound(key)) => assert_eq!("b", key),
+            other => panic!("Expected a missing key error, got {:?}", other),
+        }
+        assert_eq!(vec!["a"], slate.list().unwrap());
+        assert_eq!(Vec::<String>::new(), slate.search(&"2".to_string()).unwrap());
+        assert_eq!(Vec::<(String, u64)>::new(), slate.expiring().unwrap());
+
+        assert_eq!(1, slate.gc().unwrap());
+        assert_eq!(0, slate.gc().unwrap());
+    }
+
+    #[test]
+    fn it_drops_the_usage_and_history_of_expired_keys() {
+        let temp = create_temp_file("");
+        let config = Config { filepath: temp, ..Default::default() };
+        let slate = Slate { config: &config };
+        let (a, b) = ("a".to_string(), "b".to_string());
+
+        slate.set(&a, &"1".to_string()).unwrap();
+        slate.set(&b, &"2".to_string()).unwrap();
+        slate.record_use(&b);
+        slate.set_entry(&b, &Entry { expires: Some(1), ..Entry::new("3") }).unwrap();
+        // Removed by any other change.
+        slate.set(&a, &"4".to_string()).unwrap();
+
+        slate.gc().unwrap();
+
+        assert!(usage::read(&config).get(&b).is_none());
+        assert!(history::read(&config).get(&b).is_none());
+        assert_eq!(2, slate.history(&a).unwrap().len());
+    }
+
+    #[test]
+    fn it_lists_expiring_keys() {
+        let temp = create_temp_file("");
+        let config = Config { filepath: temp, ..Default::default() };
+        let slate = Slate { config: &config };
+        let later = time::now() + 3600;
+
+        slate.set(&"a".to_string(), &"1".to_string()).unwrap();
+        slate.set_entry(&"b".to_string(), &Entry { expires: Some(later), ..Entry::new("2") })
+            .unwrap();
+
+        assert_eq!(vec![("b".to_string(), later)], slate.expiring().unwrap());
+        assert_eq!("2", slate.get(&"b".to_string()).unwrap());
+    }
+
+    #[test]
+    fn it_sorts_keys_by_usage() {
+        let temp = create_temp_file("{\"a\":\"1\",\"b\":\"22\",\"c\":\"333\"}");
+        let config = Config { filepath: temp, ..Default::default() };
+        let slate = Slate { config: &config };
+        let (a, b) = ("a".to_string(), "b".to_string());
+
+        slate.record_use(&a);
+        slate.record_use(&b);
+        slate.record_use(&b);
+
+        assert_eq!(vec!["b", "a", "c"], slate.list_by(Sort::Frecency).unwrap());
+        assert_eq!(vec!["c", "b", "a"], slate.list_by(Sort::Size).unwrap());
+        assert_eq!(vec!["a", "b"], slate.recent(5).unwrap());
+        assert_eq!(vec!["a"], slate.recent(1).unwrap());
+
+        slate.rename(&b, &"d".to_string(), false).unwrap();
+        slate.remove(&a).unwrap();
+        assert_eq!(vec!["d"], slate.recent(5).unwrap());
+    }
+
+    #[test]
+    fn it_records_when_keys_are_created() {
+        let temp = create_temp_file("{}");
+        let config = Config { filepath: temp, ..Default::default() };
+        let slate = Slate { config: &config };
+        let (a, b) = ("a".to_string(), "b".to_string());
+        let created = |key: &String| usage::read(&config).get(key).and_then(|usage| usage.created);
+
+        slate.set(&a, &"1".to_string()).unwrap();
+        let first = created(&a).unwrap();
+        usage::update(&config, |stats| stats.get_mut("a").unwrap().created = Some(first - 100));
+
+        slate.set(&a, &"2".to_string()).unwrap();
+        slate.copy(&a, &b, false).unwrap();
+        assert_eq!(Some(first - 100), created(&a));
+        assert!(created(&b).unwrap() >= first);
+
+        slate.rename(&a, &"c".to_string(), false).unwrap();
+        assert_eq!(Some(first - 100), created(&"c".to_string()));
+    }
+
+    #[test]
+    fn it_does_not_track_usage_when_disabled() {
+        let temp = create_temp_file("{\"a\":\"1\"}");
+        let config = Config { filepath: temp, tracking: false, ..Default::default() };
+        let slate = Slate { config: &config };
+
+        slate.record_use(&"a".to_string());
+
+        assert_eq!(Vec::<String>::new(), slate.recent(5).unwrap());
+        assert!(!usage::path(&config).exists());
+    }
+
+    #[test]
+    fn it_keeps_the_history_of_values() {
+        let temp = create_temp_file("{\"a\":\"1\"}");
+        let config = Config { filepath: temp, ..Default::default() };
+        let slate = Slate { config: &config };
+        let (a, b) = ("a".to_string(), "b".to_string());
+        let values = |key: &String| -> Vec<String> {
+            slate.history(key).unwrap().into_iter().map(|revision| revision.value).collect()
+        };
+
+        slate.set(&a, &"2".to_string()).unwrap();
+        slate.transaction(|tx| {
+                tx.set(&a, &"3".to_string());
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(vec!["1", "2", "3"], values(&a));
+
+        slate.rename(&a, &b, false).unwrap();
+        assert_eq!(vec!["1", "2", "3"], values(&b));
+
+        slate.remove(&b).unwrap();
+        slate.set(&b, &"4".to_string()).unwrap();
+        assert_eq!(vec!["4"], values(&b));
+    }
+
+    #[test]
+    fn it_reads_keys_from_linked_directories() {
+        let temp = create_temp_file("{\"hosts\":\"db-1\"}");
+        let config = Config { filepath: temp, ..Default::default() };
+        let slate = Slate { config: &config };
+        let mut dir = env::temp_dir();
+        dir.push(thread_rng().gen_ascii_chars().take(10).collect::<String>());
+        fs::create_dir_all(dir.join("deploy")).unwrap();
+        fs::write(dir.join("deploy/prod.sh"), "make deploy ENV=prod").unwrap();
+        fs::write(dir.join("hosts.txt"), "web-1").unwrap();
+
+        let linked = slate.link(&dir).unwrap();
+        assert_eq!(vec![linked.clone()], slate.links());
+        let keys: Vec<String> = slate.linked().unwrap().into_iter().map(|l| l.key).collect();
+        assert_eq!(vec!["deploy/prod"], keys);
+
+        let key = "deploy/prod".to_string();
+        assert_eq!("make deploy ENV=prod", slate.get(&key).unwrap());
+        assert_eq!("db-1", slate.get(&"hosts".to_string()).unwrap());
+        assert!(slate.link_of(&key).unwrap().is_some());
+        assert!(slate.link_of(&"hosts".to_string()).unwrap().is_none());
+
+        fs::write(dir.join("deploy/prod.sh"), "make release").unwrap();
+        assert_eq!("make release", slate.get(&key).unwrap());
+
+        slate.unlink(&linked).unwrap();
+        assert!(slate.get(&key).is_err());
+        assert!(slate.unlink(&linked).is_err());
+    }
+
+    #[test]
+    fn it_sets_the_keys_of_aliases() {
+        let temp = create_temp_file("{\"t\":\"2\"}");
+        let config = Config { filepath: temp, ..Default::default() };
+        let slate = Slate { config: &config };
+        let (a, t) = ("a".to_string(), "t".to_string());
+        slate.alias(&a, &t, false).unwrap();
+
+        slate.set(&a, &"3".to_string()).unwrap();
+
+        assert_eq!(Some(Entry::alias("t")), store::open(&config).unwrap().get("a").unwrap());
+        assert_eq!("3", slate.get(&t).unwrap());
+        assert_eq!(vec!["2", "3"],
+                   slate.history(&t).unwrap().into_iter().map(|r| r.value).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn it_follows_aliases() {
+        let temp = create_temp_file("{\"pods\":\"kubectl get pods\"}");
+        let config = Config { filepath: temp, ..Default::default() };
+        let slate = Slate { config: &config };
+        let (pods, p, q) = ("pods".to_string(), "p".to_string(), "q".to_string());
+
+        slate.alias(&p, &pods, false).unwrap();
+        slate.alias(&q, &p, false).unwrap();
+        assert_eq!("kubectl get pods", slate.get(&q).unwrap());
+        assert_eq!(vec![(p.clone(), pods.clone()), (q.clone(), p.clone())],
+                   slate.aliases().unwrap());
+
+        match slate.alias(&p, &q, true) {
+            Err(SlateError::Cycle(keys)) => assert_eq!(vec!["p", "q", "p"], keys),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+        assert!(slate.alias(&p, &pods, false).is_err());
+        assert!(slate.alias(&"x".to_string(), &"missing".to_string(), false).is_err());
+
+        let renamed = "k8s/pods".to_string();
+        slate.rename(&pods, &renamed, false).unwrap();
+        assert_eq!("kubectl get pods", slate.get(&q).unwrap());
+
+        slate.remove(&renamed).unwrap();
+        match slate.get(&q) {
+            Err(SlateError::NotFound(key)) => assert_eq!(renamed, key),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn it_changes_the_targets_of_aliases() {
+        let temp = create_temp_file("{\"pods\":\"kubectl get pods\"}");
+        let config = Config { filepath: temp, ..Default::default() };
+        let slate = Slate { config: &config };
+        let (pods, p) = ("pods".to_string(), "p".to_string());
+        slate.alias(&p, &pods, false).unwrap();
+
+        slate.append(&p, &"-A".to_string()).unwrap();
+        assert_eq!("kubectl get pods\n-A", slate.get(&pods).unwrap());
+        assert_eq!(vec![(p.clone(), pods.clone())], slate.aliases().unwrap());
+
+        let renamed = "k8s/pods".to_string();
+        slate.transaction(|tx| tx.rename(&pods, &renamed, false)).unwrap();
+        assert_eq!(vec![(p.clone(), renamed.clone())], slate.aliases().unwrap());
+        assert_eq!("kubectl get pods\n-A", slate.get(&p).unwrap());
+    }
 }
@@ -0,0 +1,269 @@
+//! Directories linked to a store as read-only namespaces.
+//!
+//! Every file inside a linked directory is a key: its path relative
+//! to the directory, without the extension, like `deploy/prod` for
+//! `snippets/deploy/prod.sh`. Files are read when their keys are
+//! used, so they are always up to date. Keys saved in the store
+//! hide the linked ones with the same name.
+//!
+//! The linked directories are saved in a sidecar file next to the
+//! store, like `~/.slate.links`.
+
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use config::Config;
+use highlight;
+use sidecar;
+use store::Entry;
+
+/// A key that comes from a file of a linked directory.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Linked {
+    pub key: String,
+    /// The linked directory.
+    pub dir: PathBuf,
+    /// The file with the value.
+    pub path: PathBuf,
+}
+
+impl Linked {
+    /// Read the file as an entry, with the language of its
+    /// extension, its name and its permissions.
+    pub fn entry(&self) -> io::Result<Entry> {
+        let entry = try!(Entry::from_file(&self.path));
+        let language = self.path
+            .extension()
+            .map(|ext| highlight::normalize(&ext.to_string_lossy()))
+            .filter(|language| highlight::languages().contains(&language.as_str()));
+
+        Ok(Entry { language: language, ..entry })
+    }
+}
+
+/// Path of the sidecar file with the linked directories of a store.
+pub fn path(config: &Config) -> PathBuf {
+    sidecar::path(config, "links")
+}
+
+/// Get the directories linked to a store.
+pub fn read(config: &Config) -> Vec<PathBuf> {
+    sidecar::read(&path(config))
+}
+
+/// Save the directories linked to a store.
+pub fn write(config: &Config, dirs: &Vec<PathBuf>) -> io::Result<()> {
+    sidecar::write(&path(config), dirs)
+}
+
+/// Get the keys of all the linked directories, sorted. When two
+/// directories have the same key, the first linked one wins.
+pub fn keys(dirs: &[PathBuf]) -> Vec<Linked> {
+    let mut linked: Vec<Linked> = Vec::new();
+
+    for dir in dirs {
+        let mut files = Vec::new();
+        walk(dir, &mut files);
+        for file in files {
+            if let Some(key) = key_for(dir, &file) {
+                if !linked.iter().any(|other| other.key == key) {
+                    linked.push(Linked { key: key, dir: dir.clone(), path: file });
+                }
+            }
+        }
+    }
+    linked.sort_by(|a, b| a.key.cmp(&b.key));
+
+    linked
+}
+
+/// Find the file of a key in the linked directories, without going
+/// through all of them.
+pub fn find(dirs: &[PathBuf], key: &str) -> Option<Linked> {
+    // Keys never leave the linked directories.
+    let relative = Path::new(key);
+    let outside = relative.components().any(|component| match component {
+        Component::Normal(_) => false,
+        _ => true,
+    });
+    if key.is_empty() || outside {
+        return None;
+    }
+
+    for dir in dirs {
+        if through_link(dir, relative) {
+            continue;
+        }
+        let candidate = dir.join(relative);
+        let parent = match candidate.parent() {
+            Some(parent) => parent,
+            None => continue,
+        };
+        let entries = match fs::read_dir(parent) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+
+        let mut files: Vec<PathBuf> = entries.filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file() && key_for(dir, path).map(|k| k == key).unwrap_or(false))
+            .collect();
+        files.sort();
+        if let Some(path) = files.into_iter().next() {
+            return Some(Linked { key: key.to_owned(), dir: dir.clone(), path: path });
+        }
+    }
+
+    None
+}
+
+/// Key of a file: its path relative to the directory, without the
+/// extension and with `/` as separator. Hidden files have no key.
+pub fn key_for(dir: &Path, file: &Path) -> Option<String> {
+    let relative = match file.strip_prefix(dir) {
+        Ok(relative) => relative,
+        Err(_) => return None,
+    };
+
+    let mut parts: Vec<String> = Vec::new();
+    for component in relative.components() {
+        match component {
+            Component::Normal(name) => {
+                let name = name.to_string_lossy();
+                if name.starts_with('.') {
+                    return None;
+                }
+                parts.push(name.into_owned());
+            }
+            _ => return None,
+        }
+    }
+
+    let last = match parts.pop() {
+        Some(last) => last,
+        None => return None,
+    };
+    let stem = match last.rfind('.') {
+        Some(index) if index > 0 => last[..index].to_owned(),
+        _ => last,
+    };
+    parts.push(stem);
+
+    Some(parts.join("/"))
+}
+
+/// Whether a path inside a directory goes through a symbolic link
+/// to a directory, which `walk` doesn't follow.
+fn through_link(dir: &Path, relative: &Path) -> bool {
+    let parent = match relative.parent() {
+        Some(parent) => parent,
+        None => return false,
+    };
+    let mut current = dir.to_path_buf();
+
+    for component in parent.components() {
+        current.push(component);
+        match fs::symlink_metadata(&current) {
+            Ok(metadata) if metadata.file_type().is_symlink() => return true,
+            _ => (),
+        }
+    }
+
+    false
+}
+
+/// Collect the files inside a directory, skipping hidden ones like
+/// `.git`.
+///
+/// Symbolic links to files are followed, but not the ones to
+/// directories, which could make a loop.
+fn walk(dir: &Path, files: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let file_type = match entry.file_type() {
+            Ok(file_type) => file_type,
+            Err(_) => continue,
+        };
+        if file_type.is_dir() {
+            walk(&path, files);
+        } else if file_type.is_file() || (file_type.is_symlink() && path.is_file()) {
+            files.push(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use rand::{thread_rng, Rng};
+
+    fn temp_dir() -> PathBuf {
+        let random_name: String = thread_rng().gen_ascii_chars().take(10).collect();
+        let mut temp = env::temp_dir();
+        temp.push(random_name);
+        fs::create_dir_all(temp.join("deploy")).unwrap();
+        fs::create_dir_all(temp.join(".git")).unwrap();
+        fs::write(temp.join("deploy/prod.sh"), "make deploy ENV=prod").unwrap();
+        fs::write(temp.join("hosts"), "web-1").unwrap();
+        fs::write(temp.join(".git/HEAD"), "ref: master").unwrap();
+
+        temp
+    }
+
+    #[test]
+    fn it_gets_keys_from_paths() {
+        let dir = Path::new("/snippets");
+
+        assert_eq!(Some("deploy/prod".to_string()),
+                   key_for(dir, Path::new("/snippets/deploy/prod.sh")));
+        assert_eq!(Some("archive.tar".to_string()),
+                   key_for(dir, Path::new("/snippets/archive.tar.gz")));
+        assert_eq!(None, key_for(dir, Path::new("/snippets/.env")));
+        assert_eq!(None, key_for(dir, Path::new("/other/file.sh")));
+    }
+
+    #[test]
+    fn it_lists_and_finds_linked_keys() {
+        let dir = temp_dir();
+        let dirs = vec![dir.clone()];
+
+        let keys: Vec<String> = keys(&dirs).into_iter().map(|linked| linked.key).collect();
+        assert_eq!(vec!["deploy/prod", "hosts"], keys);
+
+        let linked = find(&dirs, "deploy/prod").unwrap();
+        assert_eq!(dir.join("deploy/prod.sh"), linked.path);
+        let entry = linked.entry().unwrap();
+        assert_eq!("make deploy ENV=prod", entry.value);
+        assert_eq!(Some("shell".to_string()), entry.language);
+
+        assert_eq!(None, find(&dirs, "deploy"));
+        assert_eq!(None, find(&dirs, "../hosts"));
+        assert_eq!(None, find(&dirs, ".git/HEAD"));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn it_does_not_follow_links_to_directories() {
+        use std::os::unix::fs::symlink;
+
+        let dir = temp_dir();
+        symlink(&dir, dir.join("deploy/loop")).unwrap();
+        symlink(dir.join("hosts"), dir.join("servers")).unwrap();
+
+        let dirs = vec![dir.clone()];
+        let keys: Vec<String> = keys(&dirs).into_iter().map(|linked| linked.key).collect();
+        assert_eq!(vec!["deploy/prod", "hosts", "servers"], keys);
+        assert_eq!(None, find(&dirs, "deploy/loop/hosts"));
+        assert_eq!(dir.join("servers"), find(&dirs, "servers").unwrap().path);
+    }
+}
@@ -1,8 +1,268 @@
+use serde_json;
 use std::fmt;
+use std::str::FromStr;
+
+use base64;
+use highlight::{self, Theme};
+use store;
+
+/// Formats available to show messages.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    /// Plain text for humans.
+    Text,
+    /// One JSON document per message.
+    Json,
+    /// One JSON document per line, lists are split in several lines.
+    Ndjson,
+}
+
+impl Default for Format {
+    fn default() -> Format {
+        Format::Text
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Format, String> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "ndjson" => Ok(Format::Ndjson),
+            _ => Err(format!("Unknown output format: {}", s)),
+        }
+    }
+}
+
+/// A change made in the store by a command.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct Change {
+    pub action: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+impl Change {
+    pub fn new(action: &'static str) -> Change {
+        Change { action: action, ..Default::default() }
+    }
+
+    pub fn key(mut self, key: &str) -> Change {
+        self.key = Some(key.to_owned());
+        self
+    }
+
+    pub fn from(mut self, from: &str) -> Change {
+        self.from = Some(from.to_owned());
+        self
+    }
+
+    pub fn count(mut self, count: usize) -> Change {
+        self.count = Some(count);
+        self
+    }
+
+    pub fn path(mut self, path: &str) -> Change {
+        self.path = Some(path.to_owned());
+        self
+    }
+}
 
 pub enum Message {
     Raw(String),
     Info(String),
+    /// The value of a key. With `eol` the text output ends
+    /// with a single new line.
+    Value {
+        key: String,
+        value: String,
+        eol: bool,
+        /// Language of the value, used to highlight it.
+        language: Option<String>,
+    },
+    /// A list of keys.
+    Keys(Vec<String>),
+    /// A change in the store, with an optional text for humans.
+    Change(Change, Option<String>),
+    /// Records with the same fields, like a table, with a text for
+    /// humans. In JSON they are listed under the given name.
+    Rows {
+        name: &'static str,
+        rows: Vec<serde_json::Value>,
+        text: String,
+    },
+    /// A single record, with a text for humans.
+    Record(serde_json::Value, String),
+    /// A binary value. As text it is written as it is, and in JSON
+    /// it is encoded with base64.
+    Bytes { key: String, bytes: Vec<u8> },
+}
+
+#[derive(Serialize)]
+struct Text<'a> {
+    message: &'a str,
+}
+
+#[derive(Serialize)]
+struct Value<'a> {
+    key: &'a str,
+    value: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    language: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct Binary<'a> {
+    key: &'a str,
+    value: String,
+    encoding: &'static str,
+}
+
+#[derive(Serialize)]
+struct Key<'a> {
+    key: &'a str,
+}
+
+#[derive(Serialize)]
+struct Keys<'a> {
+    keys: &'a Vec<String>,
+}
+
+impl Message {
+    /// Render the message in the given format.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use slate::message::{Format, Message};
+    ///
+    /// let message = Message::Keys(vec!["a".to_string(), "b".to_string()]);
+    /// print!("{}", message.render(Format::Json));
+    /// //=> {"keys":["a","b"]}
+    /// print!("{}", message.render(Format::Ndjson));
+    /// //=> {"key":"a"}
+    /// //=> {"key":"b"}
+    /// ```
+    pub fn render(&self, format: Format) -> String {
+        match format {
+            Format::Text => self.text(),
+            Format::Json => self.json(),
+            Format::Ndjson => self.ndjson(),
+        }
+    }
+
+    /// Render the message like `render`, but highlighting the values
+    /// shown as text with the theme.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use slate::highlight::Theme;
+    /// use slate::message::{Format, Message};
+    ///
+    /// let message = Message::Value {
+    ///     key: "loop".to_string(),
+    ///     value: "for f in *; do echo $f; done".to_string(),
+    ///     eol: true,
+    ///     language: Some("shell".to_string()),
+    /// };
+    /// print!("{}", message.highlight(Format::Text, &Theme::default()));
+    /// ```
+    pub fn highlight(&self, format: Format, theme: &Theme) -> String {
+        match (self, format) {
+            (&Message::Value { ref value, eol, language: Some(ref language), .. }, Format::Text) => {
+                if eol {
+                    format!("{}\n", highlight::highlight(value.trim_right(), language, theme))
+                } else {
+                    highlight::highlight(value, language, theme)
+                }
+            }
+            _ => self.render(format),
+        }
+    }
+
+    fn text(&self) -> String {
+        match *self {
+            Message::Raw(ref msg) => msg.to_owned(),
+            Message::Info(ref msg) => info(msg),
+            Message::Value { ref value, eol, .. } => {
+                if eol {
+                    info(value)
+                } else {
+                    value.to_owned()
+                }
+            }
+            Message::Keys(ref keys) => info(&keys.join("\n")),
+            Message::Change(_, Some(ref msg)) => info(msg),
+            Message::Change(_, None) => String::new(),
+            Message::Rows { ref text, .. } => info(text),
+            Message::Record(_, ref text) => info(text),
+            Message::Bytes { ref bytes, .. } => String::from_utf8_lossy(bytes).into_owned(),
+        }
+    }
+
+    fn json(&self) -> String {
+        let encoded = match *self {
+            Message::Raw(ref msg) |
+            Message::Info(ref msg) => serde_json::to_string(&Text { message: msg }),
+            Message::Value { ref key, ref value, ref language, .. } => {
+                serde_json::to_string(&Value {
+                    key: key,
+                    value: value,
+                    language: language.as_ref().map(|l| l.as_str()),
+                })
+            }
+            Message::Keys(ref keys) => serde_json::to_string(&Keys { keys: keys }),
+            Message::Change(ref change, _) => serde_json::to_string(change),
+            Message::Rows { name, ref rows, .. } => {
+                let mut object = serde_json::Map::new();
+                object.insert(name.to_string(), serde_json::Value::Array(rows.clone()));
+                serde_json::to_string(&object)
+            }
+            Message::Record(ref record, _) => serde_json::to_string(record),
+            Message::Bytes { ref key, ref bytes } => {
+                serde_json::to_string(&Binary {
+                    key: key,
+                    value: base64::encode(bytes),
+                    encoding: store::BASE64,
+                })
+            }
+        };
+
+        line(encoded)
+    }
+
+    fn ndjson(&self) -> String {
+        match *self {
+            Message::Keys(ref keys) => {
+                keys.iter()
+                    .map(|key| line(serde_json::to_string(&Key { key: key })))
+                    .collect()
+            }
+            Message::Rows { ref rows, .. } => {
+                rows.iter().map(|row| line(serde_json::to_string(row))).collect()
+            }
+            _ => self.json(),
+        }
+    }
+}
+
+/// Text with an unique eol.
+fn info(msg: &str) -> String {
+    format!("{}\n", msg.trim_right())
+}
+
+fn line(encoded: Result<String, serde_json::Error>) -> String {
+    // Messages are made of strings and numbers, so they can always be serialized.
+    format!("{}\n", encoded.expect("Cannot serialize message"))
 }
 
 impl fmt::Display for Message {
@@ -32,10 +292,7 @@ impl fmt::Display for Message {
     /// //=> "hello world\n"
     /// ```
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            &Message::Raw(ref msg) => write!(formatter, "{}", msg),
-            &Message::Info(ref msg) => write!(formatter, "{}\n", msg.trim_right()),
-        }
+        write!(formatter, "{}", self.text())
     }
 }
 
@@ -69,4 +326,65 @@ mod tests {
 
         assert_eq!(expected, actual);
     }
+
+    #[test]
+    fn it_renders_values_as_json() {
+        let expected = "{\"key\":\"foo\",\"value\":\"bar\\n\"}\n".to_string();
+        let subject = Message::Value {
+            key: "foo".to_string(),
+            value: "bar\n".to_string(),
+            eol: false,
+            language: None,
+        };
+
+        assert_eq!(expected, subject.render(Format::Json));
+    }
+
+    #[test]
+    fn it_renders_bytes_as_base64() {
+        let expected = "{\"key\":\"bin\",\"value\":\"AP8=\",\"encoding\":\"base64\"}\n";
+        let subject = Message::Bytes { key: "bin".to_string(), bytes: vec![0, 255] };
+
+        assert_eq!(expected, subject.render(Format::Json));
+    }
+
+    #[test]
+    fn it_renders_keys_as_ndjson() {
+        let expected = "{\"key\":\"a\"}\n{\"key\":\"b\"}\n".to_string();
+        let subject = Message::Keys(vec!["a".to_string(), "b".to_string()]);
+
+        assert_eq!(expected, subject.render(Format::Ndjson));
+    }
+
+    #[test]
+    fn it_renders_changes() {
+        let change = Change::new("rename").key("b").from("a");
+        let subject = Message::Change(change, Some("The key has been renamed".to_string()));
+
+        assert_eq!("The key has been renamed\n", subject.render(Format::Text));
+        assert_eq!("{\"action\":\"rename\",\"key\":\"b\",\"from\":\"a\"}\n",
+                   subject.render(Format::Json));
+    }
+
+    #[test]
+    fn it_does_not_show_silent_changes_as_text() {
+        let subject = Message::Change(Change::new("set").key("foo"), None);
+
+        assert_eq!("", subject.render(Format::Text));
+    }
+
+    #[test]
+    fn it_renders_rows() {
+        let subject = Message::Rows {
+            name: "expiring",
+            rows: vec![serde_json::from_str("{\"key\":\"a\"}").unwrap(),
+                       serde_json::from_str("{\"key\":\"b\"}").unwrap()],
+            text: "a\nb".to_string(),
+        };
+
+        assert_eq!("a\nb\n", subject.render(Format::Text));
+        assert_eq!("{\"expiring\":[{\"key\":\"a\"},{\"key\":\"b\"}]}\n",
+                   subject.render(Format::Json));
+        assert_eq!("{\"key\":\"a\"}\n{\"key\":\"b\"}\n", subject.render(Format::Ndjson));
+    }
 }
@@ -0,0 +1,252 @@
+//! External commands.
+//!
+//! Any executable named `slate-<name>` found in the PATH can be
+//! used as the command `slate <name>`, like git does. Plugins get
+//! the configuration of the store through env vars, see
+//! `Config::vars`.
+
+use std::env;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Sender};
+use std::thread::{self, JoinHandle};
+
+use cli::Streams;
+use config::Config;
+use errors::{CommandError, EXIT_IO};
+
+/// Prefix of the names of the plugin executables.
+pub const PREFIX: &'static str = "slate-";
+
+/// Directories where plugins are searched, taken from the PATH.
+pub fn search_paths() -> Vec<PathBuf> {
+    match env::var_os("PATH") {
+        Some(path) => env::split_paths(&path).collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Find the executable of a plugin.
+///
+/// The first one found in the paths is used.
+pub fn find(name: &str, paths: &[PathBuf]) -> Option<PathBuf> {
+    paths.iter()
+        .map(|dir| dir.join(format!("{}{}", PREFIX, name)))
+        .find(|path| is_executable(path))
+}
+
+/// List the names of all the plugins, with their executables.
+pub fn list(paths: &[PathBuf]) -> Vec<(String, PathBuf)> {
+    let mut plugins: Vec<(String, PathBuf)> = Vec::new();
+
+    for dir in paths {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+
+        for entry in entries.filter_map(|e| e.ok()) {
+            let filename = entry.file_name().to_string_lossy().into_owned();
+            if !filename.starts_with(PREFIX) || !is_executable(&entry.path()) {
+                continue;
+            }
+
+            let name = filename[PREFIX.len()..].to_string();
+            if !name.is_empty() && !plugins.iter().any(|&(ref n, _)| *n == name) {
+                plugins.push((name, entry.path()));
+            }
+        }
+    }
+    plugins.sort();
+
+    plugins
+}
+
+/// Run a plugin with its arguments and get its exit code.
+///
+/// When the output is a terminal the plugin uses the terminal of
+/// the program directly, so it can be interactive. Otherwise its
+/// output is copied into the streams as it is written. It reads
+/// the stdin of the program when the streams allow it, or the stdin
+/// of the streams, written in its own thread so the plugin doesn't
+/// have to read it all before exiting.
+pub fn run(executable: &Path,
+           args: &[String],
+           config: &Config,
+           streams: &mut Streams)
+           -> Result<i32, CommandError> {
+    let mut command = Command::new(executable);
+    command.args(args).envs(config.vars());
+
+    if streams.terminal {
+        try!(streams.stdout.flush());
+        let status = try!(command.status());
+
+        return Ok(status.code().unwrap_or(EXIT_IO));
+    }
+
+    let stdin = if streams.inherit_stdin { Stdio::inherit() } else { Stdio::piped() };
+    let mut child = try!(command.stdin(stdin)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn());
+    let (sender, receiver) = mpsc::channel();
+    let readers = vec![forward(child.stdout.take(), false, sender.clone()),
+                       forward(child.stderr.take(), true, sender)];
+
+    if let Some(mut stdin) = child.stdin.take() {
+        let mut input = Vec::new();
+        try!(streams.stdin.read_to_end(&mut input));
+        // The plugin may exit without reading its input, so the
+        // thread is not waited for.
+        thread::spawn(move || {
+            let _ = stdin.write_all(&input);
+        });
+    }
+    for (is_stderr, chunk) in receiver {
+        if is_stderr {
+            try!(streams.stderr.write_all(&chunk));
+        } else {
+            try!(streams.stdout.write_all(&chunk));
+        }
+    }
+    for reader in readers.into_iter().filter_map(|reader| reader) {
+        let _ = reader.join();
+    }
+    let status = try!(child.wait());
+
+    Ok(status.code().unwrap_or(EXIT_IO))
+}
+
+/// Send the output of a plugin to the channel, as it is read.
+fn forward<R: Read + Send + 'static>(output: Option<R>,
+                                     is_stderr: bool,
+                                     sender: Sender<(bool, Vec<u8>)>)
+                                     -> Option<JoinHandle<()>> {
+    output.map(|mut output| {
+        thread::spawn(move || {
+            let mut buffer = [0; 8192];
+            loop {
+                match output.read(&mut buffer) {
+                    Ok(0) | Err(_) => break,
+                    Ok(read) => {
+                        if sender.send((is_stderr, buffer[..read].to_vec())).is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+        })
+    })
+}
+
+/// Whether a path is a file that can be executed.
+#[cfg(unix)]
+pub fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    match fs::metadata(path) {
+        Ok(metadata) => metadata.is_file() && metadata.permissions().mode() & 0o111 != 0,
+        Err(_) => false,
+    }
+}
+
+#[cfg(not(unix))]
+pub fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::{self, Write};
+    use std::os::unix::fs::PermissionsExt;
+    use rand::{thread_rng, Rng};
+
+    fn plugins_dir() -> PathBuf {
+        let random_name: String = thread_rng().gen_ascii_chars().take(10).collect();
+        let mut dir = env::temp_dir();
+        dir.push(random_name);
+        fs::create_dir(&dir).unwrap();
+
+        dir
+    }
+
+    fn create_plugin(dir: &Path, filename: &str, body: &str, mode: u32) {
+        let path = dir.join(filename);
+        File::create(&path).unwrap().write_all(body.as_bytes()).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+    }
+
+    #[test]
+    fn it_lists_executable_plugins() {
+        let first = plugins_dir();
+        let second = plugins_dir();
+        create_plugin(&first, "slate-sync", "", 0o755);
+        create_plugin(&first, "slate-notes", "", 0o644);
+        create_plugin(&second, "slate-sync", "", 0o755);
+        create_plugin(&second, "slate-edit", "", 0o755);
+        create_plugin(&second, "other", "", 0o755);
+
+        let paths = vec![first.clone(), second.clone()];
+        let names: Vec<String> = list(&paths).into_iter().map(|(name, _)| name).collect();
+
+        assert_eq!(vec!["edit", "sync"], names);
+        assert_eq!(Some(first.join("slate-sync")), find("sync", &paths));
+        assert_eq!(None, find("notes", &paths));
+    }
+
+    #[test]
+    fn it_runs_plugins_with_the_configuration() {
+        let dir = plugins_dir();
+        create_plugin(&dir,
+                      "slate-echo",
+                      "#!/bin/sh\necho \"$SLATE_PROFILE $SLATE_BACKEND $SLATE_FILEPATH $1\"\nexit 7\n",
+                      0o755);
+
+        let config = Config {
+            filepath: PathBuf::from("/tmp/slate.json"),
+            profile: "work".to_string(),
+            ..Default::default()
+        };
+        let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+        let code = run(&dir.join("slate-echo"),
+                       &["arg".to_string()],
+                       &config,
+                       &mut Streams {
+                           stdin: &mut io::empty(),
+                           stdout: &mut stdout,
+                           stderr: &mut stderr,
+                           terminal: false,
+                           inherit_stdin: false,
+                       });
+
+        assert_eq!(7, code.unwrap());
+        assert_eq!("work json /tmp/slate.json arg\n", String::from_utf8(stdout).unwrap());
+    }
+
+    #[test]
+    fn it_pipes_the_streams_into_plugins() {
+        let dir = plugins_dir();
+        create_plugin(&dir, "slate-upper", "#!/bin/sh\ntr a-z A-Z\necho done >&2\n", 0o755);
+
+        let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+        let code = run(&dir.join("slate-upper"),
+                       &[],
+                       &Default::default(),
+                       &mut Streams {
+                           stdin: &mut "hello\n".as_bytes(),
+                           stdout: &mut stdout,
+                           stderr: &mut stderr,
+                           terminal: false,
+                           inherit_stdin: false,
+                       });
+
+        assert_eq!(0, code.unwrap());
+        assert_eq!("HELLO\n", String::from_utf8(stdout).unwrap());
+        assert_eq!("done\n", String::from_utf8(stderr).unwrap());
+    }
+}
@@ -0,0 +1,132 @@
+//! Files saved next to the store with extra data about it, like
+//! `~/.slate.usage` or `~/.slate.history`.
+//!
+//! Sidecars are not essential: if one cannot be read it is taken
+//! as empty, so the store keeps working when they are missing,
+//! corrupted or in a read-only directory.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::PathBuf;
+
+use config::Config;
+use files::{self, Lock};
+use results::SlateResult;
+
+/// Path of the sidecar with the given extension.
+pub fn path(config: &Config, extension: &str) -> PathBuf {
+    let mut name = config.filepath.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+    name.push(".");
+    name.push(extension);
+
+    config.filepath.with_file_name(name)
+}
+
+/// Read a sidecar, or get the default data if it cannot be read.
+pub fn read<T>(path: &PathBuf) -> T
+    where T: DeserializeOwned + Default
+{
+    load(path).unwrap_or_default()
+}
+
+/// Write the data into a temporary file and move it over the sidecar,
+/// keeping its permissions. New sidecars get the permissions of the
+/// store, so they are as private as the values.
+pub fn write<T: Serialize>(path: &PathBuf, data: &T) -> io::Result<()> {
+    let encoded = try!(serde_json::to_string(data).map_err(|e| io::Error::new(io::ErrorKind::Other, e)));
+    // The sidecar is the path of the store with one more extension.
+    let store = path.with_extension("");
+    let permissions = fs::metadata(path).or_else(|_| fs::metadata(store)).map(|m| m.permissions());
+
+    files::replace(path, encoded.as_bytes(), permissions.ok())
+}
+
+/// Change the data of a sidecar while its lock is held, so the
+/// changes made by other processes at the same time are not lost.
+///
+/// A sidecar that exists but cannot be read is left as it is.
+pub fn update<T, F>(path: &PathBuf, f: F) -> SlateResult<()>
+    where T: DeserializeOwned + Serialize + Default,
+          F: FnOnce(&mut T)
+{
+    let _lock = try!(Lock::acquire(path));
+    let mut data = try!(load(path));
+    f(&mut data);
+
+    Ok(try!(write(path, &data)))
+}
+
+/// Read a sidecar, with the default data if it doesn't exist.
+fn load<T>(path: &PathBuf) -> io::Result<T>
+    where T: DeserializeOwned + Default
+{
+    let mut buffer = String::new();
+    match File::open(path).and_then(|mut f| f.read_to_string(&mut buffer)) {
+        Ok(_) => (),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(T::default()),
+        Err(e) => return Err(e),
+    }
+
+    serde_json::from_str(&buffer).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use rand::{thread_rng, Rng};
+
+    #[test]
+    #[cfg(unix)]
+    fn it_creates_sidecars_with_the_permissions_of_the_store() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let random_name: String = thread_rng().gen_ascii_chars().take(10).collect();
+        let mut temp = env::temp_dir();
+        temp.push(random_name + ".json");
+        File::create(&temp).unwrap();
+        fs::set_permissions(&temp, fs::Permissions::from_mode(0o600)).unwrap();
+        let config = Config { filepath: temp, ..Default::default() };
+
+        let sidecar = path(&config, "usage");
+        write(&sidecar, &vec!["a"]).unwrap();
+
+        assert_eq!(0o600, fs::metadata(&sidecar).unwrap().permissions().mode() & 0o777);
+        assert_eq!(vec!["a".to_string()], read::<Vec<String>>(&sidecar));
+    }
+
+    #[test]
+    fn it_does_not_lose_concurrent_updates() {
+        let random_name: String = thread_rng().gen_ascii_chars().take(10).collect();
+        let mut temp = env::temp_dir();
+        temp.push(random_name + ".json.usage");
+
+        let writers: Vec<::std::thread::JoinHandle<()>> = (0..8)
+            .map(|_| {
+                let temp = temp.clone();
+                ::std::thread::spawn(move || for _ in 0..20 {
+                    update(&temp, |count: &mut u64| *count += 1).unwrap();
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        assert_eq!(160, read::<u64>(&temp));
+    }
+
+    #[test]
+    fn it_does_not_overwrite_sidecars_that_cannot_be_read() {
+        let random_name: String = thread_rng().gen_ascii_chars().take(10).collect();
+        let mut temp = env::temp_dir();
+        temp.push(random_name + ".json.history");
+        fs::write(&temp, "{\"a\":").unwrap();
+
+        assert!(update(&temp, |count: &mut u64| *count += 1).is_err());
+        assert_eq!("{\"a\":", fs::read_to_string(&temp).unwrap());
+    }
+}
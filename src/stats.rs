@@ -0,0 +1,183 @@
+//! Statistics about the contents of a store: sizes, namespaces,
+//! tags, stale keys and duplicated values.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use store::{Contents, Entry};
+use usage;
+
+const DAY: u64 = 24 * 60 * 60;
+
+/// Characters that separate the namespace of a key from the rest,
+/// like `k8s` in `k8s/pods` or `k8s-pods`.
+const SEPARATORS: &'static [char] = &['/', ':', '.', '-', '_'];
+
+/// Size of the value of a key, in bytes.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Size {
+    pub key: String,
+    pub size: usize,
+}
+
+/// Number of keys with a namespace or a tag.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Count {
+    pub name: String,
+    pub keys: usize,
+}
+
+/// Statistics of the keys of a store.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Summary {
+    /// Number of keys.
+    pub keys: usize,
+    /// Size of all the values, in bytes.
+    pub size: usize,
+    /// The largest values, largest first.
+    pub largest: Vec<Size>,
+    /// Keys by namespace, most used first.
+    pub namespaces: Vec<Count>,
+    /// Keys by tag, most used first.
+    pub tags: Vec<Count>,
+    /// Keys not used, or changed, in the given number of days.
+    pub stale: Vec<String>,
+    /// Groups of keys with the same value, without aliases.
+    pub duplicates: Vec<Vec<String>>,
+}
+
+/// Namespace of a key: the text before its first separator, if it
+/// has one.
+///
+/// # Example
+///
+/// ```rust
+/// use slate::stats;
+///
+/// assert_eq!(Some("k8s"), stats::namespace("k8s/pods"));
+/// assert_eq!(Some("deploy"), stats::namespace("deploy-prod"));
+/// assert_eq!(None, stats::namespace("vpn"));
+/// ```
+pub fn namespace(key: &str) -> Option<&str> {
+    match key.find(SEPARATORS) {
+        Some(index) if index > 0 => Some(&key[..index]),
+        _ => None,
+    }
+}
+
+/// Size of the value of an entry, decoded if it is binary.
+pub fn size(entry: &Entry) -> usize {
+    entry.bytes().map(|bytes| bytes.len()).unwrap_or(entry.value.len())
+}
+
+/// Get the statistics of the contents of a store, with their usage.
+///
+/// Keys are stale if they haven't been used or changed in
+/// `stale_days`, or if they have no usage at all. The `top` largest
+/// values are listed.
+pub fn summarize(contents: &Contents,
+                 stats: &usage::Stats,
+                 now: u64,
+                 stale_days: u64,
+                 top: usize)
+                 -> Summary {
+    let mut keys: Vec<&String> = contents.iter()
+        .filter(|&(_, entry)| !entry.is_expired(now))
+        .map(|(key, _)| key)
+        .collect();
+    keys.sort();
+
+    let mut summary = Summary { keys: keys.len(), ..Default::default() };
+    let mut namespaces: HashMap<&str, usize> = HashMap::new();
+    let mut tags: HashMap<&str, usize> = HashMap::new();
+    let mut values: HashMap<&str, Vec<String>> = HashMap::new();
+    let limit = now.saturating_sub(stale_days.saturating_mul(DAY));
+
+    for key in keys {
+        let entry = &contents[key];
+
+        let size = size(entry);
+        summary.size += size;
+        summary.largest.push(Size { key: key.clone(), size: size });
+
+        if let Some(namespace) = namespace(key) {
+            *namespaces.entry(namespace).or_insert(0) += 1;
+        }
+        for tag in &entry.tags {
+            *tags.entry(tag).or_insert(0) += 1;
+        }
+
+        let last = stats.get(key).and_then(|usage| usage.used.into_iter().chain(usage.updated).max());
+        if last.map(|last| last < limit).unwrap_or(true) {
+            summary.stale.push(key.clone());
+        }
+
+        if !entry.is_alias() {
+            values.entry(&entry.value).or_insert_with(Vec::new).push(key.clone());
+        }
+    }
+
+    summary.largest.sort_by(|a, b| b.size.cmp(&a.size).then(a.key.cmp(&b.key)));
+    summary.largest.truncate(top);
+    summary.namespaces = counts(namespaces);
+    summary.tags = counts(tags);
+    summary.duplicates = values.into_iter().map(|(_, keys)| keys).filter(|keys| keys.len() > 1).collect();
+    summary.duplicates.sort();
+
+    summary
+}
+
+/// Sort the counts, largest first and then by name.
+fn counts(counts: HashMap<&str, usize>) -> Vec<Count> {
+    let mut counts: Vec<Count> = counts.into_iter()
+        .map(|(name, keys)| Count { name: name.to_owned(), keys: keys })
+        .collect();
+    counts.sort_by(|a, b| match b.keys.cmp(&a.keys) {
+        Ordering::Equal => a.name.cmp(&b.name),
+        order => order,
+    });
+
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use usage::Usage;
+
+    #[test]
+    fn it_summarizes_the_contents() {
+        let mut contents = Contents::new();
+        let tagged = |value: &str, tags: &[&str]| {
+            Entry { tags: tags.iter().map(|tag| tag.to_string()).collect(), ..Entry::new(value) }
+        };
+        contents.insert("k8s/pods".to_string(), tagged("kubectl get pods", &["k8s", "ops"]));
+        contents.insert("k8s/nodes".to_string(), tagged("kubectl get nodes", &["k8s"]));
+        contents.insert("pods".to_string(), Entry::new("kubectl get pods"));
+        contents.insert("old".to_string(), Entry { expires: Some(5), ..Entry::new("gone") });
+
+        let mut stats = usage::Stats::new();
+        stats.insert("k8s/pods".to_string(),
+                     Usage { count: 1, used: Some(100 * DAY), ..Default::default() });
+        stats.insert("pods".to_string(), Usage { updated: Some(DAY), ..Default::default() });
+
+        let summary = summarize(&contents, &stats, 101 * DAY, 30, 2);
+
+        assert_eq!(3, summary.keys);
+        assert_eq!(49, summary.size);
+        assert_eq!(vec![Size { key: "k8s/nodes".to_string(), size: 17 },
+                        Size { key: "k8s/pods".to_string(), size: 16 }],
+                   summary.largest);
+        assert_eq!(vec![Count { name: "k8s".to_string(), keys: 2 }], summary.namespaces);
+        assert_eq!(vec![Count { name: "k8s".to_string(), keys: 2 },
+                        Count { name: "ops".to_string(), keys: 1 }],
+                   summary.tags);
+        assert_eq!(vec!["k8s/nodes", "pods"], summary.stale);
+        assert_eq!(vec![vec!["k8s/pods".to_string(), "pods".to_string()]],
+                   summary.duplicates);
+
+        // Keys never used are always stale.
+        let summary = summarize(&contents, &stats, 101 * DAY, u64::max_value(), 2);
+        assert_eq!(vec!["k8s/nodes"], summary.stale);
+    }
+}
@@ -0,0 +1,316 @@
+use serde_json;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, prelude::*};
+use std::path::{Path, PathBuf};
+
+use files::{self, Lock};
+use results::SlateResult;
+use errors::SlateError;
+use super::{self as store, Contents, Entry, Store};
+
+/// An entry as saved in the file.
+///
+/// Entries without attributes are saved as plain strings, which
+/// is also the format of the files of older versions.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Saved {
+    Plain(String),
+    Full(Entry),
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum SavedRef<'a> {
+    Plain(&'a str),
+    Full(&'a Entry),
+}
+
+/// Store that saves the contents in a plain JSON file.
+pub struct JsonStore {
+    filepath: PathBuf,
+}
+
+impl JsonStore {
+    pub fn new(filepath: &Path) -> JsonStore {
+        JsonStore { filepath: filepath.to_path_buf() }
+    }
+}
+
+impl Store for JsonStore {
+    /// Read the contents of the Slate file.
+    ///
+    /// If the file doesn't exist it will be created. An empty
+    /// file has no keys, but a file with invalid JSON is reported
+    /// as corrupted, so it is never overwritten by accident.
+    fn read(&self) -> SlateResult<Contents> {
+        let mut r = match File::open(&self.filepath) {
+            Ok(file) => file,
+            Err(_) => {
+                // Only if it is still missing, as the lock may not be held.
+                match OpenOptions::new().write(true).create_new(true).open(&self.filepath) {
+                    Ok(mut file) => try!(file.write_all(b"{}")),
+                    Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => (),
+                    Err(e) => return Err(From::from(e)),
+                }
+                try!(File::open(&self.filepath))
+            }
+        };
+
+        let mut buffer = String::new();
+        try!(r.read_to_string(&mut buffer));
+
+        if buffer.trim().is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let saved: HashMap<String, Saved> = match serde_json::from_str(&buffer) {
+            Ok(saved) => saved,
+            Err(e) => {
+                return Err(SlateError::Corrupted(format!("{}: {}", self.filepath.display(), e)))
+            }
+        };
+
+        let contents = saved.into_iter()
+            .map(|(key, entry)| {
+                let entry = match entry {
+                    Saved::Plain(value) => Entry::new(&value),
+                    Saved::Full(entry) => entry,
+                };
+                (key, entry)
+            })
+            .collect();
+
+        Ok(contents)
+    }
+
+    /// Write to the Slate file.
+    fn write(&self, contents: &Contents) -> SlateResult<()> {
+        let _lock = try!(Lock::acquire(&self.filepath));
+
+        self.save(contents)
+    }
+
+    fn set(&self, key: &str, entry: &Entry) -> SlateResult<()> {
+        let _lock = try!(Lock::acquire(&self.filepath));
+
+        Unlocked(self).set(key, entry)
+    }
+
+    fn remove(&self, key: &str) -> SlateResult<()> {
+        let _lock = try!(Lock::acquire(&self.filepath));
+
+        Unlocked(self).remove(key)
+    }
+
+    fn rename(&self, src: &str, dst: &str, force: bool) -> SlateResult<()> {
+        let _lock = try!(Lock::acquire(&self.filepath));
+
+        Unlocked(self).rename(src, dst, force)
+    }
+
+    fn commit(&self, contents: &Contents, _touched: &HashSet<String>) -> SlateResult<()> {
+        let _lock = try!(Lock::acquire(&self.filepath));
+
+        self.save(contents)
+    }
+
+    /// The file is locked while the entry is changed.
+    fn update(&self,
+              key: &str,
+              f: &mut FnMut(Option<&Entry>) -> SlateResult<Entry>)
+              -> SlateResult<(Option<Entry>, Entry)> {
+        let _lock = try!(Lock::acquire(&self.filepath));
+
+        store::update_entry(&Unlocked(self), key, f)
+    }
+
+    /// The file is locked while the changes are applied.
+    fn transact(&self,
+                f: &mut FnMut(&mut Contents) -> SlateResult<HashSet<String>>)
+                -> SlateResult<Contents> {
+        let _lock = try!(Lock::acquire(&self.filepath));
+
+        store::transact_contents(&Unlocked(self), f)
+    }
+
+    fn gc(&self) -> SlateResult<usize> {
+        let _lock = try!(Lock::acquire(&self.filepath));
+
+        Unlocked(self).gc()
+    }
+}
+
+impl JsonStore {
+    /// Write the contents into a temporary file and move it over the
+    /// Slate file, so the file is never left with partial changes.
+    fn save(&self, contents: &Contents) -> SlateResult<()> {
+        let encoded = try!(encode(contents));
+        // Keep the permissions of the file, like the ones set by `slate doctor --fix`.
+        let permissions = fs::metadata(&self.filepath).ok().map(|metadata| metadata.permissions());
+
+        try!(files::replace(&self.filepath, encoded.as_bytes(), permissions));
+
+        Ok(())
+    }
+}
+
+/// The store while its lock is held: the default operations of
+/// `Store` are used, writing without taking the lock again.
+struct Unlocked<'a>(&'a JsonStore);
+
+impl<'a> Store for Unlocked<'a> {
+    fn read(&self) -> SlateResult<Contents> {
+        self.0.read()
+    }
+
+    fn write(&self, contents: &Contents) -> SlateResult<()> {
+        self.0.save(contents)
+    }
+
+    fn commit(&self, contents: &Contents, _touched: &HashSet<String>) -> SlateResult<()> {
+        self.0.save(contents)
+    }
+}
+
+fn encode(contents: &Contents) -> SlateResult<String> {
+    let saved: HashMap<&String, SavedRef> = contents.iter()
+        .map(|(key, entry)| {
+            let saved = if entry.is_plain() {
+                SavedRef::Plain(&entry.value)
+            } else {
+                SavedRef::Full(entry)
+            };
+            (key, saved)
+        })
+        .collect();
+
+    Ok(try!(serde_json::to_string(&saved)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::thread;
+    use files::lock_path;
+    use rand::{thread_rng, Rng};
+
+    #[test]
+    fn it_reports_corrupted_files() {
+        let random_name: String = thread_rng().gen_ascii_chars().take(10).collect();
+        let mut temp = env::temp_dir();
+        temp.push(random_name + ".json");
+        File::create(&temp).unwrap().write_all(b"{\"a\":").unwrap();
+
+        let store = JsonStore::new(&temp);
+
+        match store.set("b", &Entry::new("2")) {
+            Err(SlateError::Corrupted(_)) => (),
+            other => panic!("Expected a corrupted store error, got {:?}", other),
+        }
+
+        let mut buffer = String::new();
+        File::open(&temp).unwrap().read_to_string(&mut buffer).unwrap();
+        assert_eq!("{\"a\":", buffer);
+    }
+
+    #[test]
+    fn it_saves_plain_entries_as_strings() {
+        let random_name: String = thread_rng().gen_ascii_chars().take(10).collect();
+        let mut temp = env::temp_dir();
+        temp.push(random_name + ".json");
+        let store = JsonStore::new(&temp);
+        let mut contents = Contents::new();
+        contents.insert("a".to_string(), Entry::new("1"));
+        contents.insert("b".to_string(), Entry { expires: Some(10), ..Entry::new("2") });
+
+        store.write(&contents).unwrap();
+
+        let mut buffer = String::new();
+        File::open(&temp).unwrap().read_to_string(&mut buffer).unwrap();
+        assert!(buffer.contains("\"a\":\"1\""));
+        assert!(buffer.contains("\"b\":{\"value\":\"2\",\"expires\":10}"));
+        assert_eq!(contents, store.read().unwrap());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn it_replaces_the_file_without_truncating_it() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let random_name: String = thread_rng().gen_ascii_chars().take(10).collect();
+        let mut temp = env::temp_dir();
+        temp.push(random_name + ".json");
+        fs::write(&temp, "{\"a\":\"1\"}").unwrap();
+        fs::set_permissions(&temp, fs::Permissions::from_mode(0o640)).unwrap();
+        let mut reader = File::open(&temp).unwrap();
+        let store = JsonStore::new(&temp);
+
+        store.remove("a").unwrap();
+
+        let mut buffer = String::new();
+        reader.read_to_string(&mut buffer).unwrap();
+        assert_eq!("{\"a\":\"1\"}", buffer);
+        assert_eq!("{}", fs::read_to_string(&temp).unwrap());
+        assert_eq!(0o640, fs::metadata(&temp).unwrap().permissions().mode() & 0o777);
+    }
+
+    #[test]
+    fn it_locks_the_file_while_updating() {
+        let random_name: String = thread_rng().gen_ascii_chars().take(10).collect();
+        let mut temp = env::temp_dir();
+        temp.push(random_name + ".json");
+
+        let writers: Vec<thread::JoinHandle<()>> = (0..4)
+            .map(|n| {
+                let temp = temp.clone();
+                thread::spawn(move || {
+                    let store = JsonStore::new(&temp);
+                    for i in 0..10 {
+                        let line = format!("{}-{}\n", n, i);
+                        store.update("log", &mut |old| {
+                                let value = old.map(|e| e.value.clone()).unwrap_or_default();
+                                Ok(Entry::new(&(value + &line)))
+                            })
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        let store = JsonStore::new(&temp);
+        assert_eq!(40, store.get("log").unwrap().unwrap().value.lines().count());
+        assert!(!lock_path(&temp).exists());
+    }
+
+    #[test]
+    fn it_locks_the_file_while_setting_keys() {
+        let random_name: String = thread_rng().gen_ascii_chars().take(10).collect();
+        let mut temp = env::temp_dir();
+        temp.push(random_name + ".json");
+
+        let writers: Vec<thread::JoinHandle<()>> = (0..4)
+            .map(|n| {
+                let temp = temp.clone();
+                thread::spawn(move || {
+                    let store = JsonStore::new(&temp);
+                    for i in 0..10 {
+                        store.set(&format!("{}-{}", n, i), &Entry::new("1")).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        let store = JsonStore::new(&temp);
+        assert_eq!(40, store.keys().unwrap().len());
+        assert!(!lock_path(&temp).exists());
+    }
+}
@@ -0,0 +1,538 @@
+//! Storage engines used to save the contents of a Slate.
+//!
+//! Every backend implements the `Store` trait. Only `read` and
+//! `write` are required; the rest of operations have default
+//! implementations that go through the whole contents, so backends
+//! that can do better (like SQLite) should override them.
+
+pub mod json;
+pub mod sqlite;
+#[cfg(unix)]
+pub mod remote;
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+use base64;
+use config::{Backend, Config};
+use results::SlateResult;
+use errors::SlateError;
+use time;
+
+/// Encoding of binary values.
+pub const BASE64: &'static str = "base64";
+
+/// A value with its attributes.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub value: String,
+    /// When the entry expires, in seconds since the Unix epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires: Option<u64>,
+    /// Language of the value, like `shell` or `sql`, when it is set
+    /// explicitly.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// Tags to group related values.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// What the value is for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// How the value is encoded: `base64` for binary values, which
+    /// are not valid UTF-8.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+    /// Permissions of the file the value was read from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<u32>,
+    /// Name of the file the value was read from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filename: Option<String>,
+    /// Key that this entry is an alias of. Aliases have no value,
+    /// the one of the key is used instead.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+}
+
+impl Entry {
+    pub fn new(value: &str) -> Entry {
+        Entry { value: value.to_owned(), ..Default::default() }
+    }
+
+    /// Build an alias of a key.
+    pub fn alias(target: &str) -> Entry {
+        Entry { alias: Some(target.to_owned()), ..Default::default() }
+    }
+
+    /// Build an entry with any bytes as its value. Text is saved as
+    /// it is, and binary values are encoded with base64.
+    pub fn from_bytes(bytes: Vec<u8>) -> Entry {
+        match String::from_utf8(bytes) {
+            Ok(value) => Entry::new(&value),
+            Err(e) => {
+                Entry {
+                    encoding: Some(BASE64.to_string()),
+                    ..Entry::new(&base64::encode(e.as_bytes()))
+                }
+            }
+        }
+    }
+
+    /// Build an entry with the contents of a file, keeping its name
+    /// and permissions.
+    pub fn from_file(path: &Path) -> io::Result<Entry> {
+        let bytes = try!(fs::read(path));
+        let metadata = try!(fs::metadata(path));
+
+        Ok(Entry {
+            mode: mode(&metadata),
+            filename: path.file_name().map(|name| name.to_string_lossy().into_owned()),
+            ..Entry::from_bytes(bytes)
+        })
+    }
+
+    /// Whether the entry is an alias of another key.
+    pub fn is_alias(&self) -> bool {
+        self.alias.is_some()
+    }
+
+    /// Whether the value is binary, and so encoded.
+    pub fn is_binary(&self) -> bool {
+        self.encoding.is_some()
+    }
+
+    /// Get the bytes of the value, decoded if needed.
+    ///
+    /// It returns `None` if the value cannot be decoded.
+    pub fn bytes(&self) -> Option<Vec<u8>> {
+        match self.encoding {
+            Some(ref encoding) if encoding == BASE64 => base64::decode(&self.value),
+            Some(_) => None,
+            None => Some(self.value.clone().into_bytes()),
+        }
+    }
+
+    /// Whether the entry has expired at the given time.
+    pub fn is_expired(&self, now: u64) -> bool {
+        match self.expires {
+            Some(expires) => expires <= now,
+            None => false,
+        }
+    }
+
+    /// Whether the entry is just a value, without other attributes.
+    pub fn is_plain(&self) -> bool {
+        self.expires.is_none() && self.language.is_none() && self.tags.is_empty() &&
+        self.description.is_none() && self.encoding.is_none() && self.mode.is_none() &&
+        self.filename.is_none() && self.alias.is_none()
+    }
+}
+
+/// Permissions of a file.
+#[cfg(unix)]
+fn mode(metadata: &fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+
+    Some(metadata.permissions().mode() & 0o777)
+}
+
+#[cfg(not(unix))]
+fn mode(_metadata: &fs::Metadata) -> Option<u32> {
+    None
+}
+
+/// All the keys and entries of a store.
+pub type Contents = HashMap<String, Entry>;
+
+/// Expired entries are hidden, and removed from the store on the
+/// next change or with `gc`.
+pub trait Store {
+    /// Read all the contents of the store, including expired entries.
+    fn read(&self) -> SlateResult<Contents>;
+
+    /// Replace all the contents of the store.
+    fn write(&self, contents: &Contents) -> SlateResult<()>;
+
+    /// Get the entry of a key, if it exists.
+    fn get(&self, key: &str) -> SlateResult<Option<Entry>> {
+        let contents = try!(self.read());
+
+        Ok(contents.get(key).filter(|entry| !entry.is_expired(time::now())).cloned())
+    }
+
+    /// Set a key with its entry.
+    fn set(&self, key: &str, entry: &Entry) -> SlateResult<()> {
+        let mut contents = try!(self.read());
+
+        purge(&mut contents, time::now());
+        contents.insert(key.to_owned(), entry.clone());
+
+        self.write(&contents)
+    }
+
+    /// Remove a key with its entry.
+    fn remove(&self, key: &str) -> SlateResult<()> {
+        let mut contents = try!(self.read());
+
+        purge(&mut contents, time::now());
+        contents.remove(key);
+
+        self.write(&contents)
+    }
+
+    /// Rename a key in a single read and write.
+    ///
+    /// It fails if the source doesn't exist, or if the destination
+    /// exists and `force` is not set. Renaming a key to itself
+    /// does nothing. The aliases of the source are changed to point
+    /// to the destination.
+    fn rename(&self, src: &str, dst: &str, force: bool) -> SlateResult<()> {
+        let mut contents = try!(self.read());
+
+        purge(&mut contents, time::now());
+        try!(rename_key(&mut contents, src, dst, force));
+
+        self.write(&contents)
+    }
+
+    /// Save the result of a transaction.
+    ///
+    /// `contents` holds the final state of the store and `touched`
+    /// the keys that were set or removed. All the changes must be
+    /// saved at once, or none of them.
+    fn commit(&self, contents: &Contents, _touched: &HashSet<String>) -> SlateResult<()> {
+        self.write(contents)
+    }
+
+    /// Change the entry of a key in a single locked read and write.
+    ///
+    /// `f` gets the current entry, if any, and returns the new one.
+    /// Nothing is saved if it fails. It returns the previous and the
+    /// new entries.
+    fn update(&self,
+              key: &str,
+              f: &mut FnMut(Option<&Entry>) -> SlateResult<Entry>)
+              -> SlateResult<(Option<Entry>, Entry)> {
+        update_entry(self, key, f)
+    }
+
+    /// Apply several changes in a single locked read and write.
+    ///
+    /// `f` gets the contents without the expired entries, changes
+    /// them and returns the keys it set or removed. It is called
+    /// once. Nothing is saved if it fails or if it changes nothing.
+    /// It returns the contents as they were before the changes.
+    fn transact(&self,
+                f: &mut FnMut(&mut Contents) -> SlateResult<HashSet<String>>)
+                -> SlateResult<Contents> {
+        transact_contents(self, f)
+    }
+
+    /// Remove all keys.
+    fn clear(&self) -> SlateResult<()> {
+        self.write(&HashMap::new())
+    }
+
+    /// Remove the expired entries, and get how many there were.
+    fn gc(&self) -> SlateResult<usize> {
+        let mut contents = try!(self.read());
+
+        let removed = purge(&mut contents, time::now());
+        if !removed.is_empty() {
+            try!(self.write(&contents));
+        }
+
+        Ok(removed.len())
+    }
+
+    /// Get all the keys, sorted.
+    fn keys(&self) -> SlateResult<Vec<String>> {
+        let contents = try!(self.read());
+        let now = time::now();
+
+        let mut keys: Vec<String> = contents.iter()
+            .filter(|&(_, entry)| !entry.is_expired(now))
+            .map(|(key, _)| key.clone())
+            .collect();
+        keys.sort();
+
+        Ok(keys)
+    }
+
+    /// Get the keys, sorted, whose values contain all the words
+    /// of the query. The search is case insensitive.
+    fn search(&self, query: &str) -> SlateResult<Vec<String>> {
+        let contents = try!(self.read());
+        let now = time::now();
+
+        let mut keys: Vec<String> = contents.iter()
+            .filter(|&(_, entry)| !entry.is_expired(now) && contains_words(&entry.value, query))
+            .map(|(key, _)| key.clone())
+            .collect();
+        keys.sort();
+
+        Ok(keys)
+    }
+}
+
+/// Whether a value contains all the words of a query, ignoring
+/// the case.
+pub fn contains_words(value: &str, query: &str) -> bool {
+    let value = value.to_lowercase();
+
+    query.split_whitespace().all(|word| value.contains(&word.to_lowercase()))
+}
+
+/// Remove the entries expired at the given time, and get their keys.
+pub fn purge(contents: &mut Contents, now: u64) -> Vec<String> {
+    let expired: Vec<String> = contents.iter()
+        .filter(|&(_, entry)| entry.is_expired(now))
+        .map(|(key, _)| key.clone())
+        .collect();
+
+    for key in &expired {
+        contents.remove(key);
+    }
+
+    expired
+}
+
+/// Change the entry of a key reading and committing all the
+/// contents, see `Store::update`.
+///
+/// This is the default implementation, used by backends that lock
+/// the store in their own way before calling it.
+pub fn update_entry<S: Store + ?Sized>(store: &S,
+                                       key: &str,
+                                       f: &mut FnMut(Option<&Entry>) -> SlateResult<Entry>)
+                                       -> SlateResult<(Option<Entry>, Entry)> {
+    let mut contents = try!(store.read());
+    let mut touched: HashSet<String> = purge(&mut contents, time::now()).into_iter().collect();
+
+    let old = contents.get(key).cloned();
+    let new = try!(f(old.as_ref()));
+    contents.insert(key.to_owned(), new.clone());
+    touched.insert(key.to_owned());
+
+    try!(store.commit(&contents, &touched));
+
+    Ok((old, new))
+}
+
+/// Apply several changes reading and committing all the contents,
+/// see `Store::transact`.
+///
+/// This is the default implementation, used by backends that lock
+/// the store in their own way before calling it.
+pub fn transact_contents<S: Store + ?Sized>(store: &S,
+                                            f: &mut FnMut(&mut Contents)
+                                                          -> SlateResult<HashSet<String>>)
+                                            -> SlateResult<Contents> {
+    let mut contents = try!(store.read());
+    let expired = purge(&mut contents, time::now());
+    let previous = contents.clone();
+
+    let mut touched = try!(f(&mut contents));
+    if !touched.is_empty() {
+        touched.extend(expired);
+        try!(store.commit(&contents, &touched));
+    }
+
+    Ok(previous)
+}
+
+/// Rename a key inside some contents, changing its aliases to point
+/// to the new name.
+///
+/// It fails if the source doesn't exist, or if the destination
+/// exists and `force` is not set. Renaming a key to itself
+/// does nothing. It returns the aliases that were changed.
+pub fn rename_key(contents: &mut Contents, src: &str, dst: &str, force: bool) -> SlateResult<Vec<String>> {
+    if !contents.contains_key(src) {
+        return Err(SlateError::NotFound(src.to_owned()));
+    }
+    if src == dst {
+        return Ok(vec![]);
+    }
+    if !force && contents.contains_key(dst) {
+        return Err(SlateError::Conflict(dst.to_owned()));
+    }
+
+    let value = contents.remove(src).unwrap();
+    contents.insert(dst.to_owned(), value);
+
+    let mut aliases = Vec::new();
+    for (key, entry) in contents.iter_mut() {
+        if entry.alias.as_ref().map(|target| target == src).unwrap_or(false) {
+            entry.alias = Some(dst.to_owned());
+            aliases.push(key.clone());
+        }
+    }
+    aliases.sort();
+
+    Ok(aliases)
+}
+
+/// Follow the aliases from a key to the key with the value.
+///
+/// It returns the keys found on the way, starting with the given
+/// one and ending with the last one, and the entry of the last one
+/// if it exists. It returns a `SlateError::Cycle` error if the
+/// aliases point to each other.
+pub fn follow(store: &Store, key: &str) -> SlateResult<(Vec<String>, Option<Entry>)> {
+    let mut chain = vec![key.to_owned()];
+
+    loop {
+        let entry = try!(store.get(&chain[chain.len() - 1]));
+        let target = match entry {
+            Some(Entry { alias: Some(ref target), .. }) => target.clone(),
+            _ => return Ok((chain, entry)),
+        };
+
+        let cycle = chain.contains(&target);
+        chain.push(target);
+        if cycle {
+            return Err(SlateError::Cycle(chain));
+        }
+    }
+}
+
+/// Whether a key is a pattern, with `*` or `?` wildcards.
+pub fn is_pattern(key: &str) -> bool {
+    key.contains('*') || key.contains('?')
+}
+
+/// Whether a key matches a pattern, where `*` matches any text and
+/// `?` any single character.
+pub fn matches(pattern: &str, key: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let key: Vec<char> = key.chars().collect();
+    // Position after the last `*` seen, in the pattern and in the key.
+    let mut star: Option<(usize, usize)> = None;
+    let (mut p, mut k) = (0, 0);
+
+    while k < key.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == key[k]) {
+            p += 1;
+            k += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            p += 1;
+            star = Some((p, k));
+        } else if let Some((after, matched)) = star {
+            // Let the last `*` take one more character.
+            p = after;
+            k = matched + 1;
+            star = Some((after, k));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Select the keys that match any of the given keys or patterns,
+/// sorted and without duplicates.
+///
+/// It returns a `SlateError::NotFound` error with the first key
+/// that doesn't exist, or pattern that doesn't match any key.
+pub fn select(keys: &[String], patterns: &[String]) -> SlateResult<Vec<String>> {
+    let mut selected = Vec::new();
+
+    for pattern in patterns {
+        let found: Vec<&String> = keys.iter().filter(|key| matches(pattern, key)).collect();
+        if found.is_empty() {
+            return Err(SlateError::NotFound(pattern.to_owned()));
+        }
+        selected.extend(found.into_iter().cloned());
+    }
+    selected.sort();
+    selected.dedup();
+
+    Ok(selected)
+}
+
+/// Open the store described by the configuration.
+///
+/// When a daemon is serving the store, its socket is set in the
+/// configuration and all the operations go through it.
+pub fn open(config: &Config) -> SlateResult<Box<Store>> {
+    #[cfg(unix)]
+    {
+        if let Some(ref socket) = config.socket {
+            let store = remote::RemoteStore::new(socket).hook_output(config.hook_output.clone());
+            return Ok(Box::new(store));
+        }
+    }
+
+    let store: Box<Store> = match config.backend {
+        Backend::Json => Box::new(json::JsonStore::new(&config.filepath)),
+        Backend::Sqlite => Box::new(try!(sqlite::SqliteStore::open(&config.filepath))),
+    };
+
+    Ok(store)
+}
+
+/// Open a store only to read it, like another store to compare
+/// with. Missing files are not created and SQLite databases are not
+/// upgraded. It never goes through a daemon.
+pub fn open_read_only(config: &Config) -> SlateResult<Box<Store>> {
+    if !config.filepath.is_file() {
+        let message = format!("The store {} doesn't exist", config.filepath.display());
+        return Err(SlateError::IO(io::Error::new(io::ErrorKind::NotFound, message)));
+    }
+
+    let store: Box<Store> = match config.backend {
+        Backend::Json => Box::new(json::JsonStore::new(&config.filepath)),
+        Backend::Sqlite => Box::new(try!(sqlite::SqliteStore::read_only(&config.filepath))),
+    };
+
+    Ok(store)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_encodes_binary_values() {
+        let text = Entry::from_bytes(b"hello".to_vec());
+        assert_eq!(Entry::new("hello"), text);
+
+        let binary = Entry::from_bytes(vec![0, 159, 146, 150]);
+        assert!(binary.is_binary());
+        assert_eq!("AJ+Slg==", binary.value);
+        assert_eq!(Some(vec![0, 159, 146, 150]), binary.bytes());
+    }
+
+    #[test]
+    fn it_matches_patterns() {
+        assert!(matches("redis", "redis"));
+        assert!(matches("redis*", "redis-conf"));
+        assert!(matches("*conf", "redis-conf"));
+        assert!(matches("k8s/*/pods", "k8s/prod/pods"));
+        assert!(matches("r?dis", "redis"));
+        assert!(matches("*", ""));
+        assert!(!matches("redis", "redis-conf"));
+        assert!(!matches("*conf", "conf-redis"));
+        assert!(!matches("r?dis", "rdis"));
+    }
+
+    #[test]
+    fn it_selects_keys() {
+        let keys: Vec<String> = vec!["a".to_string(), "ab".to_string(), "b".to_string()];
+        let select = |patterns: &[&str]| {
+            let patterns: Vec<String> = patterns.iter().map(|p| p.to_string()).collect();
+            select(&keys, &patterns)
+        };
+
+        assert_eq!(vec!["a", "ab"], select(&["a*", "a"]).unwrap());
+        assert_eq!(vec!["ab", "b"], select(&["b", "?b"]).unwrap());
+        match select(&["c*"]) {
+            Err(SlateError::NotFound(pattern)) => assert_eq!("c*", pattern),
+            _ => panic!("A pattern without keys must fail"),
+        }
+    }
+}
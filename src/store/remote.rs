@@ -0,0 +1,184 @@
+use serde_json::{self, Value};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use daemon::{Client, Params};
+use errors::SlateError;
+use hooks;
+use results::SlateResult;
+use super::{self as store, Contents, Entry, Store};
+use time;
+
+/// Times an update is tried again when someone else changed the key.
+const UPDATE_ATTEMPTS: usize = 10;
+
+/// Store served by a running daemon.
+///
+/// Every operation is sent to the daemon, which applies it to the
+/// real store.
+pub struct RemoteStore {
+    socket: PathBuf,
+    hook_output: Option<hooks::Output>,
+}
+
+impl RemoteStore {
+    pub fn new(socket: &Path) -> RemoteStore {
+        RemoteStore {
+            socket: socket.to_path_buf(),
+            hook_output: None,
+        }
+    }
+
+    /// Collect the output of the hooks that reject changes in a
+    /// buffer, see `Client::hook_output`.
+    pub fn hook_output(self, output: Option<hooks::Output>) -> RemoteStore {
+        RemoteStore { hook_output: output, ..self }
+    }
+
+    fn call(&self, method: &str, params: Params) -> SlateResult<Value> {
+        let mut client = try!(Client::connect(&self.socket)).hook_output(self.hook_output.clone());
+
+        client.call(method, params)
+    }
+}
+
+impl Store for RemoteStore {
+    fn read(&self) -> SlateResult<Contents> {
+        let contents = try!(self.call("read", Default::default()));
+
+        Ok(try!(serde_json::from_value(contents)))
+    }
+
+    fn write(&self, contents: &Contents) -> SlateResult<()> {
+        let params = Params { contents: Some(contents.clone()), ..Default::default() };
+        try!(self.call("write", params));
+
+        Ok(())
+    }
+
+    fn get(&self, key: &str) -> SlateResult<Option<Entry>> {
+        let params = Params { key: Some(key.to_owned()), ..Default::default() };
+
+        let entry = try!(self.call("raw", params));
+
+        Ok(try!(serde_json::from_value(entry)))
+    }
+
+    fn set(&self, key: &str, entry: &Entry) -> SlateResult<()> {
+        let params = Params {
+            key: Some(key.to_owned()),
+            entry: Some(entry.clone()),
+            ..Default::default()
+        };
+        try!(self.call("set", params));
+
+        Ok(())
+    }
+
+    fn remove(&self, key: &str) -> SlateResult<()> {
+        let params = Params { key: Some(key.to_owned()), ..Default::default() };
+        try!(self.call("remove", params));
+
+        Ok(())
+    }
+
+    fn rename(&self, src: &str, dst: &str, force: bool) -> SlateResult<()> {
+        let params = Params {
+            src: Some(src.to_owned()),
+            dst: Some(dst.to_owned()),
+            force: Some(force),
+            ..Default::default()
+        };
+        try!(self.call("rename", params));
+
+        Ok(())
+    }
+
+    fn commit(&self, contents: &Contents, touched: &HashSet<String>) -> SlateResult<()> {
+        let params = Params {
+            contents: Some(contents.clone()),
+            touched: Some(touched.clone()),
+            ..Default::default()
+        };
+        try!(self.call("commit", params));
+
+        Ok(())
+    }
+
+    /// The new entry is sent with the one it was made from, and the
+    /// daemon saves it under its lock if the key still has that one.
+    /// Otherwise the entry is made again from the current one.
+    fn update(&self,
+              key: &str,
+              f: &mut FnMut(Option<&Entry>) -> SlateResult<Entry>)
+              -> SlateResult<(Option<Entry>, Entry)> {
+        let mut attempts = 1;
+
+        loop {
+            let old = try!(self.get(key));
+            let new = try!(f(old.as_ref()));
+            let params = Params {
+                key: Some(key.to_owned()),
+                entry: Some(new.clone()),
+                expected: Some(old.iter().map(|entry| (key.to_owned(), entry.clone())).collect()),
+                ..Default::default()
+            };
+
+            match self.call("update", params) {
+                Ok(_) => return Ok((old, new)),
+                Err(SlateError::Changed(_)) if attempts < UPDATE_ATTEMPTS => attempts += 1,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// The changes are sent with the entries read before them, and
+    /// the daemon refuses them with a `SlateError::Changed` error if
+    /// somebody else changed those keys in the meantime.
+    fn transact(&self,
+                f: &mut FnMut(&mut Contents) -> SlateResult<HashSet<String>>)
+                -> SlateResult<Contents> {
+        let mut contents = try!(self.read());
+        store::purge(&mut contents, time::now());
+        let previous = contents.clone();
+
+        let touched = try!(f(&mut contents));
+        if touched.is_empty() {
+            return Ok(previous);
+        }
+
+        let select = |contents: &Contents| -> Contents {
+            touched.iter()
+                .filter_map(|key| contents.get(key).map(|entry| (key.clone(), entry.clone())))
+                .collect()
+        };
+        let params = Params {
+            contents: Some(select(&contents)),
+            touched: Some(touched.clone()),
+            expected: Some(select(&previous)),
+            ..Default::default()
+        };
+        try!(self.call("commit", params));
+
+        Ok(previous)
+    }
+
+    fn gc(&self) -> SlateResult<usize> {
+        let removed = try!(self.call("gc", Default::default()));
+
+        Ok(try!(serde_json::from_value(removed)))
+    }
+
+    fn keys(&self) -> SlateResult<Vec<String>> {
+        let keys = try!(self.call("list", Default::default()));
+
+        Ok(try!(serde_json::from_value(keys)))
+    }
+
+    fn search(&self, query: &str) -> SlateResult<Vec<String>> {
+        let params = Params { query: Some(query.to_owned()), ..Default::default() };
+        let keys = try!(self.call("search", params));
+
+        Ok(try!(serde_json::from_value(keys)))
+    }
+}
@@ -0,0 +1,578 @@
+use rusqlite::{self, Connection, OpenFlags, OptionalExtension, Transaction, TransactionBehavior};
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::Duration;
+
+use results::SlateResult;
+use errors::SlateError;
+use time;
+use super::{Contents, Entry, Store};
+
+/// Time to wait for other processes writing to the database.
+const LOCK_TIMEOUT_SECS: u64 = 5;
+
+const SCHEMA: &'static str = "
+CREATE TABLE IF NOT EXISTS entries (
+  key TEXT PRIMARY KEY NOT NULL,
+  value TEXT NOT NULL,
+  expires INTEGER,
+  language TEXT,
+  tags TEXT,
+  description TEXT,
+  encoding TEXT,
+  mode INTEGER,
+  filename TEXT,
+  alias TEXT
+);
+
+CREATE VIRTUAL TABLE IF NOT EXISTS entries_fts
+  USING fts5(value, content='entries', content_rowid='rowid');
+
+CREATE TRIGGER IF NOT EXISTS entries_ai AFTER INSERT ON entries BEGIN
+  INSERT INTO entries_fts(rowid, value) VALUES (new.rowid, new.value);
+END;
+
+CREATE TRIGGER IF NOT EXISTS entries_ad AFTER DELETE ON entries BEGIN
+  INSERT INTO entries_fts(entries_fts, rowid, value) VALUES ('delete', old.rowid, old.value);
+END;
+
+CREATE TRIGGER IF NOT EXISTS entries_au AFTER UPDATE ON entries BEGIN
+  INSERT INTO entries_fts(entries_fts, rowid, value) VALUES ('delete', old.rowid, old.value);
+  INSERT INTO entries_fts(rowid, value) VALUES (new.rowid, new.value);
+END;
+";
+
+/// Columns added after the first version of the schema.
+const COLUMNS: &'static [(&'static str, &'static str)] = &[("expires", "INTEGER"),
+                                                           ("language", "TEXT"),
+                                                           ("tags", "TEXT"),
+                                                           ("description", "TEXT"),
+                                                           ("encoding", "TEXT"),
+                                                           ("mode", "INTEGER"),
+                                                           ("filename", "TEXT"),
+                                                           ("alias", "TEXT")];
+
+/// Columns read to build an `Entry`, see `entry`.
+const ENTRY: &'static str = "value, expires, language, tags, description, encoding, mode, \
+                             filename, alias";
+
+const UPSERT: &'static str = "INSERT INTO entries (key, value, expires, language, tags,
+                                                  description, encoding, mode, filename, alias)
+                              VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+                              ON CONFLICT(key) DO UPDATE
+                              SET value = excluded.value, expires = excluded.expires,
+                                  language = excluded.language, tags = excluded.tags,
+                                  description = excluded.description,
+                                  encoding = excluded.encoding, mode = excluded.mode,
+                                  filename = excluded.filename, alias = excluded.alias";
+
+/// Store that saves the contents in an embedded SQLite database.
+///
+/// Keys are indexed and values are available for full-text search.
+/// Every write runs inside a transaction.
+pub struct SqliteStore {
+    conn: Connection,
+}
+
+impl SqliteStore {
+    /// Open the database, creating it and its tables if needed.
+    pub fn open(filepath: &Path) -> SlateResult<SqliteStore> {
+        let conn = try!(Connection::open(filepath));
+        try!(conn.busy_timeout(Duration::from_secs(LOCK_TIMEOUT_SECS)));
+        try!(conn.execute_batch(SCHEMA));
+
+        let store = SqliteStore { conn: conn };
+        try!(store.upgrade());
+
+        Ok(store)
+    }
+
+    /// Open a database only to read it, without creating its tables
+    /// or upgrading it. The columns missing in databases of older
+    /// versions are read as empty, and writes fail.
+    pub fn read_only(filepath: &Path) -> SlateResult<SqliteStore> {
+        let conn = try!(Connection::open_with_flags(filepath, OpenFlags::SQLITE_OPEN_READ_ONLY));
+        try!(conn.busy_timeout(Duration::from_secs(LOCK_TIMEOUT_SECS)));
+
+        let missing = try!(missing_columns(filepath));
+        if !missing.is_empty() {
+            // A temporary view hides the table, with nulls in the
+            // missing columns, as the temporary schema is not saved.
+            let columns: Vec<String> = COLUMNS.iter()
+                .map(|&(name, _)| if missing.contains(&name) {
+                    format!("NULL AS {}", name)
+                } else {
+                    name.to_owned()
+                })
+                .collect();
+            try!(conn.execute_batch(&format!("CREATE TEMP VIEW entries AS \
+                                              SELECT rowid, key, value, {} FROM main.entries",
+                                             columns.join(", "))));
+        }
+
+        Ok(SqliteStore { conn: conn })
+    }
+
+    /// Add the columns missing in databases of older versions.
+    fn upgrade(&self) -> SlateResult<()> {
+        let existing = try!(self.select_keys("SELECT name FROM pragma_table_info('entries')", &[]));
+
+        for &(name, kind) in COLUMNS {
+            if !existing.iter().any(|column| column == name) {
+                try!(self.conn
+                    .execute_batch(&format!("ALTER TABLE entries ADD COLUMN {} {}", name, kind)));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn select_keys(&self, sql: &str, params: &[&str]) -> SlateResult<Vec<String>> {
+        let mut stmt = try!(self.conn.prepare(sql));
+        let rows = try!(stmt.query_map(rusqlite::params_from_iter(params), |row| row.get(0)));
+
+        let mut keys = Vec::new();
+        for row in rows {
+            keys.push(try!(row));
+        }
+
+        Ok(keys)
+    }
+}
+
+/// Get the columns missing in a database of an older version,
+/// without changing it. They are added when the store is opened.
+pub fn missing_columns(filepath: &Path) -> SlateResult<Vec<&'static str>> {
+    let conn = try!(Connection::open_with_flags(filepath, OpenFlags::SQLITE_OPEN_READ_ONLY));
+    let mut stmt = try!(conn.prepare("SELECT name FROM pragma_table_info('entries')"));
+    let rows = try!(stmt.query_map([], |row| row.get(0)));
+
+    let mut existing: Vec<String> = Vec::new();
+    for row in rows {
+        existing.push(try!(row));
+    }
+    // Databases without the table get it with every column.
+    if existing.is_empty() {
+        return Ok(vec![]);
+    }
+
+    Ok(COLUMNS.iter()
+        .map(|&(name, _)| name)
+        .filter(|name| !existing.iter().any(|column| column == name))
+        .collect())
+}
+
+/// Condition of the entries that have not expired.
+fn alive() -> String {
+    format!("(expires IS NULL OR expires > {})", time::now())
+}
+
+/// Remove the expired entries, and get how many there were.
+fn purge(conn: &Connection) -> SlateResult<usize> {
+    let removed = try!(conn.execute(&format!("DELETE FROM entries WHERE NOT {}", alive()), []));
+
+    Ok(removed)
+}
+
+fn upsert(conn: &Connection, key: &str, entry: &Entry) -> SlateResult<()> {
+    // Tags can't have commas, see `set`, so they are saved joined by them.
+    let tags = if entry.tags.is_empty() { None } else { Some(entry.tags.join(",")) };
+
+    try!(conn.execute(UPSERT,
+                      (key,
+                       &entry.value,
+                       entry.expires.map(|e| e as i64),
+                       &entry.language,
+                       tags,
+                       &entry.description,
+                       &entry.encoding,
+                       entry.mode,
+                       &entry.filename,
+                       &entry.alias)));
+
+    Ok(())
+}
+
+/// Save the touched keys of the contents, removing the ones that
+/// are not in them.
+fn save(conn: &Connection, contents: &Contents, touched: &HashSet<String>) -> SlateResult<()> {
+    try!(purge(conn));
+    for key in touched {
+        match contents.get(key) {
+            Some(entry) => try!(upsert(conn, key, entry)),
+            None => {
+                try!(conn.execute("DELETE FROM entries WHERE key = ?1", &[key]));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Build an entry from the `ENTRY` columns of a row, starting at
+/// the given index.
+fn entry(row: &rusqlite::Row, start: usize) -> rusqlite::Result<Entry> {
+    let expires: Option<i64> = try!(row.get(start + 1));
+    let tags: Option<String> = try!(row.get(start + 3));
+
+    Ok(Entry {
+        value: try!(row.get(start)),
+        expires: expires.map(|e| e as u64),
+        language: try!(row.get(start + 2)),
+        tags: tags.map(|tags| tags.split(',').map(|tag| tag.to_owned()).collect())
+            .unwrap_or_default(),
+        description: try!(row.get(start + 4)),
+        encoding: try!(row.get(start + 5)),
+        mode: try!(row.get(start + 6)),
+        filename: try!(row.get(start + 7)),
+        alias: try!(row.get(start + 8)),
+    })
+}
+
+impl Store for SqliteStore {
+    fn read(&self) -> SlateResult<Contents> {
+        let mut stmt = try!(self.conn.prepare(&format!("SELECT key, {} FROM entries", ENTRY)));
+        let rows = try!(stmt.query_map([], |row| Ok((try!(row.get(0)), try!(entry(row, 1))))));
+
+        let mut contents = Contents::new();
+        for row in rows {
+            let (key, entry): (String, Entry) = try!(row);
+            contents.insert(key, entry);
+        }
+
+        Ok(contents)
+    }
+
+    fn write(&self, contents: &Contents) -> SlateResult<()> {
+        let tx = try!(self.conn.unchecked_transaction());
+
+        try!(tx.execute("DELETE FROM entries", []));
+        for (key, entry) in contents {
+            try!(upsert(&tx, key, entry));
+        }
+
+        try!(tx.commit());
+
+        Ok(())
+    }
+
+    /// Only the touched keys are written, inside a single
+    /// database transaction.
+    fn commit(&self, contents: &Contents, touched: &HashSet<String>) -> SlateResult<()> {
+        let tx = try!(self.conn.unchecked_transaction());
+
+        try!(save(&tx, contents, touched));
+
+        try!(tx.commit());
+
+        Ok(())
+    }
+
+    /// The database is locked for writing from the start, like in
+    /// `update`.
+    fn transact(&self,
+                f: &mut FnMut(&mut Contents) -> SlateResult<HashSet<String>>)
+                -> SlateResult<Contents> {
+        let tx = try!(Transaction::new_unchecked(&self.conn, TransactionBehavior::Immediate));
+        try!(purge(&tx));
+
+        let mut contents = try!(self.read());
+        let previous = contents.clone();
+        let touched = try!(f(&mut contents));
+        try!(save(&tx, &contents, &touched));
+
+        try!(tx.commit());
+
+        Ok(previous)
+    }
+
+    fn get(&self, key: &str) -> SlateResult<Option<Entry>> {
+        let sql = format!("SELECT {} FROM entries WHERE key = ?1 AND {}", ENTRY, alive());
+        let found = try!(self.conn.query_row(&sql, &[key], |row| entry(row, 0)).optional());
+
+        Ok(found)
+    }
+
+    fn set(&self, key: &str, entry: &Entry) -> SlateResult<()> {
+        let tx = try!(self.conn.unchecked_transaction());
+
+        try!(purge(&tx));
+        try!(upsert(&tx, key, entry));
+
+        try!(tx.commit());
+
+        Ok(())
+    }
+
+    fn remove(&self, key: &str) -> SlateResult<()> {
+        let tx = try!(self.conn.unchecked_transaction());
+
+        try!(purge(&tx));
+        try!(tx.execute("DELETE FROM entries WHERE key = ?1", &[key]));
+
+        try!(tx.commit());
+
+        Ok(())
+    }
+
+    fn rename(&self, src: &str, dst: &str, force: bool) -> SlateResult<()> {
+        let tx = try!(self.conn.unchecked_transaction());
+        try!(purge(&tx));
+
+        let exists = |key: &str| {
+            tx.query_row("SELECT 1 FROM entries WHERE key = ?1", &[key], |_| Ok(()))
+                .optional()
+                .map(|found| found.is_some())
+        };
+
+        if !try!(exists(src)) {
+            return Err(SlateError::NotFound(src.to_owned()));
+        }
+        if src == dst {
+            return Ok(());
+        }
+        if try!(exists(dst)) {
+            if !force {
+                return Err(SlateError::Conflict(dst.to_owned()));
+            }
+            try!(tx.execute("DELETE FROM entries WHERE key = ?1", &[dst]));
+        }
+
+        try!(tx.execute("UPDATE entries SET key = ?2 WHERE key = ?1", &[src, dst]));
+        try!(tx.execute("UPDATE entries SET alias = ?2 WHERE alias = ?1", &[src, dst]));
+
+        try!(tx.commit());
+
+        Ok(())
+    }
+
+    /// The database is locked for writing from the start, so no
+    /// other process can change the entry in the meantime.
+    fn update(&self,
+              key: &str,
+              f: &mut FnMut(Option<&Entry>) -> SlateResult<Entry>)
+              -> SlateResult<(Option<Entry>, Entry)> {
+        let tx = try!(Transaction::new_unchecked(&self.conn, TransactionBehavior::Immediate));
+        try!(purge(&tx));
+
+        let sql = format!("SELECT {} FROM entries WHERE key = ?1", ENTRY);
+        let old = try!(tx.query_row(&sql, &[key], |row| entry(row, 0)).optional());
+        let new = try!(f(old.as_ref()));
+        try!(upsert(&tx, key, &new));
+
+        try!(tx.commit());
+
+        Ok((old, new))
+    }
+
+    fn gc(&self) -> SlateResult<usize> {
+        purge(&self.conn)
+    }
+
+    fn keys(&self) -> SlateResult<Vec<String>> {
+        self.select_keys(&format!("SELECT key FROM entries WHERE {} ORDER BY key", alive()),
+                         &[])
+    }
+
+    /// Search using the full-text index.
+    ///
+    /// Every word of the query is matched as a prefix of the words
+    /// in the values.
+    fn search(&self, query: &str) -> SlateResult<Vec<String>> {
+        let pattern: Vec<String> = query.split_whitespace()
+            .map(|word| format!("\"{}\"*", word.replace("\"", "\"\"")))
+            .collect();
+
+        if pattern.is_empty() {
+            return self.keys();
+        }
+
+        let sql = format!("SELECT entries.key FROM entries_fts
+                           JOIN entries ON entries.rowid = entries_fts.rowid
+                           WHERE entries_fts MATCH ?1 AND {}
+                           ORDER BY entries.key",
+                          alive());
+        self.select_keys(&sql, &[&pattern.join(" ")])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::path::PathBuf;
+    use rand::{thread_rng, Rng};
+    use store;
+
+    fn temp_path() -> PathBuf {
+        let random_name: String = thread_rng().gen_ascii_chars().take(10).collect();
+        let mut temp = env::temp_dir();
+        temp.push(random_name + ".db");
+
+        temp
+    }
+
+    #[test]
+    fn it_sets_and_gets_keys() {
+        let store = SqliteStore::open(&temp_path()).unwrap();
+
+        store.set("foo", &Entry::new("bar")).unwrap();
+        store.set("foo", &Entry::new("baz")).unwrap();
+
+        assert_eq!(Some(Entry::new("baz")), store.get("foo").unwrap());
+        assert_eq!(None, store.get("missing").unwrap());
+    }
+
+    #[test]
+    fn it_reads_old_databases_without_upgrading_them() {
+        let path = temp_path();
+        {
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch("CREATE TABLE entries (key TEXT PRIMARY KEY NOT NULL, value TEXT NOT NULL);
+                                INSERT INTO entries VALUES ('foo', 'bar');")
+                .unwrap();
+        }
+
+        let store = SqliteStore::read_only(&path).unwrap();
+
+        assert_eq!(Some(Entry::new("bar")), store.get("foo").unwrap());
+        assert!(store.set("foo", &Entry::new("baz")).is_err());
+        assert_eq!(COLUMNS.len(), missing_columns(&path).unwrap().len());
+        assert!(SqliteStore::read_only(&temp_path()).is_err());
+    }
+
+    #[test]
+    fn it_lists_sorted_keys() {
+        let store = SqliteStore::open(&temp_path()).unwrap();
+
+        store.set("b", &Entry::new("2")).unwrap();
+        store.set("a", &Entry::new("1")).unwrap();
+
+        assert_eq!(vec!["a", "b"], store.keys().unwrap());
+    }
+
+    #[test]
+    fn it_searches_values() {
+        let store = SqliteStore::open(&temp_path()).unwrap();
+
+        store.set("redis", &Entry::new("daemonize yes\ndir /tmp")).unwrap();
+        store.set("nginx", &Entry::new("daemon off;")).unwrap();
+        store.set("other", &Entry::new("nothing here")).unwrap();
+        store.remove("other").unwrap();
+
+        assert_eq!(vec!["nginx", "redis"], store.search("daemon").unwrap());
+        assert_eq!(vec!["redis"], store.search("DIR daemon").unwrap());
+        assert!(store.search("nothing").unwrap().is_empty());
+    }
+
+    #[test]
+    fn it_renames_keys() {
+        let store = SqliteStore::open(&temp_path()).unwrap();
+
+        store.set("a", &Entry::new("1")).unwrap();
+        store.set("b", &Entry::new("2")).unwrap();
+
+        match store.rename("a", "b", false) {
+            Err(SlateError::Conflict(key)) => assert_eq!("b", key),
+            other => panic!("Expected a conflict, got {:?}", other),
+        }
+        match store.rename("missing", "c", false) {
+            Err(SlateError::NotFound(key)) => assert_eq!("missing", key),
+            other => panic!("Expected a missing key, got {:?}", other),
+        }
+
+        store.rename("a", "b", true).unwrap();
+
+        assert_eq!(vec!["b"], store.keys().unwrap());
+        assert_eq!(vec!["b"], store.search("1").unwrap());
+    }
+
+    #[test]
+    fn it_commits_touched_keys() {
+        let store = SqliteStore::open(&temp_path()).unwrap();
+        let mut contents = Contents::new();
+        let mut touched = HashSet::new();
+
+        store.set("a", &Entry::new("1")).unwrap();
+        store.set("b", &Entry::new("2")).unwrap();
+
+        contents.insert("c".to_string(), Entry::new("3"));
+        touched.insert("a".to_string());
+        touched.insert("c".to_string());
+        store.commit(&contents, &touched).unwrap();
+
+        assert_eq!(vec!["b", "c"], store.keys().unwrap());
+    }
+
+    #[test]
+    fn it_replaces_all_contents() {
+        let store = SqliteStore::open(&temp_path()).unwrap();
+        let mut contents = Contents::new();
+        contents.insert("new".to_string(), Entry::new("value"));
+
+        store.set("old", &Entry::new("value")).unwrap();
+        store.write(&contents).unwrap();
+
+        assert_eq!(contents, store.read().unwrap());
+        assert_eq!(vec!["new"], store.search("value").unwrap());
+    }
+
+    #[test]
+    fn it_hides_and_purges_expired_entries() {
+        let store = SqliteStore::open(&temp_path()).unwrap();
+
+        store.set("old", &Entry { expires: Some(1), ..Entry::new("1") }).unwrap();
+        store.set("new", &Entry { expires: Some(u64::max_value() / 2), ..Entry::new("2") })
+            .unwrap();
+
+        assert_eq!(None, store.get("old").unwrap());
+        assert_eq!(vec!["new"], store.keys().unwrap());
+        assert_eq!(Some(u64::max_value() / 2), store.get("new").unwrap().unwrap().expires);
+        assert_eq!(0, store.gc().unwrap());
+        assert_eq!(1, store.read().unwrap().len());
+    }
+
+    #[test]
+    fn it_saves_the_attributes_of_entries() {
+        let store = SqliteStore::open(&temp_path()).unwrap();
+        let entry = Entry {
+            language: Some("sql".to_string()),
+            tags: vec!["db".to_string(), "users".to_string()],
+            description: Some("All the users".to_string()),
+            ..Entry::new("select 1")
+        };
+
+        store.set("query", &entry).unwrap();
+
+        assert_eq!(Some(entry.clone()), store.get("query").unwrap());
+        assert_eq!(Some(&entry), store.read().unwrap().get("query"));
+    }
+
+    #[test]
+    fn it_saves_binary_entries() {
+        let store = SqliteStore::open(&temp_path()).unwrap();
+        let entry = Entry {
+            mode: Some(0o600),
+            filename: Some("id_rsa".to_string()),
+            ..Entry::from_bytes(vec![0, 255, 10])
+        };
+
+        store.set("key", &entry).unwrap();
+
+        assert_eq!(Some(entry), store.get("key").unwrap());
+    }
+
+    #[test]
+    fn it_saves_aliases() {
+        let store = SqliteStore::open(&temp_path()).unwrap();
+        store.set("pods", &Entry::new("kubectl get pods")).unwrap();
+        store.set("p", &Entry::alias("pods")).unwrap();
+
+        assert_eq!(Some(Entry::alias("pods")), store.get("p").unwrap());
+        assert_eq!((vec!["p".to_string(), "pods".to_string()], Some(Entry::new("kubectl get pods"))),
+                   store::follow(&store, "p").unwrap());
+
+        store.rename("pods", "k8s/pods", false).unwrap();
+        assert_eq!(Some(Entry::alias("k8s/pods")), store.get("p").unwrap());
+    }
+}
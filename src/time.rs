@@ -0,0 +1,198 @@
+//! Dates and durations, as seconds since the Unix epoch.
+//!
+//! All dates are in UTC.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MINUTE: u64 = 60;
+const HOUR: u64 = 60 * MINUTE;
+const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+
+/// Current time.
+pub fn now() -> u64 {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(elapsed) => elapsed.as_secs(),
+        Err(_) => 0,
+    }
+}
+
+/// Parse a duration like `90s`, `15m`, `2h`, `1d`, `2w` or a
+/// combination of them, like `1h30m`.
+///
+/// # Example
+///
+/// ```rust
+/// use slate::time::parse_duration;
+///
+/// assert_eq!(Ok(5400), parse_duration("1h30m"));
+/// assert!(parse_duration("soon").is_err());
+/// ```
+pub fn parse_duration(s: &str) -> Result<u64, String> {
+    let invalid = || format!("Invalid duration: {}", s);
+    let mut total: u64 = 0;
+    let mut number = String::new();
+
+    for c in s.trim().chars() {
+        if c.is_digit(10) {
+            number.push(c);
+            continue;
+        }
+
+        let unit = match c {
+            's' => 1,
+            'm' => MINUTE,
+            'h' => HOUR,
+            'd' => DAY,
+            'w' => WEEK,
+            _ => return Err(invalid()),
+        };
+        let amount: u64 = try!(number.parse().map_err(|_| invalid()));
+        total = try!(amount.checked_mul(unit)
+            .and_then(|secs| total.checked_add(secs))
+            .ok_or_else(&invalid));
+        number.clear();
+    }
+
+    if !number.is_empty() || total == 0 {
+        return Err(invalid());
+    }
+
+    Ok(total)
+}
+
+/// Parse a date like `2024-05-01`, `2024-05-01 18:30`,
+/// `2024-05-01T18:30:00` or `2024-05-01T18:30:00Z`.
+///
+/// # Example
+///
+/// ```rust
+/// use slate::time::parse_date;
+///
+/// assert_eq!(Ok(1714521600), parse_date("2024-05-01"));
+/// assert_eq!(Ok(1714588200), parse_date("2024-05-01 18:30"));
+/// ```
+pub fn parse_date(s: &str) -> Result<u64, String> {
+    let invalid = || format!("Invalid date: {}", s);
+    let s = s.trim().trim_right_matches('Z');
+    let (date, time) = match s.find(|c| c == 'T' || c == ' ') {
+        Some(index) => (&s[..index], &s[index + 1..]),
+        None => (s, ""),
+    };
+
+    let numbers = |part: &str, separator: char| -> Result<Vec<u64>, String> {
+        part.split(separator).map(|n| n.parse().map_err(|_| invalid())).collect()
+    };
+
+    let date = try!(numbers(date, '-'));
+    if date.len() != 3 || date[1] < 1 || date[1] > 12 || date[2] < 1 || date[2] > 31 ||
+       date[0] < 1970 {
+        return Err(invalid());
+    }
+
+    let time = if time.is_empty() { vec![0, 0] } else { try!(numbers(time, ':')) };
+    if time.len() < 2 || time.len() > 3 || time[0] > 23 || time[1] > 59 ||
+       time.get(2).map(|&secs| secs > 59).unwrap_or(false) {
+        return Err(invalid());
+    }
+
+    let days = days_from_civil(date[0], date[1], date[2]);
+
+    Ok(days * DAY + time[0] * HOUR + time[1] * MINUTE + time.get(2).cloned().unwrap_or(0))
+}
+
+/// Format a date like `2024-05-01 18:30:00 UTC`.
+pub fn format_date(timestamp: u64) -> String {
+    let (year, month, day) = civil_from_days(timestamp / DAY);
+    let secs = timestamp % DAY;
+
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+            year,
+            month,
+            day,
+            secs / HOUR,
+            secs % HOUR / MINUTE,
+            secs % MINUTE)
+}
+
+/// Format a duration with its two biggest units, like `2h 15m`.
+pub fn format_duration(secs: u64) -> String {
+    let units = [(DAY, "d"), (HOUR, "h"), (MINUTE, "m"), (1, "s")];
+    let parts: Vec<String> = units.iter()
+        .scan(secs, |rest, &(size, name)| {
+            let amount = *rest / size;
+            *rest %= size;
+            Some((amount, name))
+        })
+        .filter(|&(amount, _)| amount > 0)
+        .take(2)
+        .map(|(amount, name)| format!("{}{}", amount, name))
+        .collect();
+
+    if parts.is_empty() { "0s".to_string() } else { parts.join(" ") }
+}
+
+/// Days since the Unix epoch of a date in the Gregorian calendar.
+fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year - era * 400;
+    let month_index = (month + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146097 + day_of_era - 719468
+}
+
+/// Date in the Gregorian calendar of some days since the Unix epoch.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let days = days + 719468;
+    let era = days / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 -
+                       day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_durations() {
+        assert_eq!(Ok(90), parse_duration("90s"));
+        assert_eq!(Ok(2 * HOUR), parse_duration("2h"));
+        assert_eq!(Ok(WEEK + DAY), parse_duration("1w1d"));
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("2").is_err());
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("2y").is_err());
+    }
+
+    #[test]
+    fn it_parses_and_formats_dates() {
+        assert_eq!(Ok(0), parse_date("1970-01-01"));
+        assert_eq!(Ok(951782400), parse_date("2000-02-29"));
+        assert_eq!(Ok(1714588245), parse_date("2024-05-01T18:30:45Z"));
+        assert!(parse_date("2024-13-01").is_err());
+        assert!(parse_date("tomorrow").is_err());
+        assert!(parse_date("2024-05-01 25:00").is_err());
+
+        assert_eq!("2000-02-29 00:00:00 UTC", format_date(951782400));
+        assert_eq!("2024-05-01 18:30:45 UTC", format_date(1714588245));
+    }
+
+    #[test]
+    fn it_formats_durations() {
+        assert_eq!("0s", format_duration(0));
+        assert_eq!("45s", format_duration(45));
+        assert_eq!("2h 15m", format_duration(2 * HOUR + 15 * MINUTE + 10));
+        assert_eq!("3d 1h", format_duration(3 * DAY + HOUR));
+    }
+}
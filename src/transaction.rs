@@ -0,0 +1,166 @@
+use std::collections::HashSet;
+
+use errors::SlateError;
+use hooks::{Event, Hook};
+use results::SlateResult;
+use store::{self, Contents, Entry};
+
+/// A change made in a transaction, kept to run its hooks when the
+/// transaction is saved.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Operation {
+    pub key: String,
+    /// The new name of the key, when renamed.
+    pub new_key: Option<String>,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+}
+
+impl Operation {
+    /// The change, as seen by the hooks.
+    pub fn event(&self) -> Event {
+        Event {
+            key: &self.key,
+            new_key: self.new_key.as_ref().map(|key| key.as_str()),
+            old_value: self.old_value.as_ref().map(|value| value.as_str()),
+            new_value: self.new_value.as_ref().map(|value| value.as_str()),
+        }
+    }
+
+    /// The hook run before the change, like with the methods of
+    /// `Slate`. Renames have none.
+    pub fn pre_hook(&self) -> Option<Hook> {
+        match (&self.new_key, &self.new_value) {
+            (&Some(_), _) => None,
+            (&None, &Some(_)) => Some(Hook::PreSet),
+            (&None, &None) => Some(Hook::PreRemove),
+        }
+    }
+
+    /// The hook run after the change.
+    pub fn post_hook(&self) -> Hook {
+        match (&self.new_key, &self.new_value) {
+            (&Some(_), _) => Hook::PostRename,
+            (&None, &Some(_)) => Hook::PostSet,
+            (&None, &None) => Hook::PostRemove,
+        }
+    }
+}
+
+/// A set of changes applied in memory over the contents of a Slate.
+///
+/// Transactions are created with `Slate::transaction`. The contents
+/// are loaded once, and every change is saved at the same time
+/// when the transaction is committed.
+pub struct Transaction {
+    contents: Contents,
+    touched: HashSet<String>,
+    operations: Vec<Operation>,
+}
+
+impl Transaction {
+    pub fn new(contents: Contents) -> Transaction {
+        Transaction {
+            contents: contents,
+            touched: HashSet::new(),
+            operations: Vec::new(),
+        }
+    }
+
+    /// Set a key with its value.
+    pub fn set(&mut self, key: &String, value: &String) {
+        let old = self.contents.insert(key.to_owned(), Entry::new(value));
+        self.touched.insert(key.to_owned());
+        self.operations.push(Operation {
+            key: key.to_owned(),
+            new_key: None,
+            old_value: old.map(|entry| entry.value),
+            new_value: Some(value.to_owned()),
+        });
+    }
+
+    /// Set a key with its value and attributes.
+    pub fn set_entry(&mut self, key: &String, entry: &Entry) {
+        let old = self.contents.insert(key.to_owned(), entry.clone());
+        self.touched.insert(key.to_owned());
+        self.operations.push(Operation {
+            key: key.to_owned(),
+            new_key: None,
+            old_value: old.map(|entry| entry.value),
+            new_value: Some(entry.value.clone()),
+        });
+    }
+
+    /// Get the entry of a key, if it exists.
+    pub fn entry(&self, key: &String) -> Option<&Entry> {
+        self.contents.get(key)
+    }
+
+    /// Get the value of a key.
+    ///
+    /// It returns a `SlateError::NotFound` error if the key
+    /// doesn't exist.
+    pub fn get(&self, key: &String) -> SlateResult<String> {
+        match self.contents.get(key) {
+            Some(entry) => Ok(entry.value.to_owned()),
+            None => Err(SlateError::NotFound(key.to_owned())),
+        }
+    }
+
+    /// Remove completely a key with its value.
+    pub fn remove(&mut self, key: &String) {
+        let old = self.contents.remove(key);
+        self.touched.insert(key.to_owned());
+        self.operations.push(Operation {
+            key: key.to_owned(),
+            new_key: None,
+            old_value: old.map(|entry| entry.value),
+            new_value: None,
+        });
+    }
+
+    /// Remove all keys.
+    pub fn clear(&mut self) {
+        let keys: Vec<String> = self.contents.keys().cloned().collect();
+        for key in keys {
+            self.remove(&key);
+        }
+    }
+
+    /// Rename a key.
+    ///
+    /// It follows the same rules as `Slate::rename`.
+    pub fn rename(&mut self, src: &String, dts: &String, force: bool) -> SlateResult<()> {
+        let aliases = try!(store::rename_key(&mut self.contents, src, dts, force));
+        self.touched.insert(src.to_owned());
+        self.touched.insert(dts.to_owned());
+        self.touched.extend(aliases);
+        let value = self.contents.get(dts).map(|entry| entry.value.clone());
+        self.operations.push(Operation {
+            key: src.to_owned(),
+            new_key: Some(dts.to_owned()),
+            old_value: value.clone(),
+            new_value: value,
+        });
+
+        Ok(())
+    }
+
+    /// Get a list of all keys.
+    pub fn list(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self.contents.keys().cloned().collect();
+        keys.sort();
+
+        keys
+    }
+
+    /// Get the changes made, in order.
+    pub fn operations(&self) -> &[Operation] {
+        &self.operations
+    }
+
+    /// Get the final contents and the keys that changed.
+    pub fn into_parts(self) -> (Contents, HashSet<String>) {
+        (self.contents, self.touched)
+    }
+}
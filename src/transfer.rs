@@ -0,0 +1,225 @@
+//! Copy and move keys between stores.
+//!
+//! The entries keep their attributes, and their history and usage
+//! go with them to the sidecars of the destination. Each store is
+//! read and written once, so a transfer between profiles or
+//! backends is as cheap as a single change.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use Slate;
+use config::Config;
+use errors::SlateError;
+use history;
+use hooks::{self, Event, Hook};
+use results::SlateResult;
+use store::{self, Contents, Entry};
+use time;
+use usage;
+
+/// What happens to the keys in the source store.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    /// The keys are kept.
+    Copy,
+    /// The keys are removed once they are saved in the destination.
+    Move,
+}
+
+/// Result of a transfer.
+#[derive(Debug, Default, PartialEq)]
+pub struct Transfer {
+    /// Keys saved in the destination.
+    pub keys: Vec<String>,
+    /// Keys that already existed in the destination.
+    pub conflicts: Vec<String>,
+}
+
+/// Copy or move some keys to another store.
+///
+/// It returns a `SlateError::NotFound` error if any of the keys
+/// doesn't exist. Keys that already exist in the destination are
+/// conflicts: they are overwritten if `force` is `true`, otherwise
+/// nothing is changed and they are returned in `conflicts`, so they
+/// can be solved first.
+///
+/// The `pre-set` and `post-set` hooks of the destination run for
+/// every key, and with `Mode::Move` the `pre-remove` and
+/// `post-remove` hooks of the source too. A failing pre hook
+/// cancels the whole transfer. It returns an error if both stores
+/// are the same file, where moving would remove the keys.
+///
+/// The stores are written with plain commits, even when a daemon
+/// serves them, so the hooks and the history of both stores are run
+/// and kept by this process.
+///
+/// # Example
+///
+/// ```rust
+/// use slate::config::Config;
+/// use slate::transfer::{self, Mode};
+/// use slate::Slate;
+/// use std::env;
+///
+/// let mut temp = env::temp_dir();
+/// temp.push(".slate");
+/// let mut work = env::temp_dir();
+/// work.push(".slate-work");
+///
+/// let config = Config { filepath: temp, ..Default::default() };
+/// let other = Config { filepath: work, profile: "work".to_string(), ..Default::default() };
+/// let (source, destination): (Slate, Slate) = (From::from(&config), From::from(&other));
+///
+/// match transfer::transfer(&source, &destination, &["vpn".to_string()], Mode::Move, false) {
+///   Ok(ref result) if !result.conflicts.is_empty() => println!("Already there"),
+///   Ok(_) => println!("Moved"),
+///   Err(e) => println!("{}", e),
+/// };
+/// ```
+pub fn transfer(source: &Slate,
+                destination: &Slate,
+                keys: &[String],
+                mode: Mode,
+                force: bool)
+                -> SlateResult<Transfer> {
+    if same_file(&source.config.filepath, &destination.config.filepath) {
+        let message = "The destination is the same store as the source";
+        return Err(SlateError::IO(io::Error::new(io::ErrorKind::InvalidInput, message)));
+    }
+
+    let from = try!(source.store());
+    let to = try!(destination.store());
+    let (source, destination) = (local(source.config), local(destination.config));
+    let now = time::now();
+
+    let mut contents = try!(from.read());
+    let expired = store::purge(&mut contents, now);
+    for key in keys {
+        if !contents.contains_key(key) {
+            return Err(SlateError::NotFound(key.to_owned()));
+        }
+    }
+
+    let mut target = try!(to.read());
+    let mut touched: HashSet<String> = store::purge(&mut target, now).into_iter().collect();
+    let conflicts: Vec<String> = keys.iter().filter(|key| target.contains_key(*key)).cloned().collect();
+    if !conflicts.is_empty() && !force {
+        return Ok(Transfer { keys: vec![], conflicts: conflicts });
+    }
+
+    // The entries before and after the transfer, for the hooks.
+    let old: Contents = keys.iter()
+        .filter_map(|key| target.get(key).map(|entry| (key.to_owned(), entry.clone())))
+        .collect();
+    let new: Contents = keys.iter().map(|key| (key.to_owned(), contents[key].clone())).collect();
+    for key in keys {
+        let set = event(key, old.get(key), new.get(key));
+        try!(hooks::run(&destination, Hook::PreSet, &set));
+        if mode == Mode::Move {
+            try!(hooks::run(&source, Hook::PreRemove, &event(key, new.get(key), None)));
+        }
+    }
+
+    for key in keys {
+        target.insert(key.to_owned(), new[key].clone());
+        touched.insert(key.to_owned());
+    }
+    try!(to.commit(&target, &touched));
+
+    let revisions = history::read(&source);
+    history::update(&destination, |history| {
+        for key in keys {
+            history.insert(key.to_owned(),
+                           history::revisions(&revisions, key, &new[key].value));
+        }
+    });
+    let stats = usage::read(&source);
+    usage::update(&destination, |destination| {
+        for key in keys {
+            match mode {
+                Mode::Move => {
+                    let mut usage = stats.get(key).cloned().unwrap_or_default();
+                    usage.updated = Some(now);
+                    destination.insert(key.to_owned(), usage);
+                }
+                Mode::Copy => {
+                    destination.remove(key);
+                    usage::changed(destination, key, true, now);
+                }
+            }
+        }
+    });
+
+    if mode == Mode::Move {
+        let mut touched: HashSet<String> = expired.into_iter().collect();
+        for key in keys {
+            contents.remove(key);
+            touched.insert(key.to_owned());
+        }
+        try!(from.commit(&contents, &touched));
+
+        history::update(&source, |history| {
+            for key in keys {
+                history.remove(key);
+            }
+        });
+        usage::update(&source, |stats| {
+            for key in keys {
+                stats.remove(key);
+            }
+        });
+    }
+
+    for key in keys {
+        let set = event(key, old.get(key), new.get(key));
+        try!(hooks::run(&destination, Hook::PostSet, &set));
+        if mode == Mode::Move {
+            try!(hooks::run(&source, Hook::PostRemove, &event(key, new.get(key), None)));
+        }
+    }
+
+    Ok(Transfer { keys: keys.to_vec(), conflicts: conflicts })
+}
+
+/// Whether two paths are the same store file, even if they are
+/// written differently, like `./slate.json` and `/tmp/slate.json`.
+///
+/// Files that don't exist yet are compared through their directory.
+pub fn same_file(a: &Path, b: &Path) -> bool {
+    a == b || canonical(a) == canonical(b)
+}
+
+fn canonical(path: &Path) -> Option<PathBuf> {
+    if let Ok(path) = fs::canonicalize(path) {
+        return Some(path);
+    }
+
+    let parent = match path.parent() {
+        Some(parent) if parent.as_os_str().is_empty() => Path::new("."),
+        Some(parent) => parent,
+        None => return None,
+    };
+    match (fs::canonicalize(parent), path.file_name()) {
+        (Ok(dir), Some(name)) => Some(dir.join(name)),
+        _ => None,
+    }
+}
+
+/// A copy of a configuration that runs the hooks and keeps the
+/// history itself, instead of leaving them to a daemon.
+fn local(config: &Config) -> Config {
+    Config { socket: None, ..config.clone() }
+}
+
+/// The change of a key, as seen by the hooks.
+fn event<'a>(key: &'a str, old: Option<&'a Entry>, new: Option<&'a Entry>) -> Event<'a> {
+    Event {
+        key: key,
+        old_value: old.map(|entry| entry.value.as_str()),
+        new_value: new.map(|entry| entry.value.as_str()),
+        ..Default::default()
+    }
+}
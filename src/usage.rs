@@ -0,0 +1,213 @@
+//! Usage of the keys: how many times and when they were used, and
+//! when they were created and last changed.
+//!
+//! The usage is saved in a sidecar file next to the store, like
+//! `~/.slate.usage`, so the store itself is only written when its
+//! contents change. Usage is a nice to have: if the sidecar cannot be
+//! read or written (for example, in a read-only directory) it is
+//! ignored and the commands work as usual.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use config::Config;
+use sidecar;
+use store::Contents;
+
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+
+/// How a key has been used.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Usage {
+    /// Number of times the key has been used.
+    #[serde(default)]
+    pub count: u64,
+    /// Last time the key was used, in seconds since the Unix epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub used: Option<u64>,
+    /// Last time the value was changed, in seconds since the Unix epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated: Option<u64>,
+    /// When the key was created, in seconds since the Unix epoch.
+    /// It is unknown for keys created while usage wasn't recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<u64>,
+}
+
+impl Usage {
+    /// Score that combines how often and how recently a key was used.
+    ///
+    /// Every use counts, but recent uses count more.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use slate::usage::Usage;
+    ///
+    /// let old = Usage { count: 3, used: Some(0), ..Default::default() };
+    /// let new = Usage { count: 1, used: Some(1000), ..Default::default() };
+    ///
+    /// assert!(new.frecency(1000) > old.frecency(1000 * 24 * 3600));
+    /// ```
+    pub fn frecency(&self, now: u64) -> u64 {
+        let used = match self.used {
+            Some(used) => used,
+            None => return 0,
+        };
+        let age = now.saturating_sub(used);
+        let weight = if age < 4 * DAY {
+            100
+        } else if age < 14 * DAY {
+            70
+        } else if age < 31 * DAY {
+            50
+        } else if age < 90 * DAY {
+            30
+        } else {
+            10
+        };
+
+        self.count * weight
+    }
+}
+
+/// Usage of every key.
+pub type Stats = HashMap<String, Usage>;
+
+/// Orders used to sort the keys.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Sort {
+    /// Alphabetically.
+    Name,
+    /// Most often and recently used first.
+    Frecency,
+    /// Most recently used first.
+    Recent,
+    /// Biggest values first.
+    Size,
+    /// Most recently changed first.
+    Updated,
+}
+
+impl Default for Sort {
+    fn default() -> Sort {
+        Sort::Name
+    }
+}
+
+impl FromStr for Sort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Sort, String> {
+        match s {
+            "name" => Ok(Sort::Name),
+            "frecency" => Ok(Sort::Frecency),
+            "recent" => Ok(Sort::Recent),
+            "size" => Ok(Sort::Size),
+            "updated" => Ok(Sort::Updated),
+            _ => Err(format!("Unknown sort order: {}", s)),
+        }
+    }
+}
+
+/// Path of the sidecar file with the usage of a store.
+pub fn path(config: &Config) -> PathBuf {
+    sidecar::path(config, "usage")
+}
+
+/// Read the usage of a store.
+///
+/// It is empty if the sidecar doesn't exist or cannot be read.
+pub fn read(config: &Config) -> Stats {
+    sidecar::read(&path(config))
+}
+
+/// Record a change of the value of a key, and its creation if it
+/// didn't exist before.
+pub fn changed(stats: &mut Stats, key: &str, created: bool, now: u64) {
+    let usage = stats.entry(key.to_owned()).or_insert_with(Default::default);
+    usage.updated = Some(now);
+    if created {
+        usage.created = Some(now);
+    }
+}
+
+/// Change the usage of a store, if tracking is enabled.
+///
+/// Errors are ignored, so a store can be used even if the sidecar
+/// cannot be written.
+pub fn update<F>(config: &Config, f: F)
+    where F: FnOnce(&mut Stats)
+{
+    if !config.tracking {
+        return;
+    }
+
+    let _ = sidecar::update(&path(config), f);
+}
+
+/// Sort some keys of the contents.
+///
+/// Ties, and keys without usage, are sorted by name.
+pub fn sort(keys: &mut Vec<String>, order: Sort, contents: &Contents, stats: &Stats, now: u64) {
+    let empty = Usage::default();
+    let usage = |key: &String| stats.get(key).unwrap_or(&empty);
+    let size = |key: &String| contents.get(key).map(|entry| entry.value.len()).unwrap_or(0);
+
+    keys.sort_by(|a, b| {
+        let ordering = match order {
+            Sort::Name => a.cmp(b),
+            Sort::Frecency => usage(b).frecency(now).cmp(&usage(a).frecency(now)),
+            Sort::Recent => usage(b).used.cmp(&usage(a).used),
+            Sort::Size => size(b).cmp(&size(a)),
+            Sort::Updated => usage(b).updated.cmp(&usage(a).updated),
+        };
+
+        ordering.then_with(|| a.cmp(b))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use store::Entry;
+
+    fn used(count: u64, used: u64) -> Usage {
+        Usage { count: count, used: Some(used), ..Default::default() }
+    }
+
+    #[test]
+    fn it_parses_sort_orders() {
+        assert_eq!(Ok(Sort::Frecency), "frecency".parse());
+        assert_eq!(Ok(Sort::Updated), "updated".parse());
+        assert!("random".parse::<Sort>().is_err());
+    }
+
+    #[test]
+    fn it_sorts_keys() {
+        let now = 100 * DAY;
+        let mut contents = Contents::new();
+        contents.insert("a".to_string(), Entry::new("1"));
+        contents.insert("b".to_string(), Entry::new("12345"));
+        contents.insert("c".to_string(), Entry::new("123"));
+        let mut stats = Stats::new();
+        stats.insert("a".to_string(), used(10, now - 60 * DAY));
+        stats.insert("b".to_string(), used(2, now - HOUR));
+        stats.insert("c".to_string(),
+                     Usage { updated: Some(now), ..used(1, now - 2 * HOUR) });
+
+        let sorted = |order: Sort| {
+            let mut keys = vec!["c".to_string(), "b".to_string(), "a".to_string()];
+            sort(&mut keys, order, &contents, &stats, now);
+            keys
+        };
+
+        assert_eq!(vec!["a", "b", "c"], sorted(Sort::Name));
+        assert_eq!(vec!["a", "b", "c"], sorted(Sort::Frecency));
+        assert_eq!(vec!["b", "c", "a"], sorted(Sort::Recent));
+        assert_eq!(vec!["b", "c", "a"], sorted(Sort::Size));
+        assert_eq!(vec!["c", "a", "b"], sorted(Sort::Updated));
+    }
+}
@@ -0,0 +1,194 @@
+//! Subscriptions to the changes of a store.
+//!
+//! The store file is checked periodically. Every time it changes
+//! the contents are loaded again and compared with the previous
+//! ones, so changes made by any program are seen.
+
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use config::Config;
+use results::SlateResult;
+use store::{self, Contents};
+
+/// How often the store file is checked for changes.
+pub const POLL_INTERVAL_MS: u64 = 500;
+
+/// What happened to a key.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    Added,
+    Modified,
+    Removed,
+}
+
+impl Kind {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Kind::Added => "added",
+            Kind::Modified => "modified",
+            Kind::Removed => "removed",
+        }
+    }
+}
+
+/// A change in a key of the store.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Event {
+    pub kind: Kind,
+    pub key: String,
+}
+
+/// Get the changes between two versions of the contents, sorted by
+/// key. Only keys that start with the prefix are considered.
+pub fn diff(old: &Contents, new: &Contents, prefix: &str) -> Vec<Event> {
+    let mut events: Vec<Event> = Vec::new();
+
+    for (key, value) in new.iter().filter(|&(key, _)| key.starts_with(prefix)) {
+        let kind = match old.get(key) {
+            None => Kind::Added,
+            Some(previous) if previous != value => Kind::Modified,
+            Some(_) => continue,
+        };
+        events.push(Event {
+            kind: kind,
+            key: key.clone(),
+        });
+    }
+    for key in old.keys().filter(|key| key.starts_with(prefix) && !new.contains_key(*key)) {
+        events.push(Event {
+            kind: Kind::Removed,
+            key: key.clone(),
+        });
+    }
+    events.sort_by(|a, b| a.key.cmp(&b.key));
+
+    events
+}
+
+/// A subscription to the changes of a store.
+///
+/// It can be polled for changes, or used as a blocking iterator
+/// of events.
+pub struct Subscription {
+    config: Config,
+    prefix: String,
+    contents: Contents,
+    stamp: Option<(SystemTime, u64)>,
+    pending: Vec<Event>,
+    interval: Duration,
+}
+
+impl Subscription {
+    /// Start watching the keys that start with the prefix, from the
+    /// current contents of the store.
+    pub fn new(config: &Config, prefix: &str) -> SlateResult<Subscription> {
+        let stamp = stamp(&config.filepath);
+        let contents = try!(try!(store::open(config)).read());
+
+        Ok(Subscription {
+            config: config.clone(),
+            prefix: prefix.to_owned(),
+            contents: contents,
+            stamp: stamp,
+            pending: Vec::new(),
+            interval: Duration::from_millis(POLL_INTERVAL_MS),
+        })
+    }
+
+    /// Get the changes since the last time, without waiting.
+    pub fn poll(&mut self) -> SlateResult<Vec<Event>> {
+        let current = stamp(&self.config.filepath);
+        if current == self.stamp {
+            return Ok(Vec::new());
+        }
+
+        let contents = try!(try!(store::open(&self.config)).read());
+        let events = diff(&self.contents, &contents, &self.prefix);
+        self.stamp = current;
+        self.contents = contents;
+
+        Ok(events)
+    }
+}
+
+impl Iterator for Subscription {
+    type Item = SlateResult<Event>;
+
+    /// Wait for the next change.
+    fn next(&mut self) -> Option<SlateResult<Event>> {
+        while self.pending.is_empty() {
+            match self.poll() {
+                Ok(events) => self.pending = events.into_iter().rev().collect(),
+                Err(e) => return Some(Err(e)),
+            }
+            if self.pending.is_empty() {
+                thread::sleep(self.interval);
+            }
+        }
+
+        self.pending.pop().map(Ok)
+    }
+}
+
+/// Modification time and size of a file, if it exists.
+fn stamp(path: &PathBuf) -> Option<(SystemTime, u64)> {
+    fs::metadata(path).ok().and_then(|m| m.modified().ok().map(|time| (time, m.len())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::env;
+    use rand::{thread_rng, Rng};
+    use store::Entry;
+    use Slate;
+
+    fn contents(pairs: &[(&str, &str)]) -> Contents {
+        pairs.iter().map(|&(k, v)| (k.to_string(), Entry::new(v))).collect()
+    }
+
+    fn event(kind: Kind, key: &str) -> Event {
+        Event {
+            kind: kind,
+            key: key.to_string(),
+        }
+    }
+
+    #[test]
+    fn it_diffs_contents() {
+        let old = contents(&[("a", "1"), ("b", "2"), ("c", "3"), ("x", "0")]);
+        let new = contents(&[("a", "1"), ("b", "changed"), ("d", "4"), ("y", "0")]);
+
+        assert_eq!(vec![event(Kind::Modified, "b"),
+                        event(Kind::Removed, "c"),
+                        event(Kind::Added, "d"),
+                        event(Kind::Removed, "x"),
+                        event(Kind::Added, "y")],
+                   diff(&old, &new, ""));
+        assert_eq!(vec![event(Kind::Removed, "x")], diff(&old, &new, "x"));
+        assert!(diff(&HashMap::new(), &HashMap::new(), "").is_empty());
+    }
+
+    #[test]
+    fn it_polls_changes() {
+        let random_name: String = thread_rng().gen_ascii_chars().take(10).collect();
+        let mut filepath = env::temp_dir();
+        filepath.push(random_name + ".json");
+        let config = Config { filepath: filepath, ..Default::default() };
+        let slate: Slate = From::from(&config);
+        slate.set(&"a".to_string(), &"1".to_string()).unwrap();
+
+        let mut subscription = slate.subscribe("").unwrap();
+        assert!(subscription.poll().unwrap().is_empty());
+
+        slate.set(&"b".to_string(), &"22".to_string()).unwrap();
+        slate.remove(&"a".to_string()).unwrap();
+
+        assert_eq!(Some(event(Kind::Removed, "a")), subscription.next().map(|e| e.unwrap()));
+        assert_eq!(Some(event(Kind::Added, "b")), subscription.next().map(|e| e.unwrap()));
+    }
+}
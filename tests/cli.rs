@@ -0,0 +1,819 @@
+extern crate rand;
+extern crate slate;
+
+use std::env;
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::PathBuf;
+use rand::{thread_rng, Rng};
+use slate::cli::{self, Streams};
+use slate::config::{Backend, Config};
+use slate::store::Entry;
+use slate::Slate;
+
+/// Result of running the program: exit code, stdout and stderr.
+struct Output {
+    code: i32,
+    stdout: String,
+    stderr: String,
+}
+
+fn temp_path(extension: &str) -> PathBuf {
+    let random_name: String = thread_rng().gen_ascii_chars().take(10).collect();
+    let mut temp = env::temp_dir();
+    temp.push(random_name + extension);
+
+    temp
+}
+
+fn json_config() -> Config {
+    Config {
+        filepath: temp_path(".json"),
+        backend: Backend::Json,
+        ..Default::default()
+    }
+}
+
+fn sqlite_config() -> Config {
+    Config {
+        filepath: temp_path(".db"),
+        backend: Backend::Sqlite,
+        ..Default::default()
+    }
+}
+
+fn slate_with_input(config: &Config, args: &[&str], input: &str) -> Output {
+    let mut argv = vec!["slate".to_string()];
+    argv.extend(args.iter().map(|arg| arg.to_string()));
+
+    let mut stdin = input.as_bytes();
+    let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+    let code = cli::run(argv,
+                        config,
+                        &mut Streams {
+                            stdin: &mut stdin,
+                            stdout: &mut stdout,
+                            stderr: &mut stderr,
+                            terminal: false,
+                            inherit_stdin: false,
+                        });
+
+    Output {
+        code: code,
+        stdout: String::from_utf8(stdout).unwrap(),
+        stderr: String::from_utf8(stderr).unwrap(),
+    }
+}
+
+fn slate(config: &Config, args: &[&str]) -> Output {
+    slate_with_input(config, args, "")
+}
+
+fn assert_success(output: &Output) {
+    assert!(output.code == 0,
+            "Expected success, got {}: {}",
+            output.code,
+            output.stderr);
+}
+
+#[test]
+fn it_sets_and_gets_values() {
+    for config in &[json_config(), sqlite_config()] {
+        assert_success(&slate(config, &["set", "foo", "bar"]));
+
+        let output = slate(config, &["get", "foo"]);
+        assert_success(&output);
+        assert_eq!("bar\n", output.stdout);
+
+        let output = slate(config, &["get", "--no-eol", "foo"]);
+        assert_eq!("bar", output.stdout);
+    }
+}
+
+#[test]
+fn it_sets_values_from_stdin() {
+    let config = json_config();
+
+    assert_success(&slate_with_input(&config, &["set", "conf"], "a: 1\nb: 2\n"));
+
+    let output = slate(&config, &["get", "-n", "conf"]);
+    assert_eq!("a: 1\nb: 2\n", output.stdout);
+}
+
+#[test]
+fn it_fails_to_get_missing_keys() {
+    let config = json_config();
+    slate(&config, &["set", "redis", "daemonize yes"]);
+
+    let output = slate(&config, &["get", "rdis"]);
+    assert_eq!(2, output.code);
+    assert_eq!("", output.stdout);
+    assert_eq!("The key rdis doesn't exist. Did you mean redis?\n", output.stderr);
+
+    let output = slate(&config, &["get", "--default", "none", "rdis"]);
+    assert_success(&output);
+    assert_eq!("none\n", output.stdout);
+}
+
+#[test]
+fn it_lists_keys() {
+    for config in &[json_config(), sqlite_config()] {
+        slate(config, &["set", "b", "2"]);
+        slate(config, &["set", "a", "1"]);
+
+        let output = slate(config, &["list"]);
+        assert_success(&output);
+        assert_eq!("a\nb\n", output.stdout);
+    }
+}
+
+#[test]
+fn it_removes_keys() {
+    let config = json_config();
+    slate(&config, &["set", "a", "1"]);
+    slate(&config, &["set", "b", "2"]);
+
+    let output = slate(&config, &["remove", "a"]);
+    assert_success(&output);
+    assert_eq!("The key has been removed\n", output.stdout);
+    assert_eq!("b\n", slate(&config, &["list"]).stdout);
+
+    let output = slate(&config, &["remove", "--all"]);
+    assert_success(&output);
+    assert_eq!("All keys have been removed\n", output.stdout);
+    assert_eq!("\n", slate(&config, &["list"]).stdout);
+}
+
+#[test]
+fn it_renames_keys() {
+    for config in &[json_config(), sqlite_config()] {
+        slate(config, &["set", "a", "1"]);
+        slate(config, &["set", "b", "2"]);
+
+        let output = slate(config, &["rename", "a", "b"]);
+        assert_eq!(3, output.code);
+        assert_eq!("The key b already exists\n", output.stderr);
+
+        let output = slate(config, &["rename", "missing", "c"]);
+        assert_eq!(2, output.code);
+
+        let output = slate(config, &["rename", "--force", "a", "b"]);
+        assert_success(&output);
+        assert_eq!("The key has been renamed\n", output.stdout);
+        assert_eq!("1\n", slate(config, &["get", "b"]).stdout);
+    }
+}
+
+#[test]
+fn it_searches_values() {
+    for config in &[json_config(), sqlite_config()] {
+        slate(config, &["set", "redis", "daemonize yes"]);
+        slate(config, &["set", "nginx", "daemon off;"]);
+        slate(config, &["set", "other", "nothing"]);
+
+        let output = slate(config, &["search", "daemon"]);
+        assert_success(&output);
+        assert_eq!("nginx\nredis\n", output.stdout);
+    }
+}
+
+#[test]
+fn it_migrates_stores() {
+    let config = json_config();
+    let destination = temp_path(".db");
+    slate(&config, &["set", "a", "1"]);
+
+    let output = slate(&config,
+                       &["migrate", "--to", "sqlite", destination.to_str().unwrap()]);
+    assert_success(&output);
+    assert_eq!(format!("1 keys migrated to {}\n", destination.display()),
+               output.stdout);
+
+    let migrated = Config {
+        filepath: destination.clone(),
+        backend: Backend::Sqlite,
+        ..Default::default()
+    };
+    assert_eq!("1\n", slate(&migrated, &["get", "a"]).stdout);
+
+    let output = slate(&config,
+                       &["migrate", "--to", "sqlite", destination.to_str().unwrap()]);
+    assert_eq!(1, output.code);
+}
+
+#[test]
+fn it_applies_batches() {
+    for config in &[json_config(), sqlite_config()] {
+        let input = "set a 1\nset b two words\n# comment\nrename a c\nremove b\n";
+
+        let output = slate_with_input(config, &["batch"], input);
+        assert_success(&output);
+        assert_eq!("4 operations applied\n", output.stdout);
+        assert_eq!("c\n", slate(config, &["list"]).stdout);
+
+        let output = slate_with_input(config, &["batch"], "set d 4\nrename a e\n");
+        assert_eq!(2, output.code);
+        assert_eq!("c\n", slate(config, &["list"]).stdout);
+    }
+}
+
+#[test]
+fn it_shows_json_output() {
+    let config = json_config();
+
+    let output = slate(&config, &["--output", "json", "set", "foo", "bar"]);
+    assert_eq!("{\"action\":\"set\",\"key\":\"foo\"}\n", output.stdout);
+
+    let output = slate(&config, &["--output", "json", "get", "foo"]);
+    assert_eq!("{\"key\":\"foo\",\"value\":\"bar\"}\n", output.stdout);
+
+    let output = slate(&config, &["--output", "ndjson", "list"]);
+    assert_eq!("{\"key\":\"foo\"}\n", output.stdout);
+
+    let output = slate(&config, &["-o", "json", "get", "missing"]);
+    assert_eq!(2, output.code);
+    assert_eq!("{\"error\":{\"code\":\"not_found\",\"message\":\"The key missing doesn't \
+                exist\"}}\n",
+               output.stderr);
+}
+
+#[test]
+fn it_reports_corrupted_stores() {
+    let config = json_config();
+    File::create(&config.filepath).unwrap().write_all(b"not json").unwrap();
+
+    let output = slate(&config, &["list"]);
+    assert_eq!(4, output.code);
+}
+
+#[test]
+fn it_shows_help_and_usage_errors() {
+    let config = json_config();
+
+    let output = slate(&config, &["--help"]);
+    assert_success(&output);
+    assert!(output.stdout.starts_with("Slate: Manage your snippets"));
+
+    let output = slate(&config, &["get", "--help"]);
+    assert_success(&output);
+    assert!(output.stdout.contains("slate get"));
+
+    let output = slate(&config, &["--version"]);
+    assert_success(&output);
+
+    let output = slate(&config, &["get"]);
+    assert_eq!(1, output.code);
+    assert!(output.stderr.contains("Usage:"));
+
+    let output = slate(&config, &["unknown"]);
+    assert_eq!(1, output.code);
+
+    let output = slate(&config, &["--output", "xml", "list"]);
+    assert_eq!(1, output.code);
+}
+
+#[test]
+fn it_prints_completion_scripts() {
+    let config = json_config();
+
+    for shell in &["bash", "zsh", "fish"] {
+        let output = slate(&config, &["completions", shell]);
+        assert_success(&output);
+        assert!(output.stdout.contains("rename"));
+        assert!(output.stdout.contains("no-eol"));
+        assert!(output.stdout.contains("slate __complete"));
+    }
+
+    let output = slate(&config, &["completions", "tcsh"]);
+    assert_eq!(1, output.code);
+}
+
+#[test]
+fn it_lists_keys_for_completions() {
+    let config = json_config();
+    slate(&config, &["set", "b", "2"]);
+    slate(&config, &["set", "a", "1"]);
+
+    let output = slate(&config, &["__complete", "get"]);
+    assert_success(&output);
+    assert_eq!("a\nb\n", output.stdout);
+}
+
+#[cfg(unix)]
+#[test]
+fn it_uses_the_daemon_when_running() {
+    use std::thread;
+    use std::time::Duration;
+    use slate::daemon;
+
+    // The daemon serves another file on the socket of this store, so
+    // changes only land there if the commands go through it.
+    let config = json_config();
+    let served = json_config();
+    let socket = daemon::socket_path(&config);
+    slate(&served, &["set", "a", "1"]);
+
+    {
+        let served = served.clone();
+        let socket = socket.clone();
+        thread::spawn(move || daemon::serve(&served, &socket, None, None));
+    }
+    while !daemon::is_running(&socket) {
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_success(&slate(&config, &["set", "b", "2"]));
+    assert_success(&slate(&config, &["rename", "a", "c"]));
+    assert_eq!("b\nc\n", slate(&config, &["list"]).stdout);
+    assert_eq!(2, slate(&config, &["get", "a"]).code);
+    assert_eq!(3, slate(&config, &["rename", "b", "c"]).code);
+
+    assert!(!config.filepath.exists());
+}
+
+#[test]
+fn it_expires_keys() {
+    for config in &[json_config(), sqlite_config()] {
+        assert_success(&slate(config, &["set", "--ttl", "2h", "token", "abc"]));
+        slate(config, &["set", "plain", "1"]);
+
+        let output = slate(config, &["list", "--expiring"]);
+        assert_success(&output);
+        assert!(output.stdout.starts_with("token  "), "{}", output.stdout);
+        assert!(output.stdout.contains("(in 1h 59m)") || output.stdout.contains("(in 2h)"),
+                "{}",
+                output.stdout);
+
+        let output = slate(config, &["set", "--expires", "2000-01-01", "old", "x"]);
+        assert_eq!(1, output.code);
+        assert_eq!("The expiration date is in the past\n", output.stderr);
+    }
+}
+
+#[test]
+fn it_removes_expired_keys() {
+    let config = json_config();
+    File::create(&config.filepath)
+        .unwrap()
+        .write_all(b"{\"a\":\"1\",\"old\":{\"value\":\"x\",\"expires\":1}}")
+        .unwrap();
+
+    assert_eq!(2, slate(&config, &["get", "old"]).code);
+    assert_eq!("a\n", slate(&config, &["list"]).stdout);
+
+    let output = slate(&config, &["gc"]);
+    assert_success(&output);
+    assert_eq!("1 expired keys removed\n", output.stdout);
+}
+
+#[test]
+fn it_sorts_keys_by_usage() {
+    let config = json_config();
+    slate(&config, &["set", "a", "1"]);
+    slate(&config, &["set", "b", "22"]);
+    slate(&config, &["set", "c", "333"]);
+    slate(&config, &["get", "b"]);
+    slate(&config, &["get", "b"]);
+    slate(&config, &["get", "c"]);
+
+    assert_eq!("b\nc\na\n", slate(&config, &["list", "--sort", "frecency"]).stdout);
+    assert_eq!("c\nb\na\n", slate(&config, &["list", "--sort", "size"]).stdout);
+    assert_eq!("b\n", slate(&config, &["recent", "-n", "1"]).stdout);
+
+    let output = slate(&config, &["list", "--sort", "random"]);
+    assert_eq!(1, output.code);
+    assert_eq!("Unknown sort order: random\n", output.stderr);
+}
+
+#[test]
+fn it_highlights_values() {
+    let config = json_config();
+    slate(&config, &["set", "loop", "for f in *; do echo $f; done"]);
+    slate(&config, &["set", "--lang", "SQL", "query", "select 1"]);
+
+    assert_eq!("for f in *; do echo $f; done\n", slate(&config, &["get", "loop"]).stdout);
+    assert_eq!("select 1\n",
+               slate(&config, &["--color", "never", "get", "query"]).stdout);
+    assert_eq!("\x1b[35mselect\x1b[0m \x1b[33m1\x1b[0m\n",
+               slate(&config, &["--color", "always", "get", "query"]).stdout);
+
+    let output = slate(&config, &["--color", "always", "get", "loop"]);
+    assert!(output.stdout.contains("\x1b[36m$f\x1b[0m"), "{}", output.stdout);
+
+    let output = slate(&config, &["--output", "json", "get", "query"]);
+    assert_eq!("{\"key\":\"query\",\"value\":\"select 1\",\"language\":\"sql\"}\n",
+               output.stdout);
+
+    let output = slate(&config, &["--color", "rainbow", "get", "query"]);
+    assert_eq!(1, output.code);
+    assert_eq!("Unknown color mode: rainbow\n", output.stderr);
+
+    let config = Config { theme: "keyword=pink".to_string(), ..config };
+    let output = slate(&config, &["--color", "always", "get", "query"]);
+    assert_success(&output);
+    assert_eq!("\x1b[35mselect\x1b[0m \x1b[33m1\x1b[0m\n", output.stdout);
+    assert!(output.stderr.starts_with("Warning: "), "{}", output.stderr);
+}
+
+#[test]
+fn it_shows_keys_with_details() {
+    let config = json_config();
+    slate(&config,
+          &["set", "--tags", "k8s, ops,k8s", "--description", "All pods", "pods", "kubectl get pods"]);
+    slate(&config, &["set", "--tags", "k8s", "pods", "kubectl get pods -A\n# all namespaces"]);
+
+    let output = slate(&config, &["show", "pods"]);
+    assert_success(&output);
+    let lines: Vec<&str> = output.stdout.lines().collect();
+    assert_eq!("Key:          pods", lines[0]);
+    assert_eq!("Tags:         k8s", lines[1]);
+    assert_eq!("Description:  -", lines[2]);
+    assert_eq!("Language:     shell", lines[3]);
+    assert_eq!("Size:         36 bytes, 2 lines", lines[4]);
+    assert!(lines[5].starts_with("Created:      20"), "{}", lines[5]);
+    assert_eq!("Revisions:    2", lines[7]);
+    assert_eq!(vec!["1 | kubectl get pods -A", "2 | # all namespaces"], &lines[9..]);
+
+    let output = slate(&config, &["--output", "json", "show", "pods"]);
+    assert!(output.stdout.contains("\"tags\":[\"k8s\"]"), "{}", output.stdout);
+    assert!(output.stdout.contains("\"revisions\":2"), "{}", output.stdout);
+    assert!(output.stdout.contains("\"created\":"), "{}", output.stdout);
+
+    assert_eq!("kubectl get pods -A\n# all namespaces\n",
+               slate(&config, &["get", "pods"]).stdout);
+    assert_eq!(2, slate(&config, &["show", "missing"]).code);
+}
+
+#[test]
+fn it_copies_keys() {
+    for config in &[json_config(), sqlite_config()] {
+        slate(config, &["set", "--tags", "k8s", "k8s-pods", "kubectl get pods"]);
+        slate(config, &["set", "k8s-nodes", "kubectl get nodes"]);
+        slate(config, &["set", "other", "1"]);
+
+        let output = slate(config, &["copy", "k8s-pods", "pods"]);
+        assert_success(&output);
+        assert_eq!("The key has been copied\n", output.stdout);
+        assert!(slate(config, &["show", "pods"]).stdout.contains("Tags:         k8s\n"));
+        assert_eq!(3, slate(config, &["copy", "other", "pods"]).code);
+        assert_eq!(2, slate(config, &["copy", "missing", "pods"]).code);
+
+        let output = slate(config, &["copy", "k8s-*", "old/"]);
+        assert_success(&output);
+        assert_eq!("2 keys copied\n", output.stdout);
+
+        let output = slate(config, &["copy", "k8s-*", "old/"]);
+        assert_eq!(3, output.code);
+        assert_eq!("These keys already exist: old/k8s-nodes, old/k8s-pods. \
+                    Use --force to overwrite them\n",
+                   output.stderr);
+
+        assert_eq!("k8s-nodes\nk8s-pods\nold/k8s-nodes\nold/k8s-pods\nother\npods\n",
+                   slate(config, &["list"]).stdout);
+    }
+}
+
+#[test]
+fn it_moves_keys_to_other_stores() {
+    let config = json_config();
+    let other = sqlite_config();
+    let path = format!("{}", other.filepath.display());
+    slate(&config, &["set", "vpn", "openvpn work.ovpn"]);
+    slate(&config, &["set", "--description", "VPN", "vpn", "sudo openvpn work.ovpn"]);
+    slate(&config, &["set", "k8s-pods", "kubectl get pods"]);
+    slate(&other, &["set", "k8s-pods", "kubectl get pods -A"]);
+
+    let output = slate(&config, &["move", "--to-store", &path, "vpn", "k8s-*"]);
+    assert_eq!(3, output.code);
+    assert_eq!("These keys already exist: k8s-pods. Use --force to overwrite them\n",
+               output.stderr);
+    assert_eq!("k8s-pods\nvpn\n", slate(&config, &["list"]).stdout);
+
+    let output = slate(&config, &["move", "--force", "--to-store", &path, "vpn", "k8s-*"]);
+    assert_success(&output);
+    assert_eq!(format!("2 keys moved to {}\n", path), output.stdout);
+    assert_eq!("", slate(&config, &["list"]).stdout.trim());
+
+    let output = slate(&other, &["show", "vpn"]);
+    assert!(output.stdout.contains("Description:  VPN\n"), "{}", output.stdout);
+    assert!(output.stdout.contains("Revisions:    2\n"), "{}", output.stdout);
+    assert_eq!("kubectl get pods\n", slate(&other, &["get", "k8s-pods"]).stdout);
+
+    assert_eq!(2, slate(&config, &["move", "--to-store", &path, "vpn"]).code);
+}
+
+#[test]
+fn it_copies_keys_to_other_profiles() {
+    let dir = temp_path("");
+    std::fs::create_dir(&dir).unwrap();
+    let config = Config { filepath: dir.join(".slate"), ..json_config() };
+    slate(&config, &["set", "vpn", "openvpn work.ovpn"]);
+
+    let output = slate(&config, &["copy", "--to-profile", "work", "vpn"]);
+    assert_success(&output);
+    assert_eq!("1 key copied to the profile work\n", output.stdout);
+    assert_eq!("vpn\n", slate(&config, &["list"]).stdout);
+
+    let work = config.for_profile("work");
+    assert_eq!(dir.join(".slate-work"), work.filepath);
+    assert_eq!("openvpn work.ovpn\n", slate(&work, &["get", "vpn"]).stdout);
+    assert_eq!(1, slate(&config, &["move", "--to-profile", "default", "vpn"]).code);
+}
+
+#[test]
+fn it_appends_prepends_and_replaces_lines() {
+    for config in &[json_config(), sqlite_config()] {
+        assert_success(&slate(config, &["append", "hosts", "web-1"]));
+        assert_success(&slate_with_input(config, &["append", "hosts"], "web-2\n"));
+        assert_success(&slate(config, &["prepend", "hosts", "# Hosts"]));
+        assert_eq!("# Hosts\nweb-1\nweb-2\n", slate(config, &["get", "hosts"]).stdout);
+
+        assert_success(&slate(config, &["set", "--line", "2", "hosts", "web-01"]));
+        assert_success(&slate_with_input(config, &["set", "--line", "1", "hosts"], "# All\n"));
+        assert_eq!("# All\nweb-01\nweb-2\n", slate(config, &["get", "hosts"]).stdout);
+
+        let output = slate(config, &["set", "--line", "4", "hosts", "web-3"]);
+        assert_eq!(2, output.code);
+        assert_eq!("The value of hosts has no line 4\n", output.stderr);
+        assert_eq!(1, slate(config, &["set", "--line", "1", "--tags", "a", "hosts", "x"]).code);
+    }
+}
+
+#[test]
+#[cfg(unix)]
+fn it_saves_and_restores_files() {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    for config in &[json_config(), sqlite_config()] {
+        let dir = temp_path("");
+        fs::create_dir(&dir).unwrap();
+        let file = dir.join("keystore.jks");
+        let bytes: Vec<u8> = vec![0xfe, 0xed, 0xfe, 0xed, 0, 1, 0xff];
+        fs::write(&file, &bytes).unwrap();
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o600)).unwrap();
+        let path = format!("{}", file.display());
+
+        assert_success(&slate(config, &["set", "--file", &path, "keystore"]));
+        assert_eq!(1, slate(config, &["set", "--file", &path, "keystore", "value"]).code);
+
+        let output = slate(config, &["show", "keystore"]);
+        assert!(output.stdout.contains("Size:         7 bytes, binary\n"), "{}", output.stdout);
+        assert!(output.stdout.contains("File:         keystore.jks (mode 600)\n"),
+                "{}",
+                output.stdout);
+
+        let output = slate(config, &["--output", "json", "get", "keystore"]);
+        assert_eq!("{\"key\":\"keystore\",\"value\":\"/u3+7QAB/w==\",\"encoding\":\"base64\"}\n",
+                   output.stdout);
+
+        fs::remove_file(&file).unwrap();
+        let output = slate(config, &["get", "--output", &format!("{}", dir.display()), "keystore"]);
+        assert_success(&output);
+        assert_eq!(format!("The value has been saved in {}\n", path), output.stdout);
+        assert_eq!(bytes, fs::read(&file).unwrap());
+        assert_eq!(0o600, fs::metadata(&file).unwrap().permissions().mode() & 0o7777);
+        let output = slate(config, &["get", "--output", &path, "keystore"]);
+        assert_eq!(1, output.code);
+        assert!(output.stderr.contains("already exists"), "{}", output.stderr);
+        fs::write(&file, "old").unwrap();
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o644)).unwrap();
+        assert_success(&slate(config, &["get", "--force", "--output", &path, "keystore"]));
+        assert_eq!(bytes, fs::read(&file).unwrap());
+        assert_eq!(0o600, fs::metadata(&file).unwrap().permissions().mode() & 0o7777);
+
+        // Only the name of the saved file is used, without the special bits.
+        let entry = Entry {
+            filename: Some("../escaped".to_string()),
+            mode: Some(0o4755),
+            ..Entry::new("x")
+        };
+        Slate::from(config).set_entry(&"odd".to_string(), &entry).unwrap();
+        assert_success(&slate(config, &["get", "--output", &format!("{}", dir.display()), "odd"]));
+        let saved = dir.join("escaped");
+        assert_eq!(0o755, fs::metadata(&saved).unwrap().permissions().mode() & 0o7777);
+        assert!(!dir.parent().unwrap().join("escaped").exists());
+    }
+}
+
+#[test]
+fn it_saves_binary_input() {
+    let config = json_config();
+    let mut argv = vec!["slate".to_string(), "set".to_string(), "bin".to_string()];
+    let mut stdin: &[u8] = &[0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a];
+    let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+    let code = cli::run(argv.clone(),
+                        &config,
+                        &mut Streams {
+                            stdin: &mut stdin,
+                            stdout: &mut stdout,
+                            stderr: &mut stderr,
+                            terminal: false,
+                            inherit_stdin: false,
+                        });
+    assert_eq!(0, code);
+
+    argv[1] = "get".to_string();
+    let code = cli::run(argv,
+                        &config,
+                        &mut Streams {
+                            stdin: &mut "".as_bytes(),
+                            stdout: &mut stdout,
+                            stderr: &mut stderr,
+                            terminal: false,
+                            inherit_stdin: false,
+                        });
+    assert_eq!(0, code);
+    assert_eq!(vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a], stdout);
+}
+
+#[test]
+fn it_links_directories() {
+    let config = json_config();
+    let dir = temp_path("");
+    std::fs::create_dir_all(dir.join("deploy")).unwrap();
+    std::fs::write(dir.join("deploy/prod.sh"), "make deploy ENV=prod").unwrap();
+    std::fs::write(dir.join("hosts"), "web-1").unwrap();
+    let path = format!("{}", dir.canonicalize().unwrap().display());
+    let name = dir.file_name().unwrap().to_string_lossy().into_owned();
+    slate(&config, &["set", "deploy-staging", "make deploy ENV=staging"]);
+
+    let output = slate(&config, &["link", &path]);
+    assert_success(&output);
+    assert_eq!(format!("Linked {} with 2 keys\n", path), output.stdout);
+    assert_eq!(format!("{}  (2 keys)\n", path), slate(&config, &["link"]).stdout);
+
+    assert_eq!(format!("deploy-staging\ndeploy/prod  [{0}]\nhosts  [{0}]\n", name),
+               slate(&config, &["list"]).stdout);
+    assert_eq!("make deploy ENV=prod\n", slate(&config, &["get", "deploy/prod"]).stdout);
+    assert_eq!(format!("deploy-staging\ndeploy/prod  [{}]\n", name),
+               slate(&config, &["search", "make deploy"]).stdout);
+    assert_eq!(format!("deploy-staging\ndeploy/prod  [{}]\n", name),
+               slate(&config, &["find", "DEPLOY"]).stdout);
+    assert_eq!(format!("hosts  [{}]\n", name), slate(&config, &["find", "h*"]).stdout);
+
+    let output = slate(&config, &["remove", "hosts"]);
+    assert_eq!(1, output.code);
+    assert_eq!(format!("The key hosts is read-only, it is linked from {}\n", path),
+               output.stderr);
+
+    assert_success(&slate(&config, &["link", "--remove", &path]));
+    assert_eq!("deploy-staging\n", slate(&config, &["list"]).stdout);
+}
+
+#[test]
+fn it_shows_stats() {
+    let config = json_config();
+    slate(&config, &["set", "--tags", "k8s", "k8s/pods", "kubectl get pods"]);
+    slate(&config, &["set", "pods", "kubectl get pods"]);
+    slate(&config, &["set", "vpn", "openvpn work.ovpn"]);
+
+    let output = slate(&config, &["stats", "--top", "1"]);
+    assert_success(&output);
+    assert!(output.stdout.starts_with("Keys:        3\nSize:        49 bytes\n\
+                                       Largest:     vpn (17 bytes)\n\
+                                       Namespaces:  k8s (1)\nTags:        k8s (1)\n\
+                                       Stale:       -\nDuplicates:  k8s/pods = pods\n"),
+            "{}",
+            output.stdout);
+}
+
+#[cfg(unix)]
+#[test]
+fn it_checks_and_fixes_stores() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let config = json_config();
+    slate(&config, &["set", "vpn", "openvpn work.ovpn"]);
+    let path = config.filepath.display();
+    std::fs::set_permissions(&config.filepath, std::fs::Permissions::from_mode(0o600)).unwrap();
+    std::fs::set_permissions(&slate::history::path(&config),
+                             std::fs::Permissions::from_mode(0o600))
+        .unwrap();
+    assert_eq!("No problems found\n", slate(&config, &["doctor"]).stdout);
+
+    std::fs::set_permissions(&config.filepath, std::fs::Permissions::from_mode(0o644)).unwrap();
+    let output = slate(&config, &["doctor"]);
+    assert_eq!(8, output.code);
+    assert_eq!(format!("permissions: {} can be read by other users (mode 644)\n", path),
+               output.stdout);
+    assert_eq!("Found 1 problem, 1 can be fixed with --fix\n", output.stderr);
+
+    assert_success(&slate(&config, &["doctor", "--fix"]));
+    slate(&config, &["set", "vpn", "sudo openvpn work.ovpn"]);
+    assert_eq!("No problems found\n", slate(&config, &["doctor"]).stdout);
+
+    std::fs::write(&config.filepath, "{\"vpn\":").unwrap();
+    let output = slate(&config, &["doctor", "--fix"]);
+    assert_eq!(8, output.code);
+    assert_eq!("Found 1 problem\n", output.stderr);
+}
+
+#[test]
+fn it_finds_and_merges_duplicates() {
+    let config = json_config();
+    slate(&config, &["set", "pods", "kubectl get pods -A"]);
+    slate(&config, &["set", "k8s/pods", "kubectl get pods -A"]);
+    slate(&config, &["set", "deploy-prod", "make deploy ENV=prod"]);
+    slate(&config, &["set", "deploy-stag", "make deploy ENV=stag"]);
+
+    assert_eq!("k8s/pods = pods\n", slate(&config, &["dedupe"]).stdout);
+    assert_eq!("k8s/pods = pods\ndeploy-prod ~ deploy-stag (80%)\n",
+               slate(&config, &["dedupe", "--similarity", "0.8"]).stdout);
+    assert_eq!(1, slate(&config, &["dedupe", "--similarity", "2"]).code);
+
+    let output = slate_with_input(&config, &["dedupe", "--merge", "-s", "0.8"], "2\nd\n\n");
+    assert_success(&output);
+    assert_eq!("1 key removed\n", output.stdout);
+    assert!(output.stderr.contains("  1) k8s/pods\n  2) pods\n"), "{}", output.stderr);
+    assert_eq!("deploy-prod\ndeploy-stag\npods\n", slate(&config, &["list"]).stdout);
+
+    let output = slate_with_input(&config, &["dedupe", "--merge", "-s", "0.8"], "1\na\n");
+    assert_eq!("1 key turned into an alias\n", output.stdout);
+    assert!(output.stderr.contains("--- deploy-prod\n+++ deploy-stag\n@@ -1 +1 @@\n-make deploy ENV=prod\n\\ No newline at \
+                                    end of file\n+make deploy ENV=stag\n\\ No newline at end of file\n"),
+            "{}",
+            output.stderr);
+    assert_eq!("deploy-prod\ndeploy-stag -> deploy-prod\npods\n",
+               slate(&config, &["list"]).stdout);
+}
+
+#[test]
+fn it_creates_aliases() {
+    let config = json_config();
+    slate(&config, &["set", "k8s/pods", "kubectl get pods -A"]);
+
+    assert_success(&slate(&config, &["alias", "pods", "k8s/pods"]));
+    assert_eq!("kubectl get pods -A\n", slate(&config, &["get", "pods"]).stdout);
+    assert_eq!("k8s/pods\npods -> k8s/pods\n", slate(&config, &["list"]).stdout);
+    assert_eq!(3, slate(&config, &["alias", "k8s/pods", "pods"]).code);
+
+    let output = slate(&config, &["alias", "--force", "k8s/pods", "pods"]);
+    assert_eq!(3, output.code);
+    assert_eq!("The aliases make a cycle: k8s/pods -> pods -> k8s/pods\n", output.stderr);
+
+    assert_success(&slate(&config, &["rename", "k8s/pods", "all-pods"]));
+    assert_eq!("all-pods\npods -> all-pods\n", slate(&config, &["list"]).stdout);
+
+    let output = slate(&config, &["remove", "all-pods"]);
+    assert_success(&output);
+    assert_eq!("Warning: these aliases point to all-pods, that doesn't exist anymore: pods\n",
+               output.stderr);
+    assert_eq!(2, slate(&config, &["get", "pods"]).code);
+}
+
+#[test]
+fn it_shows_differences() {
+    let config = json_config();
+    slate(&config, &["set", "deploy-prod", "make build\nmake deploy ENV=prod"]);
+    slate(&config, &["set", "deploy-staging", "make build\nmake deploy ENV=staging"]);
+
+    assert_eq!("--- deploy-prod\n+++ deploy-staging\n@@ -1,2 +1,2 @@\n make build\n-make deploy ENV=prod\n\\ No \
+                newline at end of file\n+make deploy ENV=staging\n\\ No newline at end of file\n",
+               slate(&config, &["diff", "deploy-prod", "deploy-staging"]).stdout);
+    assert_eq!("The values are equal\n",
+               slate(&config, &["diff", "deploy-prod", "deploy-prod"]).stdout);
+    slate_with_input(&config, &["set", "deploy-eol"], "make build\nmake deploy ENV=prod\n");
+    assert_eq!("--- deploy-prod\n+++ deploy-eol\n@@ -1,2 +1,2 @@\n make build\n-make deploy ENV=prod\n\\ No newline \
+                at end of file\n+make deploy ENV=prod\n",
+               slate(&config, &["diff", "deploy-prod", "deploy-eol"]).stdout);
+    slate(&config, &["remove", "deploy-eol"]);
+
+    slate(&config, &["set", "deploy-prod", "make deploy ENV=prod"]);
+    assert_eq!("--- deploy-prod (revision 1)\n+++ deploy-prod\n@@ -1,2 +1 @@\n-make build\n make deploy ENV=prod\n\\ No \
+                newline at end of file\n",
+               slate(&config, &["diff", "--rev", "1", "deploy-prod"]).stdout);
+    assert_eq!(1, slate(&config, &["diff", "--rev", "3", "deploy-prod"]).code);
+
+    let other = json_config();
+    slate(&other, &["set", "deploy-prod", "make deploy ENV=production"]);
+    slate(&other, &["set", "vpn", "sudo openvpn work.ovpn"]);
+    let path = other.filepath.to_str().unwrap();
+
+    assert_eq!("+ vpn\n- deploy-staging\n~ deploy-prod\n",
+               slate(&config, &["diff", "--store", path]).stdout);
+    assert_eq!(format!("--- deploy-prod\n+++ deploy-prod ({})\n@@ -1 +1 @@\n-make deploy ENV=prod\n\\ No newline at \
+                        end of file\n+make deploy ENV=production\n\\ No newline at end of file\n",
+                       path),
+               slate(&config, &["diff", "--store", path, "deploy-prod"]).stdout);
+    assert_eq!(1, slate(&config, &["diff", "--store", "missing.json"]).code);
+}
+
+#[test]
+#[cfg(unix)]
+fn it_runs_hooks_in_batches() {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = temp_path("");
+    fs::create_dir(&dir).unwrap();
+    let hook = dir.join("pre-set");
+    fs::write(&hook, "#!/bin/sh\necho \"checking $SLATE_KEY\"\n! grep -q TODO\n").unwrap();
+    fs::set_permissions(&hook, fs::Permissions::from_mode(0o755)).unwrap();
+    let config = Config { hooks: Some(dir), ..json_config() };
+
+    let output = slate_with_input(&config, &["batch"], "set a done\nset b TODO\n");
+    assert_eq!(7, output.code);
+    assert_eq!("checking a\nchecking b\nThe hook pre-set rejected the change\n", output.stderr);
+    assert_eq!(2, slate(&config, &["get", "a"]).code);
+}
@@ -0,0 +1,129 @@
+extern crate rand;
+extern crate slate;
+
+use std::env;
+use std::io::prelude::*;
+use std::net::{SocketAddr, TcpStream};
+use std::thread;
+use rand::{thread_rng, Rng};
+use slate::config::Config;
+use slate::http::Server;
+
+/// Status, ETag and body of a response.
+struct Response {
+    status: u16,
+    etag: Option<String>,
+    body: String,
+}
+
+fn start(token: Option<&str>) -> SocketAddr {
+    let random_name: String = thread_rng().gen_ascii_chars().take(10).collect();
+    let mut filepath = env::temp_dir();
+    filepath.push(random_name + ".json");
+    let config = Config { filepath: filepath, ..Default::default() };
+
+    let server = Server::bind(&config, "127.0.0.1:0", token.map(|t| t.to_string())).unwrap();
+    let address = server.address().unwrap();
+    thread::spawn(move || server.run());
+
+    address
+}
+
+fn request(address: SocketAddr,
+           method: &str,
+           path: &str,
+           headers: &[(&str, &str)],
+           body: &str)
+           -> Response {
+    let mut stream = TcpStream::connect(address).unwrap();
+    let mut message = format!("{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\
+                               Content-Length: {}\r\n",
+                              method,
+                              path,
+                              body.len());
+    for &(field, value) in headers {
+        message.push_str(&format!("{}: {}\r\n", field, value));
+    }
+    message.push_str("\r\n");
+    message.push_str(body);
+    stream.write_all(message.as_bytes()).unwrap();
+
+    let mut raw = String::new();
+    stream.read_to_string(&mut raw).unwrap();
+    let (head, body) = raw.split_at(raw.find("\r\n\r\n").unwrap());
+
+    let status = head[9..12].parse().unwrap();
+    let etag = head.lines()
+        .find(|line| line.to_lowercase().starts_with("etag:"))
+        .map(|line| line[5..].trim().to_string());
+
+    Response {
+        status: status,
+        etag: etag,
+        body: body[4..].to_string(),
+    }
+}
+
+#[test]
+fn it_serves_keys() {
+    let address = start(None);
+
+    assert_eq!(201, request(address, "PUT", "/keys/redis%2Fconf", &[], "daemonize yes").status);
+    assert_eq!(201, request(address, "PUT", "/keys/nginx", &[], "daemon off;").status);
+
+    let response = request(address, "GET", "/keys/redis%2Fconf", &[], "");
+    assert_eq!(200, response.status);
+    assert_eq!("daemonize yes", response.body);
+
+    let response = request(address, "GET", "/keys?prefix=red", &[], "");
+    assert_eq!("[\"redis/conf\"]", response.body);
+
+    let response = request(address, "POST", "/rename", &[], "{\"from\":\"nginx\",\"to\":\"web\"}");
+    assert_eq!(200, response.status);
+    assert_eq!(404, request(address, "GET", "/keys/nginx", &[], "").status);
+
+    assert_eq!(204, request(address, "DELETE", "/keys/web", &[], "").status);
+    assert_eq!(404, request(address, "DELETE", "/keys/web", &[], "").status);
+    assert_eq!("[\"redis/conf\"]", request(address, "GET", "/keys", &[], "").body);
+}
+
+#[test]
+fn it_uses_etags_for_concurrent_changes() {
+    let address = start(None);
+
+    let etag = request(address, "PUT", "/keys/foo", &[], "one").etag.unwrap();
+    assert_eq!(Some(etag.clone()), request(address, "GET", "/keys/foo", &[], "").etag);
+    assert_eq!(304,
+               request(address, "GET", "/keys/foo", &[("If-None-Match", &etag)], "").status);
+
+    let response = request(address, "PUT", "/keys/foo", &[("If-Match", &etag)], "two");
+    assert_eq!(200, response.status);
+
+    // Someone else changed it meanwhile.
+    let response = request(address, "PUT", "/keys/foo", &[("If-Match", &etag)], "three");
+    assert_eq!(412, response.status);
+    assert_eq!(412,
+               request(address, "DELETE", "/keys/foo", &[("If-Match", &etag)], "").status);
+    assert_eq!(412,
+               request(address, "PUT", "/keys/foo", &[("If-None-Match", "*")], "four").status);
+    assert_eq!("two", request(address, "GET", "/keys/foo", &[], "").body);
+}
+
+#[test]
+fn it_requires_the_token() {
+    let address = start(Some("secret"));
+
+    assert_eq!(401, request(address, "GET", "/keys", &[], "").status);
+    assert_eq!(401,
+               request(address, "GET", "/keys", &[("Authorization", "Bearer wrong")], "").status);
+    assert_eq!(200,
+               request(address, "GET", "/keys", &[("Authorization", "Bearer secret")], "").status);
+}
+
+#[test]
+fn it_needs_a_token_to_listen_on_other_addresses() {
+    let config = Config::default();
+
+    assert!(Server::bind(&config, "0.0.0.0:0", None).is_err());
+    assert!(Server::bind(&config, "0.0.0.0:0", Some("secret".to_string())).is_ok());
+}